@@ -1,4 +1,4 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::fmt::{Display, Formatter};
 
 #[derive(ValueEnum, Debug, Copy, Clone, Default)]
@@ -6,6 +6,10 @@ pub enum ErrorFormat {
     #[default]
     Pretty,
     Plain,
+    /// Each error as a JSON object on its own line
+    Json,
+    /// All errors as a single SARIF 2.1.0 log
+    Sarif,
 }
 
 impl Display for ErrorFormat {
@@ -13,16 +17,113 @@ impl Display for ErrorFormat {
         match self {
             ErrorFormat::Pretty => write!(f, "pretty"),
             ErrorFormat::Plain => write!(f, "plain"),
+            ErrorFormat::Json => write!(f, "json"),
+            ErrorFormat::Sarif => write!(f, "sarif"),
         }
     }
 }
 
-#[derive(Parser, Debug, Default)]
+pub const DEFAULT_FUEL: usize = 1_000_000;
+
+#[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct PrismArgs {
-    #[arg(long, default_value_t)]
+    #[command(subcommand)]
+    pub command: Command,
+
+    #[arg(long, global = true, default_value_t)]
     pub error_format: ErrorFormat,
 
-    /// Specifies the path to an input .pr file. If None, it means stdin is used for input.
-    pub input: String,
+    /// Use the inconsistent rule `Type : Type` instead of a hierarchy of universes
+    #[arg(long, global = true)]
+    pub type_in_type: bool,
+
+    /// The maximum number of reduction steps taken while evaluating or type checking a term,
+    /// after which it is reported to not terminate
+    #[arg(long, global = true, default_value_t = DEFAULT_FUEL)]
+    pub fuel: usize,
+
+    /// Apply the suggestions that are certainly correct to the input files
+    #[arg(long, global = true)]
+    pub fix: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Type checks the program, only reporting errors
+    Check {
+        /// Specifies the path to an input .pr file. If it is not given or `-`, the program is read from stdin.
+        input: Option<String>,
+    },
+    /// Prints the evaluated program
+    Eval {
+        /// Specifies the path to an input .pr file. If it is not given or `-`, the program is read from stdin.
+        input: Option<String>,
+    },
+    /// Prints the type of the program
+    Type {
+        /// Specifies the path to an input .pr file. If it is not given or `-`, the program is read from stdin.
+        input: Option<String>,
+    },
+    /// Prints the program as it is after a stage of the compiler
+    Dump {
+        #[arg(long, default_value_t)]
+        stage: DumpStage,
+
+        /// Specifies the path to an input .pr file. If it is not given or `-`, the program is read from stdin.
+        input: Option<String>,
+    },
+    /// Starts an interactive session
+    Repl,
+    /// Prints the explanation of a diagnostic
+    Explain {
+        /// The id of the diagnostic, such as `unknown_name`
+        id: String,
+    },
+}
+
+impl Command {
+    /// Returns the path of the input file, or `None` if the program is read from stdin
+    pub fn input(&self) -> Option<&str> {
+        match self {
+            Command::Check { input }
+            | Command::Eval { input }
+            | Command::Type { input }
+            | Command::Dump { input, .. } => input.as_deref().filter(|&input| input != "-"),
+            Command::Repl | Command::Explain { .. } => None,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone, Default)]
+pub enum DumpStage {
+    /// The program as it was parsed
+    Parsed,
+    /// The program after names are resolved
+    #[default]
+    Core,
+    /// The core program, with the shifts of de Bruijn indices applied
+    Simplified,
+}
+
+impl Display for DumpStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DumpStage::Parsed => write!(f, "parsed"),
+            DumpStage::Core => write!(f, "core"),
+            DumpStage::Simplified => write!(f, "simplified"),
+        }
+    }
+}
+
+impl Default for PrismArgs {
+    fn default() -> Self {
+        Self {
+            command: Command::Check { input: None },
+            error_format: Default::default(),
+            type_in_type: false,
+            fuel: DEFAULT_FUEL,
+            fix: false,
+        }
+    }
 }
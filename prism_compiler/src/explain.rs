@@ -0,0 +1,74 @@
+use crate::interp::fuel::OutOfFuel;
+use crate::lang::literal::LiteralOverflow;
+use crate::lang::modules::IncludeCycle;
+use crate::parser::FailedToRead;
+use crate::parser::check_grammar::{
+    UnknownConstructor, UnknownNamespace, WrongArgumentCount, WrongArgumentKind,
+};
+use crate::parser::data_to_checked::{
+    InvalidConstructorType, InvalidDataSignature, WrongIndexCount,
+};
+use crate::parser::imports::UnknownImport;
+use crate::parser::parse_expr::{InvalidEscape, LevelTooLarge, LiteralTooLarge, NotAGrammar};
+use crate::parser::parsed_to_checked::UnknownName;
+use crate::type_check::errors::{
+    BadInfer, ConstructorUniverse, ExpectedFn, ExpectedFnArg, ExpectedPair, ExpectedType,
+    FailedTypeAssert, InconsistentLevels, NonPositiveOccurrence, ParamatricityUnsupported,
+    RecursionLimit,
+};
+use crate::type_check::unsolved::{FoundGoal, UnsolvedFree};
+use prism_diag::explain::{Explain, Explanation};
+use prism_parser::core::adaptive::AdaptError;
+use prism_parser::error::set_error::SetError;
+
+/// The explanations of all diagnostics that can be reported, shown by `prism explain <id>`.
+/// An enum of diagnostics has one explanation for each variant.
+pub const EXPLANATIONS: &[&[Explanation]] = &[
+    // Reading and parsing files
+    FailedToRead::EXPLANATIONS,
+    IncludeCycle::EXPLANATIONS,
+    SetError::EXPLANATIONS,
+    LevelTooLarge::EXPLANATIONS,
+    LiteralTooLarge::EXPLANATIONS,
+    InvalidEscape::EXPLANATIONS,
+    // Adapting grammars
+    NotAGrammar::EXPLANATIONS,
+    AdaptError::EXPLANATIONS,
+    UnknownNamespace::EXPLANATIONS,
+    UnknownConstructor::EXPLANATIONS,
+    WrongArgumentCount::EXPLANATIONS,
+    WrongArgumentKind::EXPLANATIONS,
+    // Resolving names
+    UnknownName::EXPLANATIONS,
+    UnknownImport::EXPLANATIONS,
+    InvalidDataSignature::EXPLANATIONS,
+    InvalidConstructorType::EXPLANATIONS,
+    WrongIndexCount::EXPLANATIONS,
+    // Type checking
+    ExpectedType::EXPLANATIONS,
+    ExpectedFn::EXPLANATIONS,
+    ExpectedPair::EXPLANATIONS,
+    ExpectedFnArg::EXPLANATIONS,
+    FailedTypeAssert::EXPLANATIONS,
+    InconsistentLevels::EXPLANATIONS,
+    RecursionLimit::EXPLANATIONS,
+    BadInfer::EXPLANATIONS,
+    ConstructorUniverse::EXPLANATIONS,
+    ParamatricityUnsupported::EXPLANATIONS,
+    NonPositiveOccurrence::EXPLANATIONS,
+    UnsolvedFree::EXPLANATIONS,
+    FoundGoal::EXPLANATIONS,
+    // Evaluating
+    OutOfFuel::EXPLANATIONS,
+    LiteralOverflow::EXPLANATIONS,
+];
+
+/// Returns the explanations of all diagnostics, in the order of `EXPLANATIONS`
+pub fn explanations() -> impl Iterator<Item = &'static Explanation> {
+    EXPLANATIONS.iter().copied().flatten()
+}
+
+/// Returns the explanation of the diagnostic with id `id`
+pub fn explain(id: &str) -> Option<&'static Explanation> {
+    explanations().find(|explanation| explanation.id == id)
+}
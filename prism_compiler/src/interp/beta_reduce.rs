@@ -6,8 +6,16 @@ use std::collections::HashMap;
 
 impl PrismDb {
     pub fn beta_reduce(&mut self, i: CoreIndex, env: &DbEnv) -> CoreIndex {
-        let mut tc_env = TypecheckPrismEnv::new(self);
-        tc_env.beta_reduce_inner(i, env, &mut HashMap::new())
+        self.normalize(i, env)
+    }
+
+    /// Beta reduces `i` by repeatedly substituting and reducing the head of the term.
+    /// This allocates a new node for every step and is slower than `normalize`, it is kept as a reference to test `normalize` against.
+    pub fn beta_reduce_by_substitution(&mut self, i: CoreIndex, env: &DbEnv) -> CoreIndex {
+        self.with_fuel(|db| {
+            let mut tc_env = TypecheckPrismEnv::new(db);
+            tc_env.beta_reduce_inner(i, env, &mut HashMap::new())
+        })
     }
 }
 
@@ -22,11 +30,30 @@ impl<'a> TypecheckPrismEnv<'a> {
 
         let e_new = match self.db.checked_values[*i] {
             // Values
-            CorePrismExpr::Type | CorePrismExpr::GrammarValue(..) | CorePrismExpr::GrammarType => {
+            CorePrismExpr::GrammarValue(..)
+            | CorePrismExpr::GrammarType
+            | CorePrismExpr::DataType(..)
+            | CorePrismExpr::DataConstruct(..)
+            | CorePrismExpr::DataElim(..)
+            | CorePrismExpr::Level
+            | CorePrismExpr::LevelConst(..)
+            | CorePrismExpr::LevelOmega
+            | CorePrismExpr::LiteralType(..)
+            | CorePrismExpr::Literal(..)
+            | CorePrismExpr::PrimOp(..) => {
                 return i;
             }
+            CorePrismExpr::Type(l) => CorePrismExpr::Type(self.beta_reduce_inner(l, &s, var_map)),
+            CorePrismExpr::LevelSucc(l) => {
+                CorePrismExpr::LevelSucc(self.beta_reduce_inner(l, &s, var_map))
+            }
+            CorePrismExpr::LevelMax(a, b) => {
+                let a = self.beta_reduce_inner(a, &s, var_map);
+                let b = self.beta_reduce_inner(b, &s, var_map);
+                CorePrismExpr::LevelMax(a, b)
+            }
 
-            CorePrismExpr::Let(_, _) => unreachable!(),
+            CorePrismExpr::Let(_, _) | CorePrismExpr::Data(_, _) => unreachable!(),
             CorePrismExpr::DeBruijnIndex(v) => {
                 let EnvEntry::RType(id) = s[v] else {
                     unreachable!()
@@ -55,6 +82,29 @@ impl<'a> TypecheckPrismEnv<'a> {
                 let b = self.beta_reduce_inner(b, &s, var_map);
                 CorePrismExpr::FnDestruct(a, b)
             }
+            CorePrismExpr::PairType(a, b) => {
+                let a = self.beta_reduce_inner(a, &s, var_map);
+                let id = self.new_tc_id();
+                var_map.insert(id, var_map.len());
+                let sub_env = s.cons(EnvEntry::RType(id));
+                let b = self.beta_reduce_inner(b, &sub_env, var_map);
+                var_map.remove(&id);
+                CorePrismExpr::PairType(a, b)
+            }
+            CorePrismExpr::PairConstruct(a, b) => {
+                let a = self.beta_reduce_inner(a, &s, var_map);
+                let b = self.beta_reduce_inner(b, &s, var_map);
+                CorePrismExpr::PairConstruct(a, b)
+            }
+            CorePrismExpr::PairFst(p) => {
+                CorePrismExpr::PairFst(self.beta_reduce_inner(p, &s, var_map))
+            }
+            CorePrismExpr::PairSnd(p) => {
+                CorePrismExpr::PairSnd(self.beta_reduce_inner(p, &s, var_map))
+            }
+            CorePrismExpr::Paramatricity(x) => {
+                CorePrismExpr::Paramatricity(self.beta_reduce_inner(x, &s, var_map))
+            }
             CorePrismExpr::Free => CorePrismExpr::Free,
             CorePrismExpr::Shift(_, _) => unreachable!(),
             CorePrismExpr::TypeAssert(_, _) => unreachable!(),
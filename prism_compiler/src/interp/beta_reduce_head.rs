@@ -1,11 +1,22 @@
 use crate::lang::CoreIndex;
 use crate::lang::env::DbEnv;
 use crate::lang::env::EnvEntry::*;
-use crate::lang::{CorePrismExpr, PrismDb};
+use crate::lang::literal::PrimResult;
+use crate::lang::{CorePrismExpr, PrismDb, ValueOrigin};
 
 impl PrismDb {
     pub fn beta_reduce_head(
-        &self,
+        &mut self,
+        start_expr: CoreIndex,
+        start_env: &DbEnv,
+    ) -> (CoreIndex, DbEnv) {
+        self.reducing(start_expr, |db| {
+            db.beta_reduce_head_steps(start_expr, start_env)
+        })
+    }
+
+    fn beta_reduce_head_steps(
+        &mut self,
         mut start_expr: CoreIndex,
         start_env: &DbEnv,
     ) -> (CoreIndex, DbEnv) {
@@ -14,14 +25,28 @@ impl PrismDb {
         let mut e: CoreIndex = start_expr;
         let mut s: DbEnv = start_env.clone();
         let mut start_env = start_env.clone();
+        let term = start_expr;
 
         loop {
+            if !self.take_fuel() {
+                let free = self.store_checked(CorePrismExpr::Free, ValueOrigin::FreeSub(term));
+                return (free, DbEnv::default());
+            }
             match self.checked_values[*e] {
                 // Values
-                CorePrismExpr::Type
+                CorePrismExpr::Type(..)
                 | CorePrismExpr::FnType(..)
+                | CorePrismExpr::PairType(..)
+                | CorePrismExpr::PairConstruct(..)
                 | CorePrismExpr::GrammarValue(..)
-                | CorePrismExpr::GrammarType => {
+                | CorePrismExpr::GrammarType
+                | CorePrismExpr::Level
+                | CorePrismExpr::LevelConst(..)
+                | CorePrismExpr::LevelSucc(..)
+                | CorePrismExpr::LevelMax(..)
+                | CorePrismExpr::LevelOmega
+                | CorePrismExpr::LiteralType(..)
+                | CorePrismExpr::Literal(..) => {
                     assert!(args.is_empty());
                     return (e, s);
                 }
@@ -78,6 +103,142 @@ impl PrismDb {
                 CorePrismExpr::TypeAssert(new_e, _) => {
                     e = new_e;
                 }
+                CorePrismExpr::PairFst(p) | CorePrismExpr::PairSnd(p) => {
+                    let (p, ps) = self.beta_reduce_head(p, &s);
+                    match (&self.checked_values[*e], &self.checked_values[*p]) {
+                        (CorePrismExpr::PairFst(_), &CorePrismExpr::PairConstruct(a, _)) => {
+                            e = a;
+                            s = ps;
+                        }
+                        (CorePrismExpr::PairSnd(_), &CorePrismExpr::PairConstruct(_, b)) => {
+                            e = b;
+                            s = ps;
+                        }
+                        _ => {
+                            return if args.is_empty() {
+                                (e, s)
+                            } else {
+                                (start_expr, start_env)
+                            };
+                        }
+                    }
+                }
+                CorePrismExpr::Data(ref decl, b) => {
+                    e = b;
+                    s = decl.reduce_env(&s);
+                }
+                // The free theorem is postulated, so it does not reduce
+                CorePrismExpr::DataType(_)
+                | CorePrismExpr::DataConstruct(..)
+                | CorePrismExpr::Paramatricity(_) => {
+                    return if args.is_empty() {
+                        (e, s)
+                    } else {
+                        (start_expr, start_env)
+                    };
+                }
+                CorePrismExpr::DataElim(d) => {
+                    let decl = self.data_decl(d).clone();
+                    // The motive, the cases and the indices come before the major premise
+                    let major = 1 + decl.constructors.len() + decl.indices;
+                    if args.len() > major {
+                        let (x, xs) = &args[args.len() - 1 - major];
+                        if let Some((k, ctor_args)) = self.split_data_construct(d, *x, xs) {
+                            // Iota reduction, `D_elim P f.. i.. (c_k a..)` reduces to `step_k P f.. a..`
+                            let elim_args = args.split_off(args.len() - 1 - major);
+                            args.extend(ctor_args.into_iter().rev());
+                            args.extend_from_slice(&elim_args[1 + decl.indices..]);
+
+                            e = decl.constructors[k].step;
+                            let s_clone = s.clone();
+                            s = s.cons(RSubst(decl.elim_node, s_clone));
+                            continue;
+                        }
+                    }
+                    return if args.is_empty() {
+                        (e, s)
+                    } else {
+                        (start_expr, start_env)
+                    };
+                }
+                CorePrismExpr::PrimOp(op) => {
+                    if args.len() >= op.arity() {
+                        let (a, as_) = args[args.len() - 1].clone();
+                        let (b, bs) = args[args.len() - 2].clone();
+                        let (a, _) = self.beta_reduce_head(a, &as_);
+                        let (b, _) = self.beta_reduce_head(b, &bs);
+                        let result = match (&self.checked_values[*a], &self.checked_values[*b]) {
+                            (CorePrismExpr::Literal(a), CorePrismExpr::Literal(b)) => {
+                                op.apply(a, b)
+                            }
+                            _ => None,
+                        };
+                        match result {
+                            Some(PrimResult::Literal(l)) => {
+                                args.truncate(args.len() - 2);
+                                e = self.store_checked(
+                                    CorePrismExpr::Literal(l),
+                                    self.checked_origins[*e],
+                                );
+                                s = DbEnv::default();
+                                continue;
+                            }
+                            // `cmp a b T t f` reduces to `t` if the comparison holds, and to `f` otherwise
+                            Some(PrimResult::Bool(b)) => {
+                                let (x, xs) = args[args.len() - if b { 4 } else { 5 }].clone();
+                                args.truncate(args.len() - 5);
+                                e = x;
+                                s = xs;
+                                continue;
+                            }
+                            Some(PrimResult::Overflow) => self.overflowed(e, op),
+                            None => {}
+                        }
+                    }
+                    return if args.is_empty() {
+                        (e, s)
+                    } else {
+                        (start_expr, start_env)
+                    };
+                }
+            }
+        }
+    }
+
+    /// If `x` reduces to a fully applied constructor `c_k a..` of the data type declared by `d`, returns `k` and `a..`
+    fn split_data_construct(
+        &mut self,
+        d: CoreIndex,
+        x: CoreIndex,
+        xs: &DbEnv,
+    ) -> Option<(usize, Vec<(CoreIndex, DbEnv)>)> {
+        let mut args = Vec::new();
+        let (mut e, mut s) = self.beta_reduce_head(x, xs);
+        loop {
+            match self.checked_values[*e] {
+                CorePrismExpr::FnDestruct(f, a) => {
+                    args.push((a, s.clone()));
+                    (e, s) = self.beta_reduce_head(f, &s);
+                }
+                CorePrismExpr::DataConstruct(d2, k)
+                    if d2 == d && args.len() == self.data_decl(d).constructors[k].arity =>
+                {
+                    args.reverse();
+                    return Some((k, args));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Returns whether `x` reduces to a free variable applied to zero or more arguments
+    pub fn is_free_headed(&mut self, x: CoreIndex, xs: &DbEnv) -> bool {
+        let (mut e, mut s) = self.beta_reduce_head(x, xs);
+        loop {
+            match self.checked_values[*e] {
+                CorePrismExpr::FnDestruct(f, _) => (e, s) = self.beta_reduce_head(f, &s),
+                CorePrismExpr::Free => return true,
+                _ => return false,
             }
         }
     }
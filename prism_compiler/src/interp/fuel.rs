@@ -0,0 +1,118 @@
+use crate::lang::literal::{LiteralOverflow, PrimOp};
+use crate::lang::{CoreIndex, PrismDb};
+use prism_diag_derive::Diagnostic;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// The number of reduction steps that the running entry point may still take.
+/// Outside of an entry point, reduction is not limited.
+#[derive(Default)]
+pub struct Fuel {
+    remaining: AtomicUsize,
+    /// The number of entry points that are running, only the outermost one sets the budget
+    depth: usize,
+    /// The outermost term that is being reduced, or the part of it given to `reducing_part`
+    reducing: Mutex<Option<CoreIndex>>,
+    /// The term that was being reduced when the fuel ran out, with the number of diagnostics at that moment
+    exhausted: OnceLock<(CoreIndex, usize)>,
+    /// The primitive operations whose result did not fit in a literal while reducing, reported when the entry point ends
+    overflowed: Mutex<Vec<(CoreIndex, PrimOp)>>,
+    /// The primitive operations that overflows were reported for, which are only reported once
+    reported_overflows: HashSet<CoreIndex>,
+}
+
+/// Reducing a term took more steps than the limit given by `--fuel`.
+///
+/// Terms are reduced while type checking and evaluating a program.
+/// A term that takes too many steps may not terminate, such as a term that uses the inconsistent rule
+/// `Type : Type` enabled by `--type-in-type`, or it may just be large.
+/// If it does terminate, the limit can be raised with `--fuel`.
+#[derive(Diagnostic)]
+#[diag(title = format!("Reduction did not terminate within {} steps", self.fuel), env = PrismDb)]
+#[help("If the term does terminate, the limit can be raised with `--fuel`")]
+pub struct OutOfFuel {
+    #[sugg(label = "While reducing this term")]
+    pub term: CoreIndex,
+    pub fuel: usize,
+}
+
+impl PrismDb {
+    /// Runs the entry point `f` with the reduction budget given by `--fuel`.
+    /// If `f` runs out of fuel, an error is reported at the term that was being reduced.
+    /// The errors reported after that are dropped, since they are caused by the terms that were not reduced.
+    /// Afterwards, the primitive operations that overflowed while running `f` are reported.
+    pub fn with_fuel<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        if self.fuel.depth > 0 {
+            return f(self);
+        }
+        *self.fuel.remaining.get_mut() = self.args.fuel;
+        self.fuel.depth += 1;
+        let result = f(self);
+        self.fuel.depth -= 1;
+
+        if let Some((term, diag_count)) = self.fuel.exhausted.take() {
+            self.diags.truncate(diag_count);
+            self.push_error(OutOfFuel {
+                term,
+                fuel: self.args.fuel,
+            });
+        }
+        for (term, op) in std::mem::take(self.fuel.overflowed.get_mut().unwrap()) {
+            if self.fuel.reported_overflows.insert(term) {
+                self.push_error(LiteralOverflow { term, op });
+            }
+        }
+        result
+    }
+
+    /// Records that the result of the primitive operation `op` at `term` did not fit in a literal,
+    /// it is reported when the running entry point ends
+    pub fn overflowed(&self, term: CoreIndex, op: PrimOp) {
+        self.fuel.overflowed.lock().unwrap().push((term, op));
+    }
+
+    /// Runs `f`, which reduces `i`. If the fuel runs out, the error is reported at the outermost term that was being reduced.
+    pub fn reducing<T>(&mut self, i: CoreIndex, f: impl FnOnce(&mut Self) -> T) -> T {
+        let reducing = self.fuel.reducing.get_mut().unwrap();
+        if reducing.is_some() {
+            return f(self);
+        }
+        *reducing = Some(i);
+        let result = f(self);
+        *self.fuel.reducing.get_mut().unwrap() = None;
+        result
+    }
+
+    /// Runs `f`, which reduces `i` as part of the term that is being reduced.
+    /// If the fuel runs out, the error is reported at `i` instead of at the outermost term.
+    /// This is used when comparing values, which are reduced one part at a time.
+    pub fn reducing_part<T>(&self, i: CoreIndex, f: impl FnOnce() -> T) -> T {
+        let outer = self.fuel.reducing.lock().unwrap().replace(i);
+        let result = f();
+        *self.fuel.reducing.lock().unwrap() = outer;
+        result
+    }
+
+    /// Takes a reduction step.
+    /// Returns `false` if there is no fuel left, in which case the term should not be reduced any further.
+    pub fn take_fuel(&self) -> bool {
+        if self.fuel.depth == 0 {
+            return true;
+        }
+        let taken = self
+            .fuel
+            .remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        if taken.is_err() {
+            let term = self
+                .fuel
+                .reducing
+                .lock()
+                .unwrap()
+                .expect("Fuel is only taken while reducing a term");
+            _ = self.fuel.exhausted.set((term, self.diags.len()));
+        }
+        taken.is_ok()
+    }
+}
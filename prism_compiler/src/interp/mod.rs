@@ -1,4 +1,6 @@
 pub mod beta_reduce;
 pub mod beta_reduce_head;
+pub mod fuel;
 pub mod is_beta_equal;
+pub mod nbe;
 pub mod simplify;
@@ -0,0 +1,830 @@
+use crate::lang::env::{DbEnv, EnvEntry};
+use crate::lang::level::{LevelAtom, LevelNf};
+use crate::lang::literal::{Literal, PrimResult};
+use crate::lang::{CoreIndex, CorePrismExpr, PrismDb, ValueOrigin};
+use crate::type_check::UniqueVariableId;
+use crate::type_check::level::Subtype;
+use prism_parser::env::GenericEnv;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The environment values are evaluated in, each variable is bound to a lazily evaluated value
+pub type NbeEnv = GenericEnv<(), Thunk>;
+
+/// A value in weak head normal form, the result of evaluating a `CoreIndex` in an `NbeEnv`.
+/// The `CoreIndex` of each value is the node it was evaluated from, which is used as the origin when reading it back.
+#[derive(Clone)]
+pub enum Value {
+    /// A node that does not refer to any variables, such as `Grammar` or a data constructor
+    Const(CoreIndex),
+    /// A literal computed by a primitive operation
+    Literal(CoreIndex, Literal),
+    Type(CoreIndex, Thunk),
+    LevelSucc(CoreIndex, Thunk),
+    LevelMax(CoreIndex, Thunk, Thunk),
+    FnType(CoreIndex, Thunk, Closure),
+    FnConstruct(CoreIndex, Closure),
+    PairType(CoreIndex, Thunk, Closure),
+    PairConstruct(CoreIndex, Thunk, Thunk),
+    /// A value that can't be reduced further, of the form `head e_1 .. e_n`
+    Neutral(Rc<Head>, Rc<[Elim]>),
+}
+
+pub enum Head {
+    /// The variable at the given de Bruijn level
+    Var(CoreIndex, usize),
+    /// A `Free` node that is not solved yet
+    Free(CoreIndex),
+    Paramatricity(CoreIndex, Thunk),
+    /// The `DataElim` node of a data type, with the environment its computation rules are in
+    DataElim(CoreIndex, NbeEnv),
+    /// A constant that is eliminated, such as a constructor or a primitive operation that is not applied to enough literals
+    Const(CoreIndex),
+    /// A value that can't be eliminated, this only happens in terms that are not well-typed
+    Stuck(Value),
+    /// A term that was not evaluated since there was no fuel left, it is read back as a `Free` node
+    OutOfFuel(CoreIndex),
+}
+
+#[derive(Clone)]
+pub enum Elim {
+    App(CoreIndex, Thunk),
+    Fst(CoreIndex),
+    Snd(CoreIndex),
+}
+
+/// The body of a binder, together with the environment it is in
+#[derive(Clone)]
+pub struct Closure {
+    body: CoreIndex,
+    env: NbeEnv,
+}
+
+/// A value that is evaluated when it is first needed
+#[derive(Clone)]
+pub struct Thunk(Rc<RefCell<ThunkState>>);
+
+enum ThunkState {
+    Pending(CoreIndex, NbeEnv),
+    Forced(Value),
+}
+
+/// A step `eval` took to reach the term in tail position that it evaluates
+enum TailStep {
+    /// A variable used at this node
+    Var(CoreIndex),
+    /// A thunk of which the value is the value of the term
+    Force(Thunk),
+}
+
+impl Value {
+    /// Returns whether this is a `Free` node applied to zero or more arguments, or a term that was not evaluated
+    fn is_free_headed(&self) -> bool {
+        matches!(self, Value::Neutral(head, _) if matches!(**head, Head::Free(_) | Head::OutOfFuel(_)))
+    }
+
+    /// The node this value was evaluated from
+    fn origin(&self) -> CoreIndex {
+        match self {
+            &Value::Const(origin)
+            | &Value::Literal(origin, _)
+            | &Value::Type(origin, _)
+            | &Value::LevelSucc(origin, _)
+            | &Value::LevelMax(origin, _, _)
+            | &Value::FnType(origin, _, _)
+            | &Value::FnConstruct(origin, _)
+            | &Value::PairType(origin, _, _)
+            | &Value::PairConstruct(origin, _, _) => origin,
+            Value::Neutral(head, _) => match **head {
+                Head::Var(origin, _)
+                | Head::Free(origin)
+                | Head::Paramatricity(origin, _)
+                | Head::DataElim(origin, _)
+                | Head::Const(origin)
+                | Head::OutOfFuel(origin) => origin,
+                Head::Stuck(ref v) => v.origin(),
+            },
+        }
+    }
+}
+
+/// How `conv` compares values that depend on `Free` nodes
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FreeMode {
+    /// Comparisons that depend on a `Free` node are blocked, so the type checker can solve it by unification
+    Block,
+    /// `Free` nodes are equal to each other, and a level that depends on one is equal to any level
+    Equal,
+}
+
+/// Combines the comparisons of two parts of a value, a part that is not equal decides the result
+fn and(a: Option<bool>, b: impl FnOnce() -> Option<bool>) -> Option<bool> {
+    match a {
+        Some(false) => Some(false),
+        Some(true) => b(),
+        None => match b() {
+            Some(false) => Some(false),
+            _ => None,
+        },
+    }
+}
+
+impl Thunk {
+    fn new(i: CoreIndex, env: NbeEnv) -> Self {
+        Self(Rc::new(RefCell::new(ThunkState::Pending(i, env))))
+    }
+
+    fn forced(v: Value) -> Self {
+        Self(Rc::new(RefCell::new(ThunkState::Forced(v))))
+    }
+
+    fn var(origin: CoreIndex, level: usize) -> Self {
+        Self::forced(Value::Neutral(
+            Rc::new(Head::Var(origin, level)),
+            Rc::new([]),
+        ))
+    }
+}
+
+impl PrismDb {
+    /// Reduces `i` in `env` to its normal form, by evaluating it to a `Value` and reading that back.
+    /// Variables of which only the type is known are kept,
+    /// so the result is valid in an env containing only these variables.
+    pub fn normalize(&mut self, i: CoreIndex, env: &DbEnv) -> CoreIndex {
+        self.with_fuel(|db| {
+            db.reducing(i, |db| {
+                let mut vars = HashMap::new();
+                let env = db.nbe_env(env, i, &mut HashMap::new(), &mut vars);
+                let v = db.eval(i, &env);
+                db.read_back(&v, vars.len())
+            })
+        })
+    }
+
+    /// Converts `env` to an `NbeEnv`, `vars` assigns the de Bruijn levels of the variables of which the value is not known.
+    /// Environments are shared between entries, so the converted environments are cached in `cache`.
+    pub(crate) fn nbe_env(
+        &self,
+        env: &DbEnv,
+        origin: CoreIndex,
+        cache: &mut HashMap<usize, NbeEnv>,
+        vars: &mut HashMap<UniqueVariableId, usize>,
+    ) -> NbeEnv {
+        let Some(((_, entry), rest)) = env.split() else {
+            return NbeEnv::default();
+        };
+        let key = env.as_ptr() as usize;
+        if let Some(env) = cache.get(&key) {
+            return env.clone();
+        }
+
+        let nbe_rest = self.nbe_env(&rest, origin, cache, vars);
+        let thunk = match *entry {
+            EnvEntry::CType(id, _) | EnvEntry::RType(id) => {
+                let next_level = vars.len();
+                let level = *vars.entry(id).or_insert(next_level);
+                Thunk::var(origin, level)
+            }
+            EnvEntry::CSubst(v, _) => Thunk::new(v, nbe_rest.clone()),
+            EnvEntry::RSubst(v, ref vs) => Thunk::new(v, self.nbe_env(vs, origin, cache, vars)),
+        };
+        let env = nbe_rest.cons(thunk);
+        cache.insert(key, env.clone());
+        env
+    }
+
+    /// Evaluates `i` in `env` to weak head normal form.
+    /// Terms in tail position, such as the body of a `Let` or the value of a variable, are evaluated in a loop,
+    /// so long chains of them don't overflow the stack.
+    pub fn eval(&self, i: CoreIndex, env: &NbeEnv) -> Value {
+        let (mut i, mut env) = (i, env.clone());
+        let mut steps = Vec::new();
+        let mut v = loop {
+            if !self.take_fuel() {
+                break Value::Neutral(Rc::new(Head::OutOfFuel(i)), Rc::new([]));
+            }
+            let forced = match self.checked_values[*i] {
+                CorePrismExpr::Free => break Value::Neutral(Rc::new(Head::Free(i)), Rc::new([])),
+                CorePrismExpr::Type(l) => break Value::Type(i, Thunk::new(l, env.clone())),
+                CorePrismExpr::Let(v, b) => {
+                    env = env.cons(Thunk::new(v, env.clone()));
+                    i = b;
+                    continue;
+                }
+                CorePrismExpr::DeBruijnIndex(v) => {
+                    steps.push(TailStep::Var(i));
+                    env[v].clone()
+                }
+                CorePrismExpr::FnType(a, b) => {
+                    break Value::FnType(
+                        i,
+                        Thunk::new(a, env.clone()),
+                        Closure {
+                            body: b,
+                            env: env.clone(),
+                        },
+                    );
+                }
+                CorePrismExpr::FnConstruct(b) => {
+                    break Value::FnConstruct(
+                        i,
+                        Closure {
+                            body: b,
+                            env: env.clone(),
+                        },
+                    );
+                }
+                CorePrismExpr::FnDestruct(f, a) => {
+                    let arg = Thunk::new(a, env.clone());
+                    match self.eval(f, &env) {
+                        Value::FnConstruct(_, c) => {
+                            env = c.env.cons(arg);
+                            i = c.body;
+                            continue;
+                        }
+                        f => break self.eliminate(f, Elim::App(i, arg)),
+                    }
+                }
+                CorePrismExpr::Shift(b, n) => {
+                    env = env.shift(n);
+                    i = b;
+                    continue;
+                }
+                CorePrismExpr::TypeAssert(e, _) => {
+                    i = e;
+                    continue;
+                }
+                CorePrismExpr::PairType(a, b) => {
+                    break Value::PairType(
+                        i,
+                        Thunk::new(a, env.clone()),
+                        Closure {
+                            body: b,
+                            env: env.clone(),
+                        },
+                    );
+                }
+                CorePrismExpr::PairConstruct(a, b) => {
+                    break Value::PairConstruct(
+                        i,
+                        Thunk::new(a, env.clone()),
+                        Thunk::new(b, env.clone()),
+                    );
+                }
+                CorePrismExpr::PairFst(p) => match self.eval(p, &env) {
+                    Value::PairConstruct(_, a, _) => a,
+                    p => break self.eliminate(p, Elim::Fst(i)),
+                },
+                CorePrismExpr::PairSnd(p) => match self.eval(p, &env) {
+                    Value::PairConstruct(_, _, b) => b,
+                    p => break self.eliminate(p, Elim::Snd(i)),
+                },
+                CorePrismExpr::Data(ref decl, b) => {
+                    // The same environment as `DataDecl::reduce_env`
+                    env = env.cons(Thunk::new(decl.type_node, env.clone()));
+                    for c in decl.constructors.iter() {
+                        env = env.cons(Thunk::new(c.node, env.clone()));
+                    }
+                    env = env.cons(Thunk::new(decl.elim_node, env.clone()));
+                    i = b;
+                    continue;
+                }
+                CorePrismExpr::DataElim(_) => {
+                    break Value::Neutral(Rc::new(Head::DataElim(i, env.clone())), Rc::new([]));
+                }
+                CorePrismExpr::Paramatricity(x) => {
+                    break Value::Neutral(
+                        Rc::new(Head::Paramatricity(i, Thunk::new(x, env.clone()))),
+                        Rc::new([]),
+                    );
+                }
+                CorePrismExpr::LevelSucc(l) => {
+                    break Value::LevelSucc(i, Thunk::new(l, env.clone()));
+                }
+                CorePrismExpr::LevelMax(a, b) => {
+                    break Value::LevelMax(
+                        i,
+                        Thunk::new(a, env.clone()),
+                        Thunk::new(b, env.clone()),
+                    );
+                }
+                CorePrismExpr::GrammarValue(_)
+                | CorePrismExpr::GrammarType
+                | CorePrismExpr::DataType(_)
+                | CorePrismExpr::DataConstruct(..)
+                | CorePrismExpr::Level
+                | CorePrismExpr::LevelConst(_)
+                | CorePrismExpr::LevelOmega
+                | CorePrismExpr::LiteralType(_)
+                | CorePrismExpr::Literal(_)
+                | CorePrismExpr::PrimOp(_) => break Value::Const(i),
+            };
+
+            // The value is the value of the thunk `forced`, which is evaluated here if it was not forced before
+            let pending = match &*forced.0.borrow() {
+                ThunkState::Forced(v) => Err(v.clone()),
+                ThunkState::Pending(i, env) => Ok((*i, env.clone())),
+            };
+            match pending {
+                Ok(pending) => {
+                    (i, env) = pending;
+                    steps.push(TailStep::Force(forced));
+                }
+                Err(v) => break v,
+            }
+        };
+
+        for step in steps.into_iter().rev() {
+            match step {
+                // Variables are read back with the origin of where they are used
+                TailStep::Var(use_site) => {
+                    if let Value::Neutral(head, spine) = &v
+                        && let Head::Var(_, level) = **head
+                        && spine.is_empty()
+                    {
+                        v = Value::Neutral(Rc::new(Head::Var(use_site, level)), Rc::new([]));
+                    }
+                }
+                TailStep::Force(t) => *t.0.borrow_mut() = ThunkState::Forced(v.clone()),
+            }
+        }
+        v
+    }
+
+    /// Returns the value of `t`, evaluating it if this was not done before
+    pub fn force(&self, t: &Thunk) -> Value {
+        let (i, env) = match &*t.0.borrow() {
+            ThunkState::Forced(v) => return v.clone(),
+            ThunkState::Pending(i, env) => (*i, env.clone()),
+        };
+        let v = self.eval(i, &env);
+        *t.0.borrow_mut() = ThunkState::Forced(v.clone());
+        v
+    }
+
+    /// Applies the closure `c` to `arg`
+    fn apply_closure(&self, c: &Closure, arg: Thunk) -> Value {
+        self.eval(c.body, &c.env.cons(arg))
+    }
+
+    /// Eliminates `v` using `elim`, reducing the result if possible
+    fn eliminate(&self, v: Value, elim: Elim) -> Value {
+        match (v, elim) {
+            (Value::FnConstruct(_, c), Elim::App(_, arg)) => self.apply_closure(&c, arg),
+            (Value::PairConstruct(_, a, _), Elim::Fst(_)) => self.force(&a),
+            (Value::PairConstruct(_, _, b), Elim::Snd(_)) => self.force(&b),
+            (Value::Neutral(head, spine), elim) => {
+                let spine: Rc<[Elim]> = spine.iter().cloned().chain([elim]).collect();
+                self.reduce_neutral(head, spine)
+            }
+            (Value::Const(c), elim) => {
+                self.reduce_neutral(Rc::new(Head::Const(c)), Rc::new([elim]))
+            }
+            (v, elim) => Value::Neutral(Rc::new(Head::Stuck(v)), Rc::new([elim])),
+        }
+    }
+
+    /// Reduces `head spine` if the head is an eliminator or a primitive operation that is applied to enough arguments
+    fn reduce_neutral(&self, head: Rc<Head>, spine: Rc<[Elim]>) -> Value {
+        let args = || {
+            spine.iter().map_while(|e| match e {
+                Elim::App(_, a) => Some(a),
+                Elim::Fst(_) | Elim::Snd(_) => None,
+            })
+        };
+        match &*head {
+            Head::DataElim(e, env) => {
+                let CorePrismExpr::DataElim(d) = self.checked_values[**e] else {
+                    unreachable!()
+                };
+                let decl = self.data_decl(d);
+                // The motive, the cases and the indices come before the major premise
+                let major = 1 + decl.constructors.len() + decl.indices;
+                if let Some(x) = args().nth(major)
+                    && let Some((k, ctor_args)) = self.split_data_value(d, &self.force(x))
+                {
+                    // Iota reduction, `D_elim P f.. i.. (c_k a..)` reduces to `step_k P f.. a..`
+                    let step_env = env.cons(Thunk::forced(Value::Neutral(
+                        Rc::new(Head::DataElim(*e, env.clone())),
+                        Rc::new([]),
+                    )));
+                    let mut v = self.eval(decl.constructors[k].step, &step_env);
+                    let motive_and_cases = spine[..1 + decl.constructors.len()].iter().cloned();
+                    let rest = spine[major + 1..].iter().cloned();
+                    for elim in motive_and_cases.chain(ctor_args).chain(rest) {
+                        v = self.eliminate(v, elim);
+                    }
+                    return v;
+                }
+            }
+            Head::Const(c) => {
+                if let CorePrismExpr::PrimOp(op) = self.checked_values[**c]
+                    && args().count() >= op.arity()
+                {
+                    let mut args = args();
+                    let a = self.force(args.next().unwrap());
+                    let b = self.force(args.next().unwrap());
+                    let result = match (self.as_literal(&a), self.as_literal(&b)) {
+                        (Some(a), Some(b)) => op.apply(a, b),
+                        _ => None,
+                    };
+                    match result {
+                        Some(PrimResult::Literal(l)) => {
+                            let mut v = Value::Literal(*c, l);
+                            for elim in spine[2..].iter().cloned() {
+                                v = self.eliminate(v, elim);
+                            }
+                            return v;
+                        }
+                        // `cmp a b T t f` reduces to `t` if the comparison holds, and to `f` otherwise
+                        Some(PrimResult::Bool(b)) => {
+                            let Elim::App(_, x) = &spine[if b { 3 } else { 4 }] else {
+                                unreachable!()
+                            };
+                            let mut v = self.force(x);
+                            for elim in spine[5..].iter().cloned() {
+                                v = self.eliminate(v, elim);
+                            }
+                            return v;
+                        }
+                        Some(PrimResult::Overflow) => self.overflowed(*c, op),
+                        None => {}
+                    }
+                }
+            }
+            Head::Var(..)
+            | Head::Free(_)
+            | Head::Paramatricity(..)
+            | Head::Stuck(_)
+            | Head::OutOfFuel(_) => {}
+        }
+        Value::Neutral(head, spine)
+    }
+
+    /// If `v` is a fully applied constructor `c_k a..` of the data type declared by `d`, returns `k` and `a..`
+    fn split_data_value(&self, d: CoreIndex, v: &Value) -> Option<(usize, Vec<Elim>)> {
+        let (c, spine): (CoreIndex, &[Elim]) = match v {
+            &Value::Const(c) => (c, &[]),
+            Value::Neutral(head, spine) => match **head {
+                Head::Const(c) => (c, spine),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match self.checked_values[*c] {
+            CorePrismExpr::DataConstruct(d2, k)
+                if d2 == d
+                    && spine.len() == self.data_decl(d).constructors[k].arity
+                    && spine.iter().all(|e| matches!(e, Elim::App(..))) =>
+            {
+                Some((k, spine.to_vec()))
+            }
+            _ => None,
+        }
+    }
+
+    fn as_literal<'a>(&'a self, v: &'a Value) -> Option<&'a Literal> {
+        match v {
+            Value::Literal(_, l) => Some(l),
+            Value::Const(c) => match &self.checked_values[**c] {
+                CorePrismExpr::Literal(l) => Some(l),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Reads back `v` to a term in normal form, which is valid in an env with `depth` variables
+    pub fn read_back(&mut self, v: &Value, depth: usize) -> CoreIndex {
+        // Normal forms can be exponentially larger than their values, so reading back takes fuel as well
+        if !self.take_fuel() {
+            return self.store_checked(CorePrismExpr::Free, ValueOrigin::FreeSub(v.origin()));
+        }
+        let (e, origin) = match v {
+            &Value::Const(i) => return i,
+            Value::Literal(origin, l) => (CorePrismExpr::Literal(l.clone()), *origin),
+            Value::Type(origin, l) => {
+                (CorePrismExpr::Type(self.read_back_thunk(l, depth)), *origin)
+            }
+            Value::LevelSucc(origin, l) => (
+                CorePrismExpr::LevelSucc(self.read_back_thunk(l, depth)),
+                *origin,
+            ),
+            Value::LevelMax(origin, a, b) => {
+                let a = self.read_back_thunk(a, depth);
+                let b = self.read_back_thunk(b, depth);
+                (CorePrismExpr::LevelMax(a, b), *origin)
+            }
+            Value::FnType(origin, a, b) => {
+                let a = self.read_back_thunk(a, depth);
+                let b = self.read_back_closure(b, *origin, depth);
+                (CorePrismExpr::FnType(a, b), *origin)
+            }
+            Value::FnConstruct(origin, b) => {
+                let b = self.read_back_closure(b, *origin, depth);
+                (CorePrismExpr::FnConstruct(b), *origin)
+            }
+            Value::PairType(origin, a, b) => {
+                let a = self.read_back_thunk(a, depth);
+                let b = self.read_back_closure(b, *origin, depth);
+                (CorePrismExpr::PairType(a, b), *origin)
+            }
+            Value::PairConstruct(origin, a, b) => {
+                let a = self.read_back_thunk(a, depth);
+                let b = self.read_back_thunk(b, depth);
+                (CorePrismExpr::PairConstruct(a, b), *origin)
+            }
+            Value::Neutral(head, spine) => {
+                let mut e = self.read_back_head(head, depth);
+                for elim in spine.iter() {
+                    let (e_new, origin) = match elim {
+                        Elim::App(origin, a) => (
+                            CorePrismExpr::FnDestruct(e, self.read_back_thunk(a, depth)),
+                            *origin,
+                        ),
+                        Elim::Fst(origin) => (CorePrismExpr::PairFst(e), *origin),
+                        Elim::Snd(origin) => (CorePrismExpr::PairSnd(e), *origin),
+                    };
+                    e = self.store_checked(e_new, self.checked_origins[*origin]);
+                }
+                return e;
+            }
+        };
+        self.store_checked(e, self.checked_origins[*origin])
+    }
+
+    fn read_back_thunk(&mut self, t: &Thunk, depth: usize) -> CoreIndex {
+        let v = self.force(t);
+        self.read_back(&v, depth)
+    }
+
+    fn read_back_closure(&mut self, c: &Closure, origin: CoreIndex, depth: usize) -> CoreIndex {
+        let v = self.apply_closure(c, Thunk::var(origin, depth));
+        self.read_back(&v, depth + 1)
+    }
+
+    fn read_back_head(&mut self, head: &Head, depth: usize) -> CoreIndex {
+        match head {
+            &Head::Var(origin, level) => self.store_checked(
+                CorePrismExpr::DeBruijnIndex(depth - level - 1),
+                self.checked_origins[*origin],
+            ),
+            &Head::Free(i) | &Head::DataElim(i, _) | &Head::Const(i) => i,
+            Head::Paramatricity(origin, x) => {
+                let x = self.read_back_thunk(x, depth);
+                self.store_checked(
+                    CorePrismExpr::Paramatricity(x),
+                    self.checked_origins[**origin],
+                )
+            }
+            Head::Stuck(v) => self.read_back(v, depth),
+            &Head::OutOfFuel(i) => self.store_checked(CorePrismExpr::Free, ValueOrigin::FreeSub(i)),
+        }
+    }
+
+    /// Compares `i1` in `s1` with `i2` in `s2` by evaluating both, see `conv`
+    pub fn convertible(
+        &mut self,
+        (i1, s1): (CoreIndex, &DbEnv),
+        (i2, s2): (CoreIndex, &DbEnv),
+        mode: FreeMode,
+        sub: Option<Subtype>,
+    ) -> Option<bool> {
+        self.reducing(i1, |db| {
+            let mut cache = HashMap::new();
+            let mut vars = HashMap::new();
+            let env1 = db.nbe_env(s1, i1, &mut cache, &mut vars);
+            let env2 = db.nbe_env(s2, i2, &mut cache, &mut vars);
+            let v1 = db.eval(i1, &env1);
+            let v2 = db.reducing_part(i2, || db.eval(i2, &env2));
+            db.conv(&v1, &v2, vars.len(), mode, sub)
+        })
+    }
+
+    /// Compares the values `v1` and `v2`, which are valid in an env with `depth` variables.
+    /// Values are equal up to eta for functions and pairs.
+    /// If `sub` is given, a universe is also accepted if its level is smaller on the side given by `sub`.
+    /// Returns `None` if the result depends on `Free` nodes and `mode` is `Block`.
+    pub fn conv(
+        &self,
+        v1: &Value,
+        v2: &Value,
+        depth: usize,
+        mode: FreeMode,
+        sub: Option<Subtype>,
+    ) -> Option<bool> {
+        // Values can be compared many times, like normal forms are read back, so comparing takes fuel as well
+        if !self.take_fuel() {
+            return match mode {
+                FreeMode::Block => None,
+                FreeMode::Equal => Some(false),
+            };
+        }
+        if mode == FreeMode::Block && (v1.is_free_headed() || v2.is_free_headed()) {
+            return None;
+        }
+
+        match (v1, v2) {
+            (Value::Type(_, l1), Value::Type(_, l2)) => {
+                self.conv_level(&self.force_part(l1), &self.force_part(l2), mode, sub)
+            }
+            _ if self.is_level_value(v1) || self.is_level_value(v2) => {
+                self.conv_level(v1, v2, mode, None)
+            }
+            _ if self.as_literal(v1).is_some() && self.as_literal(v2).is_some() => {
+                Some(self.as_literal(v1) == self.as_literal(v2))
+            }
+            (&Value::Const(c1), &Value::Const(c2)) => Some(self.conv_const(c1, c2)),
+            (Value::FnType(_, a1, b1), Value::FnType(_, a2, b2)) => {
+                and(self.conv_thunk(a1, a2, depth, mode, None), || {
+                    self.conv_closure(b1, b2, depth, mode, sub)
+                })
+            }
+            (Value::FnConstruct(_, b1), Value::FnConstruct(_, b2)) => {
+                self.conv_closure(b1, b2, depth, mode, None)
+            }
+            (Value::PairType(_, a1, b1), Value::PairType(_, a2, b2)) => {
+                and(self.conv_thunk(a1, a2, depth, mode, sub), || {
+                    self.conv_closure(b1, b2, depth, mode, sub)
+                })
+            }
+            (Value::PairConstruct(_, a1, b1), Value::PairConstruct(_, a2, b2)) => {
+                and(self.conv_thunk(a1, a2, depth, mode, None), || {
+                    self.conv_thunk(b1, b2, depth, mode, None)
+                })
+            }
+            // Eta for functions, `x => b` is equal to `f` if `b` is equal to `f x`
+            (&Value::FnConstruct(origin, ref b), v) | (v, &Value::FnConstruct(origin, ref b)) => {
+                let x = Thunk::var(origin, depth);
+                let b = self.apply_closure(b, x.clone());
+                let f = self.eliminate(v.clone(), Elim::App(origin, x));
+                self.conv(&b, &f, depth + 1, mode, None)
+            }
+            // Eta for pairs, `(a, b)` is equal to `p` if `a` is equal to `fst p` and `b` to `snd p`
+            (&Value::PairConstruct(origin, ref a, ref b), v)
+            | (v, &Value::PairConstruct(origin, ref a, ref b)) => {
+                let fst = self.eliminate(v.clone(), Elim::Fst(origin));
+                and(
+                    self.conv(&self.force_part(a), &fst, depth, mode, None),
+                    || {
+                        let snd = self.eliminate(v.clone(), Elim::Snd(origin));
+                        self.conv(&self.force_part(b), &snd, depth, mode, None)
+                    },
+                )
+            }
+            (Value::Neutral(h1, spine1), Value::Neutral(h2, spine2)) => {
+                if spine1.len() != spine2.len() {
+                    return Some(false);
+                }
+                let heads = self.conv_head(h1, h2, depth, mode);
+                spine1.iter().zip(spine2.iter()).fold(heads, |acc, elims| {
+                    and(acc, || match elims {
+                        (Elim::App(_, a1), Elim::App(_, a2)) => {
+                            self.conv_thunk(a1, a2, depth, mode, None)
+                        }
+                        (Elim::Fst(_), Elim::Fst(_)) | (Elim::Snd(_), Elim::Snd(_)) => Some(true),
+                        _ => Some(false),
+                    })
+                })
+            }
+            _ => Some(false),
+        }
+    }
+
+    fn conv_thunk(
+        &self,
+        t1: &Thunk,
+        t2: &Thunk,
+        depth: usize,
+        mode: FreeMode,
+        sub: Option<Subtype>,
+    ) -> Option<bool> {
+        self.conv(&self.force_part(t1), &self.force_part(t2), depth, mode, sub)
+    }
+
+    /// Forces `t` while comparing values, the fuel it takes is attributed to the term of `t`
+    fn force_part(&self, t: &Thunk) -> Value {
+        let pending = match &*t.0.borrow() {
+            ThunkState::Forced(v) => return v.clone(),
+            &ThunkState::Pending(i, _) => i,
+        };
+        self.reducing_part(pending, || self.force(t))
+    }
+
+    fn conv_closure(
+        &self,
+        c1: &Closure,
+        c2: &Closure,
+        depth: usize,
+        mode: FreeMode,
+        sub: Option<Subtype>,
+    ) -> Option<bool> {
+        let x = Thunk::var(c1.body, depth);
+        let v1 = self.reducing_part(c1.body, || self.apply_closure(c1, x.clone()));
+        let v2 = self.reducing_part(c2.body, || self.apply_closure(c2, x));
+        self.conv(&v1, &v2, depth + 1, mode, sub)
+    }
+
+    fn conv_head(&self, h1: &Head, h2: &Head, depth: usize, mode: FreeMode) -> Option<bool> {
+        match (h1, h2) {
+            (Head::Var(_, l1), Head::Var(_, l2)) => Some(l1 == l2),
+            // This is only reached if `mode` is `Equal`
+            (Head::Free(_) | Head::OutOfFuel(_), Head::Free(_) | Head::OutOfFuel(_)) => Some(true),
+            (Head::Paramatricity(_, x1), Head::Paramatricity(_, x2)) => {
+                self.conv_thunk(x1, x2, depth, mode, None)
+            }
+            (Head::DataElim(e1, _), Head::DataElim(e2, _)) => {
+                let (&CorePrismExpr::DataElim(d1), &CorePrismExpr::DataElim(d2)) =
+                    (&self.checked_values[**e1], &self.checked_values[**e2])
+                else {
+                    unreachable!()
+                };
+                Some(d1 == d2)
+            }
+            (&Head::Const(c1), &Head::Const(c2)) => Some(self.conv_const(c1, c2)),
+            (Head::Stuck(v1), Head::Stuck(v2)) => self.conv(v1, v2, depth, mode, None),
+            _ => Some(false),
+        }
+    }
+
+    /// Constants are equal if they are the same constant, grammars are never equal
+    fn conv_const(&self, c1: CoreIndex, c2: CoreIndex) -> bool {
+        match (&self.checked_values[*c1], &self.checked_values[*c2]) {
+            (CorePrismExpr::GrammarType, CorePrismExpr::GrammarType)
+            | (CorePrismExpr::Level, CorePrismExpr::Level) => true,
+            (CorePrismExpr::DataType(d1), CorePrismExpr::DataType(d2)) => d1 == d2,
+            (CorePrismExpr::DataConstruct(d1, k1), CorePrismExpr::DataConstruct(d2, k2)) => {
+                d1 == d2 && k1 == k2
+            }
+            (CorePrismExpr::LiteralType(t1), CorePrismExpr::LiteralType(t2)) => t1 == t2,
+            (CorePrismExpr::Literal(l1), CorePrismExpr::Literal(l2)) => l1 == l2,
+            (CorePrismExpr::PrimOp(op1), CorePrismExpr::PrimOp(op2)) => op1 == op2,
+            _ => false,
+        }
+    }
+
+    /// Levels are compared using their normal form
+    fn conv_level(
+        &self,
+        v1: &Value,
+        v2: &Value,
+        mode: FreeMode,
+        sub: Option<Subtype>,
+    ) -> Option<bool> {
+        if self.args.type_in_type {
+            return Some(true);
+        }
+        let nf1 = self.value_level_nf(v1, &|level| level);
+        let nf2 = self.value_level_nf(v2, &|level| level);
+        let holds = match sub {
+            None => nf1.leq(&nf2) && nf2.leq(&nf1),
+            Some(Subtype::Left) => nf1.leq(&nf2),
+            Some(Subtype::Right) => nf2.leq(&nf1),
+        };
+        if holds {
+            Some(true)
+        } else if nf1.has_free() || nf2.has_free() {
+            match mode {
+                FreeMode::Block => None,
+                FreeMode::Equal => Some(true),
+            }
+        } else {
+            Some(false)
+        }
+    }
+
+    /// Returns whether `v` is a level constructed from the level operations
+    fn is_level_value(&self, v: &Value) -> bool {
+        match v {
+            Value::LevelSucc(..) | Value::LevelMax(..) => true,
+            Value::Const(c) => self.checked_values[**c].is_level_value(),
+            _ => false,
+        }
+    }
+
+    /// Computes the normal form of the level `v`, `var` gives the atom of the variable at a de Bruijn level
+    pub(crate) fn value_level_nf<V: PartialEq>(
+        &self,
+        v: &Value,
+        var: &impl Fn(usize) -> V,
+    ) -> LevelNf<LevelAtom<V>> {
+        match v {
+            Value::LevelSucc(_, a) => self.value_level_nf(&self.force(a), var).succ(),
+            Value::LevelMax(_, a, b) => self
+                .value_level_nf(&self.force(a), var)
+                .max(self.value_level_nf(&self.force(b), var)),
+            &Value::Const(c) => match self.checked_values[*c] {
+                CorePrismExpr::LevelConst(n) => LevelNf::constant(n),
+                CorePrismExpr::LevelOmega => LevelNf::omega(),
+                _ => LevelNf::atom(LevelAtom::Neutral(c)),
+            },
+            Value::Neutral(head, spine) if spine.is_empty() => match **head {
+                Head::Var(_, level) => LevelNf::atom(LevelAtom::Var(var(level))),
+                Head::Free(i) | Head::OutOfFuel(i) => LevelNf::atom(LevelAtom::Free(i)),
+                _ => LevelNf::atom(LevelAtom::Neutral(v.origin())),
+            },
+            _ => LevelNf::atom(LevelAtom::Neutral(v.origin())),
+        }
+    }
+}
@@ -19,7 +19,7 @@ impl TypecheckPrismEnv<'_> {
         var_map: &mut HashMap<UniqueVariableId, usize>,
     ) -> CoreIndex {
         let e_new = match &self.db.checked_values[*i] {
-            CorePrismExpr::Type => CorePrismExpr::Type,
+            &CorePrismExpr::Type(l) => CorePrismExpr::Type(self.simplify_inner(l, s, var_map)),
             &CorePrismExpr::Let(v, b) => {
                 let v = self.simplify_inner(v, s, var_map);
                 let id = self.new_tc_id();
@@ -58,6 +58,25 @@ impl TypecheckPrismEnv<'_> {
                 let b = self.simplify_inner(b, s, var_map);
                 CorePrismExpr::FnDestruct(a, b)
             }
+            &CorePrismExpr::PairType(a, b) => {
+                let a = self.simplify_inner(a, s, var_map);
+                let id = self.new_tc_id();
+                var_map.insert(id, var_map.len());
+                let b = self.simplify_inner(b, &s.cons(EnvEntry::RType(id)), var_map);
+                var_map.remove(&id);
+                CorePrismExpr::PairType(a, b)
+            }
+            &CorePrismExpr::PairConstruct(a, b) => {
+                let a = self.simplify_inner(a, s, var_map);
+                let b = self.simplify_inner(b, s, var_map);
+                CorePrismExpr::PairConstruct(a, b)
+            }
+            &CorePrismExpr::PairFst(p) => {
+                CorePrismExpr::PairFst(self.simplify_inner(p, s, var_map))
+            }
+            &CorePrismExpr::PairSnd(p) => {
+                CorePrismExpr::PairSnd(self.simplify_inner(p, s, var_map))
+            }
             CorePrismExpr::Free => CorePrismExpr::Free,
             &CorePrismExpr::Shift(b, i) => {
                 return self.simplify_inner(b, &s.shift(i.min(s.len())), var_map);
@@ -69,6 +88,42 @@ impl TypecheckPrismEnv<'_> {
             }
             CorePrismExpr::GrammarValue(p) => CorePrismExpr::GrammarValue(p.clone()),
             CorePrismExpr::GrammarType => CorePrismExpr::GrammarType,
+            &CorePrismExpr::Data(ref decl, b) => {
+                let decl = decl.clone();
+                let ids: Vec<_> = (0..decl.constructors.len() + 2)
+                    .map(|_| self.new_tc_id())
+                    .collect();
+                let mut s = s.clone();
+                for &id in &ids {
+                    var_map.insert(id, var_map.len());
+                    s = s.cons(EnvEntry::RType(id));
+                }
+                let b = self.simplify_inner(b, &s, var_map);
+                for id in &ids {
+                    var_map.remove(id);
+                }
+                CorePrismExpr::Data(decl, b)
+            }
+            &CorePrismExpr::DataType(d) => CorePrismExpr::DataType(d),
+            &CorePrismExpr::DataConstruct(d, k) => CorePrismExpr::DataConstruct(d, k),
+            &CorePrismExpr::DataElim(d) => CorePrismExpr::DataElim(d),
+            CorePrismExpr::Level => CorePrismExpr::Level,
+            &CorePrismExpr::LevelConst(n) => CorePrismExpr::LevelConst(n),
+            &CorePrismExpr::LevelSucc(l) => {
+                CorePrismExpr::LevelSucc(self.simplify_inner(l, s, var_map))
+            }
+            &CorePrismExpr::LevelMax(a, b) => {
+                let a = self.simplify_inner(a, s, var_map);
+                let b = self.simplify_inner(b, s, var_map);
+                CorePrismExpr::LevelMax(a, b)
+            }
+            CorePrismExpr::LevelOmega => CorePrismExpr::LevelOmega,
+            &CorePrismExpr::Paramatricity(x) => {
+                CorePrismExpr::Paramatricity(self.simplify_inner(x, s, var_map))
+            }
+            &CorePrismExpr::LiteralType(t) => CorePrismExpr::LiteralType(t),
+            CorePrismExpr::Literal(l) => CorePrismExpr::Literal(l.clone()),
+            &CorePrismExpr::PrimOp(op) => CorePrismExpr::PrimOp(op),
         };
         self.db.store_checked(e_new, self.db.checked_origins[*i])
     }
@@ -0,0 +1,43 @@
+use crate::lang::literal::{LiteralType, PrimOp};
+use crate::lang::{CorePrismExpr, PrismDb, ValueOrigin};
+
+impl PrismDb {
+    /// Returns the builtin with the given name.
+    /// Builtins are only used if a name is not defined in scope, so they can be shadowed.
+    pub fn builtin(&mut self, name: &str, origin: ValueOrigin) -> Option<CorePrismExpr> {
+        Some(match name {
+            // p => fst p
+            "fst" => {
+                let p = self.store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let fst = self.store_checked(CorePrismExpr::PairFst(p), origin);
+                CorePrismExpr::FnConstruct(fst)
+            }
+            // p => snd p
+            "snd" => {
+                let p = self.store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let snd = self.store_checked(CorePrismExpr::PairSnd(p), origin);
+                CorePrismExpr::FnConstruct(snd)
+            }
+            "Level" => CorePrismExpr::Level,
+            "lzero" => CorePrismExpr::LevelConst(0),
+            // l => lsuc l
+            "lsuc" => {
+                let l = self.store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let succ = self.store_checked(CorePrismExpr::LevelSucc(l), origin);
+                CorePrismExpr::FnConstruct(succ)
+            }
+            // a => b => lmax a b
+            "lmax" => {
+                let a = self.store_checked(CorePrismExpr::DeBruijnIndex(1), origin);
+                let b = self.store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let max = self.store_checked(CorePrismExpr::LevelMax(a, b), origin);
+                let max = self.store_checked(CorePrismExpr::FnConstruct(max), origin);
+                CorePrismExpr::FnConstruct(max)
+            }
+            "Nat" => CorePrismExpr::LiteralType(LiteralType::Nat),
+            "Int" => CorePrismExpr::LiteralType(LiteralType::Int),
+            "String" => CorePrismExpr::LiteralType(LiteralType::String),
+            _ => CorePrismExpr::PrimOp(PrimOp::from_name(name)?),
+        })
+    }
+}
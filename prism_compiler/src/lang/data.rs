@@ -0,0 +1,61 @@
+use crate::lang::env::DbEnv;
+use crate::lang::env::EnvEntry::*;
+use crate::lang::{CoreIndex, CorePrismExpr, PrismDb};
+use prism_input::input::Input;
+use std::sync::Arc;
+
+/// An inductive data type declaration `data D: A { c_1: T_1; ... };`
+///
+/// The body of a `CorePrismExpr::Data` is checked in the scope of the declaration,
+/// which binds (in order) the data type `D`, each of the constructors and the eliminator `D_elim`.
+#[derive(Clone)]
+pub struct DataDecl {
+    pub name: Input,
+    /// The type of the data type, of the form `(i_1: A_1) -> ... -> (i_n: A_n) -> Type`
+    pub typ: CoreIndex,
+    /// The number of indices `n` of the data type
+    pub indices: usize,
+    /// The `CorePrismExpr::DataType` node
+    pub type_node: CoreIndex,
+    pub constructors: Arc<[DataConstructor]>,
+    /// The type of the eliminator, valid in the scope of `D` and the constructors
+    pub elim_type: CoreIndex,
+    /// The `CorePrismExpr::DataElim` node
+    pub elim_node: CoreIndex,
+}
+
+#[derive(Clone)]
+pub struct DataConstructor {
+    pub name: Input,
+    /// The type of the constructor, valid in the scope of `D`
+    pub typ: CoreIndex,
+    /// The number of arguments the constructor takes
+    pub arity: usize,
+    /// The `CorePrismExpr::DataConstruct` node
+    pub node: CoreIndex,
+    /// The computation rule of the eliminator for this constructor.
+    /// This is a function taking the motive, the cases and the constructor arguments,
+    /// valid in the scope of `D`, the constructors and the eliminator.
+    pub step: CoreIndex,
+}
+
+impl DataDecl {
+    /// The environment the body of the declaration is reduced in
+    pub fn reduce_env(&self, s: &DbEnv) -> DbEnv {
+        let mut s = s.cons(RSubst(self.type_node, s.clone()));
+        for c in self.constructors.iter() {
+            s = s.cons(RSubst(c.node, s.clone()));
+        }
+        s.cons(RSubst(self.elim_node, s.clone()))
+    }
+}
+
+impl PrismDb {
+    /// Returns the declaration of a data type, given the index of its `CorePrismExpr::Data` node
+    pub fn data_decl(&self, d: CoreIndex) -> &Arc<DataDecl> {
+        let CorePrismExpr::Data(decl, _) = &self.checked_values[*d] else {
+            unreachable!("Data constants should refer to a data declaration")
+        };
+        decl
+    }
+}
@@ -2,6 +2,7 @@ use std::fmt::Write;
 
 use crate::lang::CoreIndex;
 use crate::lang::env::DbEnv;
+use crate::lang::level::LevelNf;
 use crate::lang::{CorePrismExpr, PrismDb};
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Default)]
@@ -10,6 +11,7 @@ pub enum PrecedenceLevel {
     Let,
     Construct,
     FnType,
+    PairType,
     TypeAssert,
     Destruct,
     Base,
@@ -20,16 +22,33 @@ impl CorePrismExpr {
     fn precedence_level(&self) -> PrecedenceLevel {
         match self {
             CorePrismExpr::Let(..) => PrecedenceLevel::Let,
+            CorePrismExpr::Data(..) => PrecedenceLevel::Let,
             CorePrismExpr::FnConstruct(..) => PrecedenceLevel::Construct,
             CorePrismExpr::FnType(..) => PrecedenceLevel::FnType,
+            CorePrismExpr::PairType(..) => PrecedenceLevel::PairType,
             CorePrismExpr::TypeAssert(..) => PrecedenceLevel::TypeAssert,
             CorePrismExpr::FnDestruct(..) => PrecedenceLevel::Destruct,
+            CorePrismExpr::PairFst(..) => PrecedenceLevel::Destruct,
+            CorePrismExpr::PairSnd(..) => PrecedenceLevel::Destruct,
+            CorePrismExpr::PairConstruct(..) => PrecedenceLevel::Base,
             CorePrismExpr::Free => PrecedenceLevel::Base,
             CorePrismExpr::Shift(..) => PrecedenceLevel::Base,
-            CorePrismExpr::Type => PrecedenceLevel::Base,
+            CorePrismExpr::Type(..) => PrecedenceLevel::Base,
             CorePrismExpr::DeBruijnIndex(..) => PrecedenceLevel::Base,
             CorePrismExpr::GrammarValue(..) => PrecedenceLevel::Base,
             CorePrismExpr::GrammarType => PrecedenceLevel::Base,
+            CorePrismExpr::DataType(..) => PrecedenceLevel::Base,
+            CorePrismExpr::DataConstruct(..) => PrecedenceLevel::Base,
+            CorePrismExpr::DataElim(..) => PrecedenceLevel::Base,
+            CorePrismExpr::Level => PrecedenceLevel::Base,
+            CorePrismExpr::LevelConst(..) => PrecedenceLevel::Base,
+            CorePrismExpr::LevelSucc(..) => PrecedenceLevel::Destruct,
+            CorePrismExpr::LevelMax(..) => PrecedenceLevel::Destruct,
+            CorePrismExpr::LevelOmega => PrecedenceLevel::Base,
+            CorePrismExpr::Paramatricity(..) => PrecedenceLevel::Destruct,
+            CorePrismExpr::LiteralType(..) => PrecedenceLevel::Base,
+            CorePrismExpr::Literal(..) => PrecedenceLevel::Base,
+            CorePrismExpr::PrimOp(..) => PrecedenceLevel::Base,
         }
     }
 }
@@ -40,6 +59,7 @@ impl PrismDb {
         i: CoreIndex,
         w: &mut impl Write,
         max_precedence: PrecedenceLevel,
+        names: &mut Vec<Option<String>>,
     ) -> std::fmt::Result {
         let e = &self.checked_values[*i];
 
@@ -48,38 +68,61 @@ impl PrismDb {
         }
 
         match e {
-            CorePrismExpr::Type => write!(w, "Type")?,
+            &CorePrismExpr::Type(l) => self.display_type(l, w)?,
             &CorePrismExpr::Let(v, b) => {
                 write!(w, "let ")?;
-                self.display(v, w, PrecedenceLevel::Construct)?;
+                self.display(v, w, PrecedenceLevel::Construct, names)?;
                 writeln!(w, ";")?;
-                self.display(b, w, PrecedenceLevel::Let)?;
+                self.display_under(b, w, PrecedenceLevel::Let, names, [None])?;
             }
-            &CorePrismExpr::DeBruijnIndex(i) => write!(w, "#{i}")?,
+            &CorePrismExpr::DeBruijnIndex(i) => match names.iter().rev().nth(i) {
+                Some(Some(name)) => write!(w, "{name}")?,
+                _ => write!(w, "#{i}")?,
+            },
             &CorePrismExpr::FnType(a, b) => {
-                self.display(a, w, PrecedenceLevel::TypeAssert)?;
+                self.display(a, w, PrecedenceLevel::TypeAssert, names)?;
                 write!(w, " -> ")?;
-                self.display(b, w, PrecedenceLevel::FnType)?;
+                self.display_under(b, w, PrecedenceLevel::FnType, names, [None])?;
             }
             &CorePrismExpr::FnConstruct(b) => {
                 write!(w, "=> ")?;
-                self.display(b, w, PrecedenceLevel::Construct)?;
+                self.display_under(b, w, PrecedenceLevel::Construct, names, [None])?;
             }
             &CorePrismExpr::FnDestruct(a, b) => {
-                self.display(a, w, PrecedenceLevel::Destruct)?;
+                self.display(a, w, PrecedenceLevel::Destruct, names)?;
                 write!(w, " ")?;
-                self.display(b, w, PrecedenceLevel::Base)?;
+                self.display(b, w, PrecedenceLevel::Base, names)?;
+            }
+            &CorePrismExpr::PairType(a, b) => {
+                self.display(a, w, PrecedenceLevel::TypeAssert, names)?;
+                write!(w, " * ")?;
+                self.display_under(b, w, PrecedenceLevel::PairType, names, [None])?;
+            }
+            &CorePrismExpr::PairConstruct(a, b) => {
+                write!(w, "(")?;
+                self.display(a, w, PrecedenceLevel::default(), names)?;
+                write!(w, ", ")?;
+                self.display(b, w, PrecedenceLevel::default(), names)?;
+                write!(w, ")")?;
+            }
+            &CorePrismExpr::PairFst(p) => {
+                write!(w, "fst ")?;
+                self.display(p, w, PrecedenceLevel::Base, names)?;
+            }
+            &CorePrismExpr::PairSnd(p) => {
+                write!(w, "snd ")?;
+                self.display(p, w, PrecedenceLevel::Base, names)?;
             }
             CorePrismExpr::Free => write!(w, "{{{}}}", i.0)?,
             &CorePrismExpr::Shift(v, i) => {
                 write!(w, "([SHIFT {i}] ")?;
-                self.display(v, w, PrecedenceLevel::default())?;
+                self.display(v, w, PrecedenceLevel::default(), names)?;
                 write!(w, ")")?;
             }
             &CorePrismExpr::TypeAssert(e, typ) => {
-                self.display(e, w, PrecedenceLevel::Destruct)?;
+                self.display(e, w, PrecedenceLevel::Destruct, names)?;
                 write!(w, ": ")?;
-                self.display(typ, w, PrecedenceLevel::Destruct)?;
+                self.display(typ, w, PrecedenceLevel::Destruct, names)?;
             }
             CorePrismExpr::GrammarValue(_) => {
                 write!(w, "[GRAMMAR]")?;
@@ -87,6 +130,53 @@ impl PrismDb {
             CorePrismExpr::GrammarType => {
                 write!(w, "Grammar")?;
             }
+            &CorePrismExpr::Data(ref decl, b) => {
+                write!(w, "data {}: ", decl.name)?;
+                self.display(decl.typ, w, PrecedenceLevel::Construct, names)?;
+                writeln!(w, " {{")?;
+                let data_name = Some(decl.name.to_string());
+                for c in decl.constructors.iter() {
+                    write!(w, "    {}: ", c.name)?;
+                    self.display_under(
+                        c.typ,
+                        w,
+                        PrecedenceLevel::default(),
+                        names,
+                        [data_name.clone()],
+                    )?;
+                    writeln!(w, ";")?;
+                }
+                writeln!(w, "}};")?;
+                let bound = std::iter::once(data_name)
+                    .chain(decl.constructors.iter().map(|c| Some(c.name.to_string())))
+                    .chain([Some(format!("{}_elim", decl.name))]);
+                self.display_under(b, w, PrecedenceLevel::Let, names, bound)?;
+            }
+            &CorePrismExpr::DataType(d) => write!(w, "{}", self.data_decl(d).name)?,
+            &CorePrismExpr::DataConstruct(d, k) => {
+                write!(w, "{}", self.data_decl(d).constructors[k].name)?
+            }
+            &CorePrismExpr::DataElim(d) => write!(w, "{}_elim", self.data_decl(d).name)?,
+            CorePrismExpr::Level => write!(w, "Level")?,
+            CorePrismExpr::LevelConst(n) => write!(w, "{n}")?,
+            &CorePrismExpr::LevelSucc(l) => {
+                write!(w, "lsuc ")?;
+                self.display(l, w, PrecedenceLevel::Base, names)?;
+            }
+            &CorePrismExpr::LevelMax(a, b) => {
+                write!(w, "lmax ")?;
+                self.display(a, w, PrecedenceLevel::Base, names)?;
+                write!(w, " ")?;
+                self.display(b, w, PrecedenceLevel::Base, names)?;
+            }
+            CorePrismExpr::LevelOmega => write!(w, "ω")?,
+            &CorePrismExpr::Paramatricity(x) => {
+                write!(w, "paramatricity ")?;
+                self.display(x, w, PrecedenceLevel::Base, names)?;
+            }
+            CorePrismExpr::LiteralType(t) => write!(w, "{t}")?,
+            CorePrismExpr::Literal(l) => write!(w, "{l}")?,
+            CorePrismExpr::PrimOp(op) => write!(w, "{}", op.name())?,
         }
 
         if e.precedence_level() < max_precedence {
@@ -96,9 +186,108 @@ impl PrismDb {
         Ok(())
     }
 
+    /// Displays `i`, which is in scope of the variables of `names` followed by the variables of `bound`
+    fn display_under(
+        &self,
+        i: CoreIndex,
+        w: &mut impl Write,
+        max_precedence: PrecedenceLevel,
+        names: &mut Vec<Option<String>>,
+        bound: impl IntoIterator<Item = Option<String>>,
+    ) -> std::fmt::Result {
+        let len = names.len();
+        names.extend(bound);
+        let result = self.display(i, w, max_precedence, names);
+        names.truncate(len);
+        result
+    }
+
+    /// Displays the universe at level `l`, which is shown as just `Type` if the level is not known yet
+    fn display_type(&self, l: CoreIndex, w: &mut impl Write) -> std::fmt::Result {
+        let Some(nf) = self.syntactic_level_nf(l, &[]) else {
+            return write!(w, "Type");
+        };
+        if nf.omega {
+            return write!(w, "Type.{{ω}}");
+        }
+        if nf.atoms.is_empty() {
+            return write!(w, "Type {}", nf.constant);
+        }
+
+        // Display as `lmax a (lmax b ...)`, where each part is of the form `lsuc (lsuc .. #v)`
+        let mut parts = vec![];
+        if nf.atoms.iter().all(|&(_, k)| k < nf.constant) {
+            parts.push(nf.constant.to_string());
+        }
+        for &(v, k) in &nf.atoms {
+            let mut part = format!("#{v}");
+            for _ in 0..k {
+                part = if part.contains(' ') {
+                    format!("lsuc ({part})")
+                } else {
+                    format!("lsuc {part}")
+                };
+            }
+            parts.push(part);
+        }
+        let level = parts
+            .into_iter()
+            .rev()
+            .reduce(|rest, part| {
+                let wrap = |p: String| if p.contains(' ') { format!("({p})") } else { p };
+                format!("lmax {} {}", wrap(part), wrap(rest))
+            })
+            .expect("Level has at least one part");
+        write!(w, "Type.{{{level}}}")
+    }
+
+    /// Computes the normal form of the level `l` without an environment, for display purposes.
+    /// The atoms are the de Bruijn indices of the free variables of `l`.
+    /// `env` contains the levels of the variables bound inside `l`.
+    /// Returns `None` if the level is not known yet.
+    fn syntactic_level_nf(
+        &self,
+        l: CoreIndex,
+        env: &[Option<LevelNf<usize>>],
+    ) -> Option<LevelNf<usize>> {
+        Some(match self.checked_values[*l] {
+            CorePrismExpr::LevelConst(n) => LevelNf::constant(n),
+            CorePrismExpr::LevelSucc(a) => self.syntactic_level_nf(a, env)?.succ(),
+            CorePrismExpr::LevelMax(a, b) => self
+                .syntactic_level_nf(a, env)?
+                .max(self.syntactic_level_nf(b, env)?),
+            CorePrismExpr::LevelOmega => LevelNf::omega(),
+            CorePrismExpr::DeBruijnIndex(v) => match env.len().checked_sub(v + 1) {
+                Some(i) => env[i].clone()?,
+                None => LevelNf::atom(v - env.len()),
+            },
+            CorePrismExpr::Let(v, b) => {
+                let mut env = env.to_vec();
+                env.push(self.syntactic_level_nf(v, &env));
+                self.syntactic_level_nf(b, &env)?
+            }
+            CorePrismExpr::Shift(v, shift) => match env.len().checked_sub(shift) {
+                Some(len) => self.syntactic_level_nf(v, &env[..len])?,
+                None => {
+                    let mut nf = self.syntactic_level_nf(v, &[])?;
+                    for (a, _) in &mut nf.atoms {
+                        *a += shift - env.len();
+                    }
+                    nf
+                }
+            },
+            _ => return None,
+        })
+    }
+
     pub fn index_to_string(&self, i: CoreIndex) -> String {
+        self.index_to_named_string(i, &[])
+    }
+
+    /// Displays `i`, where the free variables are named by `names`, the last name being the innermost variable
+    pub fn index_to_named_string(&self, i: CoreIndex, names: &[Option<String>]) -> String {
         let mut s = String::new();
-        self.display(i, &mut s, PrecedenceLevel::default())
+        self.display(i, &mut s, PrecedenceLevel::default(), &mut names.to_vec())
             .expect("Writing to String shouldn't fail");
         s
     }
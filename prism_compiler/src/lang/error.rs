@@ -1,9 +1,12 @@
 use crate::args::ErrorFormat;
 use crate::lang::PrismDb;
-use prism_diag::{RenderConfig, RenderFormat};
+use prism_diag::sarif::SarifLog;
+use prism_diag::{Diag, RenderConfig, RenderFormat};
 use std::mem;
 
 impl PrismDb {
+    /// Prints the reported diagnostics and forgets them.
+    /// In the SARIF format this prints a complete log, so the diagnostics of a run should be printed at once.
     pub fn eprint_errors(&mut self) {
         let errors = mem::take(&mut self.diags);
 
@@ -14,6 +17,17 @@ impl PrismDb {
             ErrorFormat::Plain => RenderConfig {
                 format: RenderFormat::Plain,
             },
+            ErrorFormat::Json => {
+                for error in errors {
+                    eprintln!("{}", error.render_json(&self.input.inner()));
+                }
+                return;
+            }
+            ErrorFormat::Sarif => {
+                let log = SarifLog::new("prism", &errors, &self.input.inner());
+                eprintln!("{}", log.render());
+                return;
+            }
         };
 
         for error in errors {
@@ -21,8 +35,13 @@ impl PrismDb {
         }
     }
 
+    /// Whether any of the reported diagnostics is an error, rather than a warning or note
+    pub fn has_errors(&self) -> bool {
+        self.diags.iter().any(Diag::is_error)
+    }
+
     pub fn assert_no_errors(&mut self) {
-        if !self.diags.is_empty() {
+        if self.has_errors() {
             self.eprint_errors();
             panic!("Errors encountered, see above");
         }
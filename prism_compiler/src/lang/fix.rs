@@ -0,0 +1,70 @@
+use crate::lang::PrismDb;
+use prism_diag::sugg::Applicability;
+use std::collections::HashMap;
+
+impl PrismDb {
+    /// Applies the machine-applicable suggestions of the reported diagnostics to the files on disk.
+    /// Suggestions that overlap an earlier suggestion are skipped, they can be applied by running again.
+    pub fn apply_fixes(&mut self) {
+        let mut fixes: HashMap<_, Vec<_>> = HashMap::new();
+        for sugg in self.diags.iter().flat_map(|diag| &diag.suggestions) {
+            if sugg.applicability == Applicability::MachineApplicable {
+                let file = sugg.span.start_pos().file();
+                fixes.entry(file).or_default().push(sugg);
+            }
+        }
+
+        let input = self.input.inner();
+        for (file, mut suggs) in fixes {
+            let path = input.get_path(file);
+            // Programs read from stdin can't be fixed
+            if !path.is_file() {
+                continue;
+            }
+            suggs.sort_by_key(|sugg| sugg.span.start_pos().idx_in_file());
+
+            let source = input.get_str(file);
+            let mut fixed = String::with_capacity(source.len());
+            let mut copied_until = 0;
+            let mut applied = 0;
+            for sugg in suggs {
+                let start = sugg.span.start_pos().idx_in_file();
+                if start < copied_until {
+                    continue;
+                }
+                fixed.push_str(&source[copied_until..start]);
+                fixed.push_str(&sugg.replacement);
+                copied_until = sugg.span.end_pos().idx_in_file();
+                applied += 1;
+            }
+            fixed.push_str(&source[copied_until..]);
+
+            match std::fs::write(path, fixed) {
+                Ok(()) => eprintln!("Applied {applied} fix(es) to `{}`\n", path.display()),
+                Err(err) => eprintln!("Failed to write fixes to `{}`: {err}\n", path.display()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::PrismDb;
+
+    #[test]
+    fn similar_names_are_not_fixed() {
+        let source = "let xyz = Type; let abc = xyx; abd";
+        let path = std::env::temp_dir().join(format!("prism_fix_{}.pr", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+
+        let mut db = PrismDb::default();
+        let file = db.load_file(path.clone()).unwrap();
+        db.process_file(file);
+        assert!(!db.diags.is_empty());
+        db.apply_fixes();
+
+        let fixed = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(fixed, source);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::lang::data::{DataConstructor, DataDecl};
+use crate::lang::env::{DbEnv, EnvEntry};
+use crate::lang::modules::Export;
+use crate::lang::statements::StatementsScope;
+use crate::lang::{CoreIndex, CorePrismExpr, PrismDb, ProcessedFileTableEntry, ValueOrigin};
+use crate::parser::ParsedPrismExpr;
+use std::sync::Arc;
+
+impl CorePrismExpr {
+    /// Returns this expression with each `CoreIndex` it refers to replaced by `f`
+    pub fn map_indices(&self, f: &mut impl FnMut(CoreIndex) -> CoreIndex) -> Self {
+        match *self {
+            CorePrismExpr::Free => CorePrismExpr::Free,
+            CorePrismExpr::Type(l) => CorePrismExpr::Type(f(l)),
+            CorePrismExpr::Let(v, b) => CorePrismExpr::Let(f(v), f(b)),
+            CorePrismExpr::DeBruijnIndex(i) => CorePrismExpr::DeBruijnIndex(i),
+            CorePrismExpr::FnType(a, b) => CorePrismExpr::FnType(f(a), f(b)),
+            CorePrismExpr::FnConstruct(b) => CorePrismExpr::FnConstruct(f(b)),
+            CorePrismExpr::FnDestruct(a, b) => CorePrismExpr::FnDestruct(f(a), f(b)),
+            CorePrismExpr::Shift(b, i) => CorePrismExpr::Shift(f(b), i),
+            CorePrismExpr::TypeAssert(e, t) => CorePrismExpr::TypeAssert(f(e), f(t)),
+            CorePrismExpr::GrammarValue(ref g) => CorePrismExpr::GrammarValue(g.clone()),
+            CorePrismExpr::GrammarType => CorePrismExpr::GrammarType,
+            CorePrismExpr::PairType(a, b) => CorePrismExpr::PairType(f(a), f(b)),
+            CorePrismExpr::PairConstruct(a, b) => CorePrismExpr::PairConstruct(f(a), f(b)),
+            CorePrismExpr::PairFst(p) => CorePrismExpr::PairFst(f(p)),
+            CorePrismExpr::PairSnd(p) => CorePrismExpr::PairSnd(f(p)),
+            CorePrismExpr::Data(ref decl, b) => {
+                CorePrismExpr::Data(Arc::new(decl.map_indices(f)), f(b))
+            }
+            CorePrismExpr::DataType(d) => CorePrismExpr::DataType(f(d)),
+            CorePrismExpr::DataConstruct(d, k) => CorePrismExpr::DataConstruct(f(d), k),
+            CorePrismExpr::DataElim(d) => CorePrismExpr::DataElim(f(d)),
+            CorePrismExpr::Level => CorePrismExpr::Level,
+            CorePrismExpr::LevelConst(n) => CorePrismExpr::LevelConst(n),
+            CorePrismExpr::LevelSucc(l) => CorePrismExpr::LevelSucc(f(l)),
+            CorePrismExpr::LevelMax(a, b) => CorePrismExpr::LevelMax(f(a), f(b)),
+            CorePrismExpr::LevelOmega => CorePrismExpr::LevelOmega,
+            CorePrismExpr::Paramatricity(x) => CorePrismExpr::Paramatricity(f(x)),
+            CorePrismExpr::LiteralType(t) => CorePrismExpr::LiteralType(t),
+            CorePrismExpr::Literal(ref l) => CorePrismExpr::Literal(l.clone()),
+            CorePrismExpr::PrimOp(op) => CorePrismExpr::PrimOp(op),
+        }
+    }
+}
+
+impl DataDecl {
+    fn map_indices(&self, f: &mut impl FnMut(CoreIndex) -> CoreIndex) -> Self {
+        DataDecl {
+            name: self.name.clone(),
+            typ: f(self.typ),
+            indices: self.indices,
+            type_node: f(self.type_node),
+            constructors: self
+                .constructors
+                .iter()
+                .map(|c| DataConstructor {
+                    name: c.name.clone(),
+                    typ: f(c.typ),
+                    arity: c.arity,
+                    node: f(c.node),
+                    step: f(c.step),
+                })
+                .collect(),
+            elim_type: f(self.elim_type),
+            elim_node: f(self.elim_node),
+        }
+    }
+}
+
+impl ValueOrigin {
+    fn map_indices(self, f: &mut impl FnMut(CoreIndex) -> CoreIndex) -> Self {
+        match self {
+            ValueOrigin::SourceCode(span) => ValueOrigin::SourceCode(span),
+            ValueOrigin::TypeOf(i) => ValueOrigin::TypeOf(f(i)),
+            ValueOrigin::FreeSub(i) => ValueOrigin::FreeSub(f(i)),
+            ValueOrigin::Failure => ValueOrigin::Failure,
+        }
+    }
+}
+
+impl EnvEntry {
+    fn map_indices(&self, f: &mut impl FnMut(CoreIndex) -> CoreIndex) -> Self {
+        match *self {
+            EnvEntry::CType(id, t) => EnvEntry::CType(id, f(t)),
+            EnvEntry::CSubst(v, t) => EnvEntry::CSubst(f(v), f(t)),
+            EnvEntry::RType(id) => EnvEntry::RType(id),
+            EnvEntry::RSubst(v, ref env) => EnvEntry::RSubst(f(v), map_env_indices(env, f)),
+        }
+    }
+}
+
+fn map_env_indices(env: &DbEnv, f: &mut impl FnMut(CoreIndex) -> CoreIndex) -> DbEnv {
+    let entries: Vec<_> = env.iter().map(|(_, e)| e.map_indices(f)).collect();
+    entries
+        .into_iter()
+        .rev()
+        .fold(DbEnv::default(), |env, e| env.cons(e))
+}
+
+impl PrismDb {
+    /// Removes the checked values that can't be reached from the files that are processed,
+    /// such as the values of files that changed since they were processed.
+    /// The `CoreIndex`es of the processed files are remapped, any other `CoreIndex` is no longer valid afterward.
+    pub fn collect_garbage(&mut self) {
+        self.collect_garbage_keeping(&mut StatementsScope::default());
+    }
+
+    /// Like `collect_garbage`, but also keeps the values of the statements of `scope`, which is remapped
+    pub fn collect_garbage_keeping(&mut self, scope: &mut StatementsScope) {
+        // Mark the values reachable from the processed files and from the scope
+        let mut live = vec![false; self.checked_values.len()];
+        let mut todo: Vec<CoreIndex> = self
+            .files
+            .values()
+            .flat_map(|entry| match entry {
+                ProcessedFileTableEntry::Processing => {
+                    unreachable!("Garbage is not collected while processing a file")
+                }
+                ProcessedFileTableEntry::Processed(file) => [file.core, file.typ]
+                    .into_iter()
+                    .chain(file.exports.iter().map(|e| e.node)),
+            })
+            .collect();
+        map_env_indices(&scope.env, &mut |i| {
+            todo.push(i);
+            i
+        });
+        while let Some(i) = todo.pop() {
+            if live[*i] {
+                continue;
+            }
+            live[*i] = true;
+
+            let mut mark = |j: CoreIndex| {
+                todo.push(j);
+                j
+            };
+            self.checked_values[*i].map_indices(&mut mark);
+            self.checked_origins[*i].map_indices(&mut mark);
+            if let Some(&t) = self.checked_types.get(&i) {
+                todo.push(t);
+            }
+        }
+
+        // Assign the new indices, values keep their relative order
+        let mut new_indices = vec![None; live.len()];
+        let mut next = 0;
+        for (i, &is_live) in live.iter().enumerate() {
+            if is_live {
+                new_indices[i] = Some(CoreIndex(next));
+                next += 1;
+            }
+        }
+        let mut remap =
+            |i: CoreIndex| new_indices[*i].expect("Values referred to by live values are live");
+
+        // Move the live values
+        let values = std::mem::take(&mut self.checked_values);
+        let origins = std::mem::take(&mut self.checked_origins);
+        for ((e, origin), is_live) in values.iter().zip(origins).zip(&live) {
+            if *is_live {
+                self.checked_values.push(e.map_indices(&mut remap));
+                self.checked_origins.push(origin.map_indices(&mut remap));
+            }
+        }
+        self.checked_types = std::mem::take(&mut self.checked_types)
+            .into_iter()
+            .filter_map(|(i, t)| Some((new_indices[*i]?, remap(t))))
+            .collect();
+        self.holes = std::mem::take(&mut self.holes)
+            .into_iter()
+            .filter_map(|(i, hole)| Some((new_indices[*i]?, hole)))
+            .collect();
+        self.scope_names = std::mem::take(&mut self.scope_names)
+            .into_iter()
+            .filter_map(|(i, names)| Some((new_indices[*i]?, names)))
+            .collect();
+
+        // Remap the processed files and the scope
+        scope.env = map_env_indices(&scope.env, &mut remap);
+        let remap_exports = |exports: &Arc<[Export]>| -> Arc<[Export]> {
+            exports
+                .iter()
+                .map(|e| Export {
+                    node: new_indices[*e.node].expect("Exports are live"),
+                    names: e.names.clone(),
+                })
+                .collect()
+        };
+        for entry in self.files.values_mut() {
+            let ProcessedFileTableEntry::Processed(file) = entry else {
+                unreachable!()
+            };
+            file.core = new_indices[*file.core].expect("Processed files are live");
+            file.typ = new_indices[*file.typ].expect("Processed files are live");
+            file.exports = remap_exports(&file.exports);
+        }
+
+        // Parsed values of files that changed can refer to values that were removed, they are never used again
+        for e in self.parsed_values.iter_mut() {
+            match e {
+                ParsedPrismExpr::Include(_, core) => match new_indices[**core] {
+                    Some(new) => *core = new,
+                    None => *e = ParsedPrismExpr::Free,
+                },
+                ParsedPrismExpr::Module(_, exports) => {
+                    if exports.iter().all(|e| new_indices[*e.node].is_some()) {
+                        *exports = remap_exports(exports);
+                    } else {
+                        *e = ParsedPrismExpr::Free;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -3,7 +3,6 @@ use prism_diag::Diag;
 use prism_input::input_table::InputTableIndex;
 use prism_parser::META_GRAMMAR;
 use prism_parser::core::tokens::Tokens;
-use prism_parser::error::ParseError;
 use prism_parser::error::set_error::SetError;
 use prism_parser::grammar::grammar_file::GrammarFile;
 use prism_parser::parser::instance::run_parser_rule;
@@ -23,10 +22,6 @@ impl PrismDb {
             HashMap::new(),
             &mut (),
         );
-        (
-            gram,
-            tokens,
-            errs.errors.iter().map(SetError::diag).collect(),
-        )
+        (gram, tokens, errs.diags().collect())
     }
 }
@@ -0,0 +1,127 @@
+use crate::lang::env::DbEnv;
+use crate::lang::{CoreIndex, CorePrismExpr, PrismDb};
+use crate::type_check::UniqueVariableId;
+use std::collections::HashMap;
+
+/// A universe level in normal form `max(c, a_1 + k_1, ..., a_n + k_n)`, or `ω`
+#[derive(Clone, Debug)]
+pub struct LevelNf<A> {
+    pub constant: usize,
+    pub atoms: Vec<(A, usize)>,
+    pub omega: bool,
+}
+
+impl<A: PartialEq> LevelNf<A> {
+    pub fn constant(constant: usize) -> Self {
+        Self {
+            constant,
+            atoms: vec![],
+            omega: false,
+        }
+    }
+
+    pub fn atom(atom: A) -> Self {
+        Self {
+            constant: 0,
+            atoms: vec![(atom, 0)],
+            omega: false,
+        }
+    }
+
+    pub fn omega() -> Self {
+        Self {
+            constant: 0,
+            atoms: vec![],
+            omega: true,
+        }
+    }
+
+    pub fn succ(mut self) -> Self {
+        self.constant += 1;
+        for (_, k) in &mut self.atoms {
+            *k += 1;
+        }
+        self
+    }
+
+    pub fn max(mut self, other: Self) -> Self {
+        self.constant = self.constant.max(other.constant);
+        self.omega |= other.omega;
+        for (atom, k) in other.atoms {
+            match self.atoms.iter_mut().find(|(a, _)| *a == atom) {
+                Some((_, k2)) => *k2 = (*k2).max(k),
+                None => self.atoms.push((atom, k)),
+            }
+        }
+        self
+    }
+
+    /// Returns whether this level is at most `other`, for all values of the atoms
+    pub fn leq(&self, other: &Self) -> bool {
+        if other.omega {
+            return true;
+        }
+        if self.omega {
+            return false;
+        }
+        // Atoms are at least 0, so `a + k` is at least `k`
+        let constant_leq =
+            self.constant <= other.constant || other.atoms.iter().any(|&(_, k)| self.constant <= k);
+        constant_leq
+            && self
+                .atoms
+                .iter()
+                .all(|(a, k)| other.atoms.iter().any(|(b, l)| a == b && k <= l))
+    }
+
+    pub fn mentions(&self, atom: &A) -> bool {
+        self.atoms.iter().any(|(a, _)| a == atom)
+    }
+}
+
+/// The variables a level in normal form can be made of
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum LevelAtom<V = UniqueVariableId> {
+    Var(V),
+    Free(CoreIndex),
+    /// A level that is stuck for another reason, for example a variable applied to an argument
+    Neutral(CoreIndex),
+}
+
+impl<V> LevelNf<LevelAtom<V>> {
+    /// Returns whether this level depends on a level that is not inferred yet
+    pub fn has_free(&self) -> bool {
+        self.atoms
+            .iter()
+            .any(|(a, _)| matches!(a, LevelAtom::Free(_)))
+    }
+}
+
+impl CorePrismExpr {
+    /// Returns whether this is a level constructed from the level operations
+    pub fn is_level_value(&self) -> bool {
+        matches!(
+            self,
+            CorePrismExpr::LevelConst(_)
+                | CorePrismExpr::LevelSucc(_)
+                | CorePrismExpr::LevelMax(_, _)
+                | CorePrismExpr::LevelOmega
+        )
+    }
+}
+
+impl PrismDb {
+    /// Computes the normal form of the level `l` in `s`
+    pub fn level_nf(&mut self, l: CoreIndex, s: &DbEnv) -> LevelNf<LevelAtom> {
+        self.reducing(l, |db| {
+            let mut vars = HashMap::new();
+            let env = db.nbe_env(s, l, &mut HashMap::new(), &mut vars);
+            let mut ids = vec![UniqueVariableId::DUMMY; vars.len()];
+            for (id, level) in vars {
+                ids[level] = id;
+            }
+            let v = db.eval(l, &env);
+            db.value_level_nf(&v, &|level| ids[level])
+        })
+    }
+}
@@ -0,0 +1,215 @@
+use crate::lang::{CoreIndex, PrismDb};
+use prism_diag_derive::Diagnostic;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// The builtin types of literals, these are opaque to the type checker
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LiteralType {
+    Nat,
+    Int,
+    String,
+}
+
+/// A literal value, of the corresponding `LiteralType`
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Literal {
+    Nat(u64),
+    Int(i64),
+    String(Arc<str>),
+}
+
+/// A builtin operation on literals, which is evaluated natively once its arguments are literals
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PrimOp {
+    NatAdd,
+    NatMul,
+    NatLt,
+    NatEq,
+    IntAdd,
+    IntMul,
+    IntLt,
+    IntEq,
+    StringConcat,
+    StringEq,
+}
+
+impl Literal {
+    pub fn typ(&self) -> LiteralType {
+        match self {
+            Literal::Nat(_) => LiteralType::Nat,
+            Literal::Int(_) => LiteralType::Int,
+            Literal::String(_) => LiteralType::String,
+        }
+    }
+}
+
+/// The result of applying a `PrimOp` to literals
+pub enum PrimResult {
+    Literal(Literal),
+    /// The result of a comparison, which selects one of the two values given after the type
+    Bool(bool),
+    /// The result does not fit in a literal, the application does not reduce and `LiteralOverflow` is reported
+    Overflow,
+}
+
+/// The result of a primitive operation is too large to be represented,
+/// literals of type `Nat` can be at most `18446744073709551615`,
+/// and literals of type `Int` must be between `-9223372036854775808` and `+9223372036854775807`.
+///
+/// The application of the operation does not reduce, so a type that depends on its result can't be checked:
+///
+/// ```prism
+/// let x = nat_add 18446744073709551615 1;
+/// (Type : nat_eq x 0 (Type 1) Type Type)
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("The result of `{}` does not fit in a literal", self.op.name()), env = PrismDb)]
+pub struct LiteralOverflow {
+    #[sugg(label = "This operation overflowed")]
+    pub term: CoreIndex,
+    pub op: PrimOp,
+}
+
+impl PrimOp {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "nat_add" => PrimOp::NatAdd,
+            "nat_mul" => PrimOp::NatMul,
+            "nat_lt" => PrimOp::NatLt,
+            "nat_eq" => PrimOp::NatEq,
+            "int_add" => PrimOp::IntAdd,
+            "int_mul" => PrimOp::IntMul,
+            "int_lt" => PrimOp::IntLt,
+            "int_eq" => PrimOp::IntEq,
+            "string_concat" => PrimOp::StringConcat,
+            "string_eq" => PrimOp::StringEq,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PrimOp::NatAdd => "nat_add",
+            PrimOp::NatMul => "nat_mul",
+            PrimOp::NatLt => "nat_lt",
+            PrimOp::NatEq => "nat_eq",
+            PrimOp::IntAdd => "int_add",
+            PrimOp::IntMul => "int_mul",
+            PrimOp::IntLt => "int_lt",
+            PrimOp::IntEq => "int_eq",
+            PrimOp::StringConcat => "string_concat",
+            PrimOp::StringEq => "string_eq",
+        }
+    }
+
+    /// The type of both arguments of this operation
+    pub fn operand_type(self) -> LiteralType {
+        match self {
+            PrimOp::NatAdd | PrimOp::NatMul | PrimOp::NatLt | PrimOp::NatEq => LiteralType::Nat,
+            PrimOp::IntAdd | PrimOp::IntMul | PrimOp::IntLt | PrimOp::IntEq => LiteralType::Int,
+            PrimOp::StringConcat | PrimOp::StringEq => LiteralType::String,
+        }
+    }
+
+    /// Comparisons are Church encoded booleans, `nat_lt a b : (T: Type) -> T -> T -> T`
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            PrimOp::NatLt | PrimOp::NatEq | PrimOp::IntLt | PrimOp::IntEq | PrimOp::StringEq
+        )
+    }
+
+    /// The number of arguments this operation needs before it reduces
+    pub fn arity(self) -> usize {
+        if self.is_comparison() { 5 } else { 2 }
+    }
+
+    /// Applies this operation to the literals `a` and `b`.
+    /// Returns `None` if the literals are not of the operand type.
+    pub fn apply(self, a: &Literal, b: &Literal) -> Option<PrimResult> {
+        if a.typ() != self.operand_type() || b.typ() != self.operand_type() {
+            return None;
+        }
+        let literal = match (self, a, b) {
+            (PrimOp::NatAdd, &Literal::Nat(a), &Literal::Nat(b)) => {
+                a.checked_add(b).map(Literal::Nat)
+            }
+            (PrimOp::NatMul, &Literal::Nat(a), &Literal::Nat(b)) => {
+                a.checked_mul(b).map(Literal::Nat)
+            }
+            (PrimOp::IntAdd, &Literal::Int(a), &Literal::Int(b)) => {
+                a.checked_add(b).map(Literal::Int)
+            }
+            (PrimOp::IntMul, &Literal::Int(a), &Literal::Int(b)) => {
+                a.checked_mul(b).map(Literal::Int)
+            }
+            (PrimOp::StringConcat, Literal::String(a), Literal::String(b)) => {
+                Some(Literal::String(format!("{a}{b}").into()))
+            }
+            (PrimOp::NatLt, Literal::Nat(a), Literal::Nat(b)) => {
+                return Some(PrimResult::Bool(a < b));
+            }
+            (PrimOp::IntLt, Literal::Int(a), Literal::Int(b)) => {
+                return Some(PrimResult::Bool(a < b));
+            }
+            (PrimOp::NatEq | PrimOp::IntEq | PrimOp::StringEq, a, b) => {
+                return Some(PrimResult::Bool(a == b));
+            }
+            _ => return None,
+        };
+        Some(literal.map_or(PrimResult::Overflow, PrimResult::Literal))
+    }
+}
+
+impl Display for LiteralType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LiteralType::Nat => write!(f, "Nat"),
+            LiteralType::Int => write!(f, "Int"),
+            LiteralType::String => write!(f, "String"),
+        }
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Nat(n) => write!(f, "{n}"),
+            Literal::Int(n) => write!(f, "{n:+}"),
+            Literal::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '"' => write!(f, "\\\"")?,
+                        c => write!(f, "{c}")?,
+                    }
+                }
+                write!(f, "\"")
+            }
+        }
+    }
+}
+
+/// Parses the contents of a string literal, returns `None` if it contains an unknown escape sequence
+pub fn unescape(s: &str) -> Option<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        result.push(match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            '\\' => '\\',
+            '"' => '"',
+            _ => return None,
+        });
+    }
+    Some(result)
+}
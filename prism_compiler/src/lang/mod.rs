@@ -1,21 +1,36 @@
 use crate::args::PrismArgs;
-use crate::parser::{GRAMMAR, ParserPrismEnv};
-use prism_diag::{Diag, IntoDiag};
+use crate::interp::fuel::Fuel;
+use crate::lang::data::DataDecl;
+use crate::lang::literal::{Literal, LiteralType, PrimOp};
+use crate::lang::modules::{Export, ModuleGraph};
+use crate::parser::named_env::NamesEnv;
+use crate::parser::{GRAMMAR, ParsedIndex, ParsedPrismExpr, ParserPrismEnv};
+use prism_diag::Diag;
 use prism_input::input_table::{InputTable, InputTableIndex};
 use prism_input::span::Span;
 use prism_parser::core::tokens::Tokens;
+use prism_parser::error::set_error::SetError;
 use prism_parser::grammar::grammar_file::GrammarFile;
+use prism_parser::parser::instance::IncrementalParser;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::fmt::{Display, Formatter};
 use std::ops::Deref;
 use std::sync::Arc;
 
+mod builtins;
+pub mod data;
 mod diags;
 pub mod display;
 pub mod env;
 pub mod error;
+mod fix;
+mod gc;
 pub mod grammar;
+pub mod level;
+pub mod literal;
+pub mod modules;
+pub mod statements;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub enum ValueOrigin {
@@ -49,7 +64,8 @@ impl Deref for CoreIndex {
 #[derive(Clone)]
 pub enum CorePrismExpr {
     Free,
-    Type,
+    /// The universe at the given level
+    Type(CoreIndex),
     Let(CoreIndex, CoreIndex),
     DeBruijnIndex(usize),
     FnType(CoreIndex, CoreIndex),
@@ -59,6 +75,32 @@ pub enum CorePrismExpr {
     TypeAssert(CoreIndex, CoreIndex),
     GrammarValue(Arc<GrammarFile>),
     GrammarType,
+    PairType(CoreIndex, CoreIndex),
+    PairConstruct(CoreIndex, CoreIndex),
+    PairFst(CoreIndex),
+    PairSnd(CoreIndex),
+    /// A data declaration, followed by the expression it is in scope of
+    Data(Arc<DataDecl>, CoreIndex),
+    /// The data type declared by the given `Data` node
+    DataType(CoreIndex),
+    /// The `k`-th constructor of the data type declared by the given `Data` node
+    DataConstruct(CoreIndex, usize),
+    /// The eliminator of the data type declared by the given `Data` node
+    DataElim(CoreIndex),
+    /// The type of universe levels
+    Level,
+    LevelConst(usize),
+    LevelSucc(CoreIndex),
+    LevelMax(CoreIndex, CoreIndex),
+    /// The level above all other levels, this is the level of types quantifying over levels
+    LevelOmega,
+    /// The free theorem of the given value, its type is the parametricity translation of the type of the value
+    Paramatricity(CoreIndex),
+    /// The builtin type of literals of the given kind
+    LiteralType(LiteralType),
+    Literal(Literal),
+    /// A builtin operation on literals, it reduces once it is applied to enough literals
+    PrimOp(PrimOp),
 }
 
 pub struct PrismDb {
@@ -67,13 +109,27 @@ pub struct PrismDb {
     // File info
     pub input: Arc<InputTable>,
     files: HashMap<InputTableIndex, ProcessedFileTableEntry>,
+    pub modules: ModuleGraph,
+    /// The parser of each file, which keeps the results of the last parse to reuse them after an edit
+    pub(crate) parsers: HashMap<InputTableIndex, IncrementalParser<SetError>>,
+
+    // Parsed Values, these are kept for the lifetime of the database since grammars refer to them
+    pub parsed_values: Vec<ParsedPrismExpr>,
+    pub parsed_spans: Vec<Span>,
+    /// The names in scope of each grammar value, by the `grammar_id` of the grammar
+    pub grammar_scopes: HashMap<usize, NamesEnv>,
 
     // Checked Values
     pub checked_values: Vec<CorePrismExpr>,
     pub checked_origins: Vec<ValueOrigin>,
     pub checked_types: HashMap<CoreIndex, CoreIndex>,
+    pub holes: HashMap<CoreIndex, Hole>,
+    /// The names of the variables in scope of values whose type can be shown in errors,
+    /// the last name being the innermost variable
+    pub scope_names: HashMap<CoreIndex, Vec<Option<String>>>,
 
     pub diags: Vec<Diag>,
+    pub(crate) fuel: Fuel,
 }
 
 enum ProcessedFileTableEntry {
@@ -81,11 +137,21 @@ enum ProcessedFileTableEntry {
     Processed(ProcessedFile),
 }
 
+/// A named hole `?name`, the `Free` node it is elaborated to is reported after type checking
+pub struct Hole {
+    pub name: String,
+    /// The names of the variables in scope of the hole, the last name being the innermost variable
+    pub names: Vec<Option<String>>,
+}
+
 #[derive(Clone)]
 pub struct ProcessedFile {
+    pub parsed: ParsedIndex,
     pub core: CoreIndex,
     pub typ: CoreIndex,
     pub tokens: Arc<Tokens>,
+    /// The top-level statements of the file, in order
+    pub exports: Arc<[Export]>,
 }
 
 impl Default for PrismDb {
@@ -100,42 +166,54 @@ impl PrismDb {
             args,
             input: Arc::new(GRAMMAR.0.deep_clone()),
 
+            parsed_values: Default::default(),
+            parsed_spans: Default::default(),
+            grammar_scopes: Default::default(),
+
             checked_values: Default::default(),
             checked_origins: Default::default(),
             checked_types: Default::default(),
+            holes: Default::default(),
+            scope_names: Default::default(),
             diags: Default::default(),
             files: Default::default(),
+            modules: Default::default(),
+            parsers: Default::default(),
+            fuel: Default::default(),
         }
     }
 
-    pub fn process_main_file(&mut self) -> ProcessedFile {
-        let file = self.args.input.clone();
-        let Some(file) = self.load_file(file.into()) else {
-            todo!()
-            // return ProcessedFile {
-            //     core: self.
-            //     typ: CoreIndex(),
-            //     tokens: Arc::new(Tokens::Multi(vec![])),
-            // }
+    /// Processes the file given in the arguments, or the program read from stdin if no file is given.
+    /// Returns `None` if it could not be read, in which case an error is reported.
+    pub fn process_main_file(&mut self) -> Option<ProcessedFile> {
+        let file = match self.args.command.input() {
+            Some(path) => self.load_file(path.into())?,
+            None => self.load_stdin()?,
         };
-        self.process_file(file)
+        Some(self.process_file(file))
     }
 
     pub fn process_file(&mut self, file: InputTableIndex) -> ProcessedFile {
         match self.files.entry(file) {
             Entry::Occupied(v) => match v.get() {
                 ProcessedFileTableEntry::Processing => {
-                    panic!("Import cycle")
+                    unreachable!("Include cycles are reported by `process_include`")
                 }
                 ProcessedFileTableEntry::Processed(p) => return p.clone(),
             },
             Entry::Vacant(v) => v.insert(ProcessedFileTableEntry::Processing),
         };
 
-        let (core, tokens) = self.parse_prism_file(file);
+        let (parsed, core, tokens, exports) = self.parse_prism_file(file);
 
         let typ = self.type_check(core);
-        let processed_file = ProcessedFile { core, typ, tokens };
+        let processed_file = ProcessedFile {
+            parsed,
+            core,
+            typ,
+            tokens,
+            exports,
+        };
         self.files.insert(
             file,
             ProcessedFileTableEntry::Processed(processed_file.clone()),
@@ -143,20 +221,43 @@ impl PrismDb {
         processed_file
     }
 
-    pub fn parse_prism_file(&mut self, file: InputTableIndex) -> (CoreIndex, Arc<Tokens>) {
+    pub fn parse_prism_file(
+        &mut self,
+        file: InputTableIndex,
+    ) -> (ParsedIndex, CoreIndex, Arc<Tokens>, Arc<[Export]>) {
+        self.enter_file(file);
         let mut parse_env = ParserPrismEnv::new(self);
         let (parsed, tokens) = parse_env.parse_file(file);
-        let core = parse_env.parsed_to_checked(parsed);
-        (core, tokens)
+        let (core, exports) = parse_env.file_to_checked(parsed);
+        self.exit_file(file);
+        (parsed, core, tokens, exports)
     }
 
     pub fn update_file(&mut self, file: InputTableIndex, content: String) {
-        self.files.remove(&file);
+        self.invalidate_file(file);
+        self.parsers.remove(&file);
         self.input.inner_mut().update_file(file, content);
     }
 
+    /// Replaces the text in `span` by `text`.
+    /// Unlike `update_file`, this keeps the parts of the last parse of the file that the edit doesn't change.
+    pub fn edit_file(&mut self, span: Span, text: &str) {
+        let file = span.start_pos().file();
+        self.invalidate_file(file);
+        let edit = self.input.inner_mut().edit_file(span, text);
+        if let Some(parser) = self.parsers.get_mut(&file) {
+            parser.edit(&edit);
+        }
+
+        // Parsed values that are reused should point to their new position
+        for span in &mut self.parsed_spans {
+            *span = edit.shift_span(*span);
+        }
+    }
+
     pub fn remove_file(&mut self, file: InputTableIndex) {
-        self.files.remove(&file);
+        self.invalidate_file(file);
+        self.parsers.remove(&file);
         self.input.inner_mut().remove(file);
     }
 
@@ -0,0 +1,211 @@
+use crate::lang::{CoreIndex, PrismDb, ProcessedFile};
+use prism_diag::explain::{Explain, Explanation};
+use prism_diag::{Annotation, AnnotationGroup, AnnotationKind, Diag, IntoDiag, Level};
+use prism_input::input_table::InputTableIndex;
+use prism_input::span::Span;
+use std::collections::{HashMap, HashSet};
+
+/// A top-level `let` or `data` statement of a file, which is in scope of the files that import it
+pub struct Export {
+    /// The `Let` or `Data` node of the statement
+    pub node: CoreIndex,
+    /// The names bound by the statement and their number of implicit arguments, `_` for names that are not exported
+    pub names: Vec<(String, usize)>,
+}
+
+/// The graph of `include!`s and `import`s between the files of a `PrismDb`
+#[derive(Default)]
+pub struct ModuleGraph {
+    /// The file included by each `include!` or `import`, or `None` if it could not be included
+    includes: HashMap<Span, Option<InputTableIndex>>,
+    /// The spans in `includes` that are `import`s rather than `include!`s
+    imports: HashSet<Span>,
+    /// The files that are currently being parsed, each file is included by the previous one
+    stack: Vec<InputTableIndex>,
+}
+
+impl ModuleGraph {
+    /// Returns the files that are directly included by `file`
+    pub fn includes_of(&self, file: InputTableIndex) -> HashSet<InputTableIndex> {
+        self.includes
+            .iter()
+            .filter(|(span, _)| span.start_pos().file() == file)
+            .filter_map(|(_, to)| *to)
+            .collect()
+    }
+
+    /// Returns the files that directly or indirectly include `file`, including `file` itself
+    pub fn dependents_of(&self, file: InputTableIndex) -> HashSet<InputTableIndex> {
+        let mut dependents = HashSet::from([file]);
+        let mut todo = vec![file];
+        while let Some(next) = todo.pop() {
+            for (span, to) in &self.includes {
+                let from = span.start_pos().file();
+                if *to == Some(next) && dependents.insert(from) {
+                    todo.push(from);
+                }
+            }
+        }
+        dependents
+    }
+
+    /// Returns the spans of the `include!`s and `import`s that lead from `file` back to the file at the top of the stack,
+    /// and whether each is an `import`, if `file` is currently being parsed
+    fn cycle_to(&self, file: InputTableIndex) -> Option<Vec<(Span, bool)>> {
+        let start = self.stack.iter().position(|&f| f == file)?;
+        let chain = self.stack[start..]
+            .windows(2)
+            .map(|w| {
+                let span = *self
+                    .includes
+                    .iter()
+                    .find(|(span, to)| span.start_pos().file() == w[0] && **to == Some(w[1]))
+                    .expect("Files on the stack are included by the previous file")
+                    .0;
+                (span, self.imports.contains(&span))
+            })
+            .collect();
+        Some(chain)
+    }
+
+    /// Returns the number of `include!`s and `import`s that were processed
+    pub(crate) fn include_count(&self) -> usize {
+        self.includes.len()
+    }
+
+    /// Forgets the `include!`s and `import`s in `file`
+    fn remove_includes_of(&mut self, file: InputTableIndex) {
+        self.includes
+            .retain(|span, _| span.start_pos().file() != file);
+        self.imports.retain(|span| span.start_pos().file() != file);
+    }
+}
+
+/// A chain of `include!`s and `import`s that leads back to the file it started in
+pub struct IncludeCycle {
+    /// The spans of the `include!`s and `import`s in the cycle, each in the file included by the previous one,
+    /// and whether each is an `import`
+    pub chain: Vec<(Span, bool)>,
+}
+
+impl Explain for IncludeCycle {
+    const EXPLANATIONS: &'static [Explanation] = &[Explanation {
+        id: "include_cycle",
+        explanation: "\
+A file includes or imports itself, through the files it includes or imports.
+
+Including a file inserts its code in place of the `include!`, and importing a file processes it before the code after the `import`,
+so a cycle of includes or imports would never end.
+For example, if `a.pr` and `b.pr` import each other:
+
+```text
+// a.pr
+import b;
+
+// b.pr
+import a;
+```
+
+The same happens if they include each other with `include!(b)` and `include!(a)`.
+Move the code that both files need to a third file that is included or imported by both.",
+    }];
+}
+
+impl IntoDiag<PrismDb> for IncludeCycle {
+    fn into_diag(self, env: &mut PrismDb) -> Diag {
+        let input = env.input.inner();
+        let files: Vec<_> = self
+            .chain
+            .iter()
+            .map(|(s, _)| s.start_pos().file())
+            .collect();
+        let groups = self
+            .chain
+            .iter()
+            .enumerate()
+            .map(|(k, &(span, imported))| AnnotationGroup {
+                annotations: vec![Annotation {
+                    span,
+                    label: Some(format!(
+                        "`{}` {} `{}`",
+                        input.get_path(files[k]).display(),
+                        if imported { "imports" } else { "includes" },
+                        input.get_path(files[(k + 1) % files.len()]).display()
+                    )),
+                    kind: AnnotationKind::Primary,
+                }],
+            })
+            .collect();
+        Diag {
+            level: Level::Error,
+            title: if self.chain.iter().all(|&(_, imported)| imported) {
+                "Import cycle"
+            } else if self.chain.iter().all(|&(_, imported)| !imported) {
+                "Include cycle"
+            } else {
+                "Include and import cycle"
+            }
+            .to_string(),
+            id: Self::EXPLANATIONS[0].id.to_string(),
+            groups,
+            footers: vec![],
+            suggestions: vec![],
+        }
+    }
+}
+
+impl PrismDb {
+    /// Processes the file included by the `include!` or `import` at `span`, which refers to the file `name` relative to the current file.
+    /// `imported` is whether it is an `import`.
+    /// Returns `None` if the file could not be included, in which case an error is reported.
+    pub fn process_include(
+        &mut self,
+        span: Span,
+        name: &str,
+        imported: bool,
+    ) -> Option<ProcessedFile> {
+        // An `include!` may be constructed multiple times during parsing, it is only reported once
+        let file = match self.modules.includes.get(&span) {
+            Some(&file) => file?,
+            None => {
+                let current_file = span.start_pos().file();
+                let mut path = self.input.inner().get_path(current_file).to_path_buf();
+                assert!(path.pop());
+                path.push(format!("{name}.pr"));
+
+                let file = self.load_file_read_at(path, Some(span), imported);
+                self.modules.includes.insert(span, file);
+                if imported {
+                    self.modules.imports.insert(span);
+                }
+                let file = file?;
+
+                if let Some(mut chain) = self.modules.cycle_to(file) {
+                    chain.push((span, imported));
+                    self.modules.includes.insert(span, None);
+                    self.push_error(IncludeCycle { chain });
+                    return None;
+                }
+                file
+            }
+        };
+        Some(self.process_file(file))
+    }
+
+    /// Marks `file` as being parsed until `exit_file` is called
+    pub(super) fn enter_file(&mut self, file: InputTableIndex) {
+        self.modules.remove_includes_of(file);
+        self.modules.stack.push(file);
+    }
+
+    pub(super) fn exit_file(&mut self, file: InputTableIndex) {
+        assert_eq!(self.modules.stack.pop(), Some(file));
+    }
+
+    /// Forgets the processed results of `file` and of the files that include it
+    pub(super) fn invalidate_file(&mut self, file: InputTableIndex) {
+        for dependent in self.modules.dependents_of(file) {
+            self.files.remove(&dependent);
+        }
+    }
+}
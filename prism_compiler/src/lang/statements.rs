@@ -0,0 +1,94 @@
+use crate::lang::env::DbEnv;
+use crate::lang::{CoreIndex, PrismDb};
+use crate::parser::named_env::NamedEnv;
+use crate::parser::{ParsedIndex, ParsedPrismExpr, ParserPrismEnv};
+use prism_input::input_table::InputTableIndex;
+
+/// The scope of the statements at the start of a file,
+/// in which the rest of the file can be processed without processing the statements again
+#[derive(Clone, Default)]
+pub struct StatementsScope {
+    /// The number of statements, see `PrismDb::statement_body`
+    pub(super) statements: usize,
+    pub(super) names: NamedEnv,
+    pub(super) env: DbEnv,
+}
+
+impl StatementsScope {
+    /// The env of the statements, in which the values processed in this scope are valid
+    pub fn env(&self) -> &DbEnv {
+        &self.env
+    }
+}
+
+/// The rest of a file after the statements of a `StatementsScope`, see `PrismDb::process_after`
+pub struct ProcessedRest {
+    /// The rest of the file, valid in the env of the scope it was processed in
+    pub core: CoreIndex,
+    pub typ: CoreIndex,
+    /// The scope of all statements of the file, or `None` if errors were reported
+    pub scope: Option<StatementsScope>,
+}
+
+impl PrismDb {
+    /// Processes the rest of `file` after the statements of `scope`, which the file starts with.
+    /// Only the rest of the file is converted and type checked, in the scope of the statements.
+    /// Returns `None` if the statements of `scope` could not be parsed, in which case errors were reported.
+    pub fn process_after(
+        &mut self,
+        file: InputTableIndex,
+        scope: &StatementsScope,
+    ) -> Option<ProcessedRest> {
+        self.enter_file(file);
+        let (parsed, _) = ParserPrismEnv::new(self).parse_file(file);
+
+        let mut rest = Some(parsed);
+        for _ in 0..scope.statements {
+            rest = rest.and_then(|rest| self.statement_body(rest));
+        }
+        let Some(rest) = rest else {
+            self.exit_file(file);
+            assert!(self.has_errors());
+            return None;
+        };
+        let mut end = rest;
+        let mut statements = scope.statements;
+        while let Some(body) = self.statement_body(end) {
+            end = body;
+            statements += 1;
+        }
+
+        let (core, end_names) =
+            ParserPrismEnv::new(self).parsed_to_checked_with_scope_of(rest, &scope.names, end);
+        self.exit_file(file);
+        let (end_core, names) = end_names.expect("The end of the statements is converted");
+
+        let (typ, env) = self.type_check_with_scope_of(core, &scope.env, Some(end_core));
+        let scope = env
+            .filter(|_| !self.has_errors())
+            .map(|env| StatementsScope {
+                statements,
+                names,
+                env,
+            });
+        Some(ProcessedRest { core, typ, scope })
+    }
+
+    /// Returns the expression that the statement `parsed` is followed by, or `None` if it is not a statement
+    pub(crate) fn statement_body(&self, parsed: ParsedIndex) -> Option<ParsedIndex> {
+        let start = self.parsed_spans[*parsed].start_pos();
+        let body = match self.parsed_values[*parsed] {
+            ParsedPrismExpr::Let(_, _, b)
+            | ParsedPrismExpr::Data(_, _, _, b)
+            | ParsedPrismExpr::Import(_, _, b)
+            | ParsedPrismExpr::ShiftTo { expr: b, .. } => b,
+            _ => return None,
+        };
+        // The body of an adapted grammar may be constructed from the source of the grammar
+        let body_start = self.parsed_spans[*body].start_pos();
+        if body_start.file() != start.file() || body_start.idx_in_file() < start.idx_in_file() {
+            return None;
+        }
+        Some(body)
+    }
+}
@@ -1,5 +1,7 @@
 pub mod args;
+pub mod explain;
 pub mod interp;
 pub mod lang;
 pub mod parser;
+pub mod repl;
 pub mod type_check;
@@ -1,38 +1,79 @@
 use clap::Parser;
-use prism_compiler::args::PrismArgs;
+use prism_compiler::args::{Command, DumpStage, PrismArgs};
+use prism_compiler::explain::explain;
 use prism_compiler::lang::PrismDb;
+use prism_compiler::lang::env::DbEnv;
+use prism_compiler::parser::ParserPrismEnv;
+use prism_compiler::repl::Repl;
 use std::process::exit;
 
 fn main() {
     let args = PrismArgs::parse();
+    let command = args.command.clone();
     let mut env = PrismDb::new(args);
 
-    //Load file
-    let _processed = env.process_main_file();
+    match &command {
+        Command::Repl => {
+            let stdin = std::io::stdin().lock();
+            Repl::new(env)
+                .run(stdin)
+                .expect("Failed to read from stdin");
+            exit(0);
+        }
+        Command::Explain { id } => match explain(id) {
+            Some(explanation) => {
+                println!("{}", explanation.explanation);
+                exit(0);
+            }
+            None => {
+                eprintln!("Unknown diagnostic id `{id}`");
+                exit(1);
+            }
+        },
+        _ => {}
+    }
 
-    // Print info
-    // println!(
-    //     "> Parsed Program\n====================\n{}\n\n",
-    //     env.parse_index_to_string(processed.parsed),
-    // );
-    // println!(
-    //     "> Core Program\n====================\n{}\n\n",
-    //     env.index_to_string(processed.core),
-    // );
+    //Load file
+    let processed = env.process_main_file();
+    if env.has_errors() {
+        finish(&mut env);
+    }
+    let processed = processed.expect("Files that can't be read are reported");
 
-    if !env.diags.is_empty() {
-        env.eprint_errors();
-        exit(1);
+    match command {
+        Command::Check { .. } | Command::Repl | Command::Explain { .. } => {}
+        Command::Eval { .. } => {
+            let evaluated = env.index_to_br_string(processed.core, &DbEnv::default());
+            println!("{evaluated}");
+        }
+        Command::Type { .. } => {
+            let typ = env.index_to_br_string(processed.typ, &DbEnv::default());
+            println!("{typ}");
+        }
+        Command::Dump { stage, .. } => {
+            let dumped = match stage {
+                DumpStage::Parsed => {
+                    ParserPrismEnv::new(&mut env).parse_index_to_string(processed.parsed)
+                }
+                DumpStage::Core => env.index_to_string(processed.core),
+                DumpStage::Simplified => env.index_to_sm_string(processed.core),
+            };
+            println!("{dumped}");
+        }
     }
 
-    // println!(
-    //     "> Type of program\n====================\n{}\n\n",
-    //     env.index_to_br_string(processed.typ, &DbEnv::default())
-    // );
-    //
-    // println!(
-    //     "> Evaluated\n====================\n{}\n\n",
-    //     env.index_to_br_string(processed.core, &DbEnv::default())
-    // );
-    exit(0);
+    // Evaluating can report that a term did not terminate
+    finish(&mut env);
+}
+
+/// Applies the fixes of the reported diagnostics if requested, prints the diagnostics and exits.
+/// This is done once, since the fixes are applied to the source as it was read,
+/// and a SARIF log contains all diagnostics.
+fn finish(env: &mut PrismDb) -> ! {
+    if env.args.fix {
+        env.apply_fixes();
+    }
+    let has_errors = env.has_errors();
+    env.eprint_errors();
+    exit(if has_errors { 1 } else { 0 })
 }
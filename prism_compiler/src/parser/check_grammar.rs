@@ -0,0 +1,460 @@
+use crate::parser::parse_expr::{ArgKind, expr_constructor_args};
+use crate::parser::{GRAMMAR, ParsedIndex, ParserPrismEnv};
+use prism_diag_derive::Diagnostic;
+use prism_input::input::Input;
+use prism_input::span::Span;
+use prism_parser::grammar::grammar_file::GrammarFile;
+use prism_parser::grammar::rule::Rule;
+use prism_parser::grammar::rule_action::RuleAction;
+use prism_parser::grammar::rule_expr::RuleExpr;
+use prism_parser::parsable::parsed::Parsed;
+use prism_parser::parser::parsed_list::ParsedList;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The kinds of the values bound to names in a rule expression, see `expr_kind`
+type Scope = HashMap<String, Option<ArgKind>>;
+
+/// Returns the possible arguments of the constructor `ns::name`, or `None` if it does not exist
+fn constructor_args(ns: &str, name: &str) -> Option<&'static [&'static [ArgKind]]> {
+    use ArgKind::*;
+    match ns {
+        "Expr" => expr_constructor_args(name),
+        // These are the values that `ParsedList::from_construct` constructs
+        "ParsedList" => match name {
+            "Cons" => Some(&[&[Any, List]]),
+            "Nil" => Some(&[&[]]),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Combines the kinds of the values that can be produced by the same expression.
+/// Kinds that are `None` are skipped, the result is `None` if all kinds are.
+fn combine_all(kinds: impl IntoIterator<Item = Option<ArgKind>>) -> Option<ArgKind> {
+    kinds
+        .into_iter()
+        .flatten()
+        .reduce(|k1, k2| if k1 == k2 { k1 } else { ArgKind::Any })
+}
+
+/// Returns the kind of the parsed value `parsed`
+fn parsed_kind(parsed: &Parsed) -> ArgKind {
+    if parsed.try_value_ref::<Input>().is_some() {
+        ArgKind::Input
+    } else if parsed.try_value_ref::<ParsedIndex>().is_some() {
+        ArgKind::Expr
+    } else if parsed.try_value_ref::<ParsedList>().is_some() {
+        ArgKind::List
+    } else if parsed.try_value_ref::<GrammarFile>().is_some() {
+        ArgKind::Grammar
+    } else {
+        ArgKind::Any
+    }
+}
+
+/// Returns `span` if it is known, or `fallback` otherwise.
+/// Grammars that were not parsed from source code do not know the spans of their actions.
+fn known_span(span: Span, fallback: Span) -> Span {
+    if span == Span::dummy() {
+        fallback
+    } else {
+        span
+    }
+}
+
+/// A grammar that is adapted while parsing a program uses `#adapt` with a namespace other than `Expr`.
+///
+/// Only expressions can be adapted while parsing a program. For example:
+///
+/// ```prism
+/// adapt grammar {
+///     adapt rule expr {
+///         adapt group base {
+///             "%" #adapt(Foo, g, expr) => Type;
+///         }
+///     }
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Grammar adapts unknown namespace `{}`", self.ns))]
+pub(crate) struct UnknownNamespace {
+    #[sugg]
+    span: Span,
+    ns: String,
+}
+
+/// A grammar that is adapted while parsing a program constructs a value that does not exist.
+///
+/// The values that can be constructed are the expressions of the language, such as `Expr::FnDestruct(f, a)`.
+/// Values are usually constructed with Prism expressions, but after allowing the actions of the meta grammar,
+/// a grammar can construct values directly. For example:
+///
+/// ```prism
+/// adapt grammar {
+///     adapt rule wrapped_expr {
+///         adapt group {
+///             prule_action;
+///         }
+///     }
+/// };
+/// adapt grammar {
+///     adapt rule expr {
+///         adapt group base {
+///             "%" => (Expr::Foo());
+///         }
+///     }
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Grammar constructs unknown value `{}::{}`", self.ns, self.name))]
+pub(crate) struct UnknownConstructor {
+    #[sugg]
+    span: Span,
+    ns: String,
+    name: String,
+}
+
+/// A grammar that is adapted while parsing a program constructs a value with the wrong number of arguments,
+/// for example a function application `Expr::FnDestruct(f, a)` with only one argument:
+///
+/// ```prism
+/// adapt grammar {
+///     adapt rule wrapped_expr {
+///         adapt group {
+///             prule_action;
+///         }
+///     }
+/// };
+/// adapt grammar {
+///     adapt rule expr {
+///         adapt group base {
+///             "%" f:expr => (Expr::FnDestruct(f));
+///         }
+///     }
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("`{}::{}` is constructed with {} arguments, expected {}", self.ns, self.name, self.got, self.expected))]
+pub(crate) struct WrongArgumentCount {
+    #[sugg]
+    span: Span,
+    ns: String,
+    name: String,
+    got: usize,
+    expected: String,
+}
+
+/// A grammar that is adapted while parsing a program constructs a value with an argument of the wrong kind.
+///
+/// Arguments can be pieces of the input such as names, expressions or lists.
+/// For example, the argument of a function application `Expr::FnDestruct(f, a)` should be an expression, not a name:
+///
+/// ```prism
+/// adapt grammar {
+///     adapt rule wrapped_expr {
+///         adapt group {
+///             prule_action;
+///         }
+///     }
+/// };
+/// adapt grammar {
+///     adapt rule expr {
+///         adapt group base {
+///             "%" f:expr x:#str(['a'-'z']+) => (Expr::FnDestruct(f, x));
+///         }
+///     }
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Argument {} of `{}::{}` should be {}, but is {}", self.index + 1, self.ns, self.name, self.expected, self.got))]
+pub(crate) struct WrongArgumentKind {
+    #[sugg]
+    span: Span,
+    ns: String,
+    name: String,
+    index: usize,
+    expected: ArgKind,
+    got: ArgKind,
+}
+
+impl ParserPrismEnv<'_> {
+    /// Checks that the values constructed by `grammar` can be constructed and that it only adapts expressions.
+    /// Errors are reported at the actions of the grammar, or at `span` if these are not known.
+    /// Returns whether the grammar can be adapted.
+    pub(super) fn check_grammar(&mut self, grammar: &GrammarFile, span: Span) -> bool {
+        let error_count = self.db.diags.len();
+        for rule in grammar.rules.iter() {
+            for block in rule.blocks.iter() {
+                for constructor in block.constructors.iter() {
+                    self.check_rule_expr(&constructor.expr, grammar, span);
+                }
+            }
+        }
+        self.db.diags.len() == error_count
+    }
+
+    fn check_rule_expr(&mut self, expr: &RuleExpr, grammar: &GrammarFile, span: Span) {
+        match expr {
+            RuleExpr::Action(e, action) => {
+                self.check_rule_expr(e, grammar, span);
+                let mut scope = HashMap::new();
+                self.bind_names(e, grammar, &mut scope, &mut Vec::new());
+                self.check_action(action, &scope, span);
+            }
+            RuleExpr::RunVar { args: es, .. } | RuleExpr::Sequence(es) | RuleExpr::Choice(es) => {
+                for e in es.iter() {
+                    self.check_rule_expr(e, grammar, span);
+                }
+            }
+            RuleExpr::Repeat { expr, delim, .. } => {
+                self.check_rule_expr(expr, grammar, span);
+                self.check_rule_expr(delim, grammar, span);
+            }
+            RuleExpr::AtAdapt {
+                ns,
+                expr,
+                span: adapt_span,
+                ..
+            } => {
+                // Only expressions can be adapted while parsing a program
+                let ns = ns.as_str(&self.db.input);
+                if ns != "Expr" {
+                    let ns = ns.to_string();
+                    let span = known_span(*adapt_span, span);
+                    self.db.push_error(UnknownNamespace { span, ns });
+                }
+                self.check_rule_expr(expr, grammar, span);
+            }
+            RuleExpr::NameBind(_, e)
+            | RuleExpr::SliceInput(e)
+            | RuleExpr::PosLookahead(e)
+            | RuleExpr::NegLookahead(e) => self.check_rule_expr(e, grammar, span),
+            RuleExpr::CharClass(_) | RuleExpr::Literal(_) => {}
+        }
+    }
+
+    fn check_action(&mut self, action: &RuleAction, scope: &Scope, span: Span) {
+        let RuleAction::Construct { ns, name, args, .. } = action else {
+            return;
+        };
+        for arg in args.iter() {
+            self.check_action(arg, scope, span);
+        }
+
+        let ns = ns.as_str(&self.db.input).to_string();
+        let name = name.as_str(&self.db.input).to_string();
+        let span = known_span(action.span(), span);
+        let Some(signatures) = constructor_args(&ns, &name) else {
+            self.db.push_error(UnknownConstructor { span, ns, name });
+            return;
+        };
+        let Some(signature) = signatures.iter().find(|s| s.len() == args.len()) else {
+            let expected = signatures
+                .iter()
+                .map(|s| s.len().to_string())
+                .collect::<Vec<_>>()
+                .join(" or ");
+            self.db.push_error(WrongArgumentCount {
+                span,
+                ns,
+                name,
+                got: args.len(),
+                expected,
+            });
+            return;
+        };
+
+        for (index, (&expected, arg)) in signature.iter().zip(args.iter()).enumerate() {
+            let Some(got) = self.action_kind(arg, scope) else {
+                continue;
+            };
+            if expected != ArgKind::Any && got != ArgKind::Any && got != expected {
+                self.db.push_error(WrongArgumentKind {
+                    span: known_span(arg.span(), span),
+                    ns: ns.clone(),
+                    name: name.clone(),
+                    index,
+                    expected,
+                    got,
+                });
+            }
+        }
+    }
+
+    /// Checks that `args` are valid arguments of the expression constructor `constructor`, reporting errors at `span`.
+    /// Grammars are checked before they are adapted, but the kinds of some values are only known while parsing.
+    /// Returns whether the value can be constructed.
+    pub(super) fn check_construct(
+        &mut self,
+        span: Span,
+        constructor: &str,
+        args: &[Parsed],
+    ) -> bool {
+        let signature = expr_constructor_args(constructor)
+            .and_then(|signatures| signatures.iter().find(|s| s.len() == args.len()));
+        let got: Vec<ArgKind> = args.iter().map(parsed_kind).collect();
+        let valid = signature.is_some_and(|signature| {
+            signature
+                .iter()
+                .zip(&got)
+                .all(|(&expected, &got)| expected == ArgKind::Any || expected == got)
+        });
+        // Values are also constructed without a span while determining the context of grammar adaptations
+        if valid || span == Span::dummy() || !self.reported_spans.insert(span) {
+            return valid;
+        }
+
+        let ns = "Expr".to_string();
+        let name = constructor.to_string();
+        let Some(signatures) = expr_constructor_args(constructor) else {
+            self.db.push_error(UnknownConstructor { span, ns, name });
+            return false;
+        };
+        let Some(signature) = signature else {
+            let expected = signatures
+                .iter()
+                .map(|s| s.len().to_string())
+                .collect::<Vec<_>>()
+                .join(" or ");
+            self.db.push_error(WrongArgumentCount {
+                span,
+                ns,
+                name,
+                got: args.len(),
+                expected,
+            });
+            return false;
+        };
+        for (index, (&expected, &got)) in signature.iter().zip(&got).enumerate() {
+            if expected != ArgKind::Any && expected != got {
+                self.db.push_error(WrongArgumentKind {
+                    span,
+                    ns: ns.clone(),
+                    name: name.clone(),
+                    index,
+                    expected,
+                    got,
+                });
+            }
+        }
+        false
+    }
+
+    /// Returns the kind of value produced by `action`, if it is known before parsing.
+    /// Returns `None` if it is the value of the rule that is being resolved, see `expr_kind`.
+    fn action_kind(&self, action: &RuleAction, scope: &Scope) -> Option<ArgKind> {
+        Some(match action {
+            RuleAction::InputLiteral(..) => ArgKind::Input,
+            RuleAction::Construct { ns, .. } => match &*ns.as_str(&self.db.input) {
+                "Expr" => ArgKind::Expr,
+                "ParsedList" => ArgKind::List,
+                _ => ArgKind::Any,
+            },
+            RuleAction::Value { .. } => ArgKind::Expr,
+            // Names that are not bound in the rule expression are arguments of the rule or of the grammar
+            RuleAction::Name(name, _) => {
+                return scope
+                    .get(&*name.as_str(&self.db.input))
+                    .copied()
+                    .unwrap_or(Some(ArgKind::Any));
+            }
+        })
+    }
+
+    /// Adds the names bound by `expr` to `scope`, with the kind of the value they are bound to
+    fn bind_names(
+        &self,
+        expr: &RuleExpr,
+        grammar: &GrammarFile,
+        scope: &mut Scope,
+        visiting: &mut Vec<String>,
+    ) {
+        match expr {
+            RuleExpr::NameBind(name, e) => {
+                let kind = self.expr_kind(e, grammar, visiting);
+                scope
+                    .entry(name.as_str(&self.db.input).to_string())
+                    .and_modify(|k| *k = combine_all([*k, kind]))
+                    .or_insert(kind);
+            }
+            RuleExpr::Sequence(es) | RuleExpr::Choice(es) => {
+                for e in es.iter() {
+                    self.bind_names(e, grammar, scope, visiting);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the kind of value produced by parsing `expr`, if it is known before parsing.
+    /// Returns `None` if `expr` produces the value of a rule that is being resolved, such as `#this`,
+    /// which does not change the kind of that rule.
+    fn expr_kind(
+        &self,
+        expr: &RuleExpr,
+        grammar: &GrammarFile,
+        visiting: &mut Vec<String>,
+    ) -> Option<ArgKind> {
+        match expr {
+            RuleExpr::SliceInput(_) | RuleExpr::Literal(_) | RuleExpr::CharClass(_) => {
+                Some(ArgKind::Input)
+            }
+            RuleExpr::Repeat { .. } => Some(ArgKind::List),
+            RuleExpr::Action(e, action) => {
+                let mut scope = HashMap::new();
+                self.bind_names(e, grammar, &mut scope, visiting);
+                self.action_kind(action, &scope)
+            }
+            RuleExpr::Choice(es) => {
+                combine_all(es.iter().map(|e| self.expr_kind(e, grammar, visiting)))
+            }
+            RuleExpr::RunVar { rule, .. } => match &*rule.as_str(&self.db.input) {
+                "#this" | "#next" => None,
+                rule => self.rule_kind(rule, grammar, visiting),
+            },
+            _ => Some(ArgKind::Any),
+        }
+    }
+
+    /// Returns the kind of value produced by the rule `name`, if it is known before parsing.
+    /// The rule is looked up in `grammar` and in the grammar of the language that it adapts.
+    fn rule_kind(
+        &self,
+        name: &str,
+        grammar: &GrammarFile,
+        visiting: &mut Vec<String>,
+    ) -> Option<ArgKind> {
+        if visiting.iter().any(|v| v == name) {
+            return None;
+        }
+        let named = |r: &&Arc<Rule>| r.name.as_str(&self.db.input) == name;
+
+        // An adapted rule also has the constructors of the rule it adapts
+        let mut rules: Vec<&Arc<Rule>> = grammar.rules.iter().filter(named).collect();
+        if rules.iter().all(|r| r.adapt) {
+            rules.extend(GRAMMAR.1.rules.iter().filter(named));
+        }
+        // Names that are not rules are arguments of the rule
+        if rules.is_empty() {
+            return Some(ArgKind::Any);
+        }
+
+        visiting.push(name.to_string());
+        let kind = combine_all(
+            rules
+                .iter()
+                .flat_map(|r| r.blocks.iter())
+                .flat_map(|b| b.constructors.iter())
+                .map(|c| self.expr_kind(&c.expr, grammar, visiting))
+                .collect::<Vec<_>>(),
+        );
+        visiting.pop();
+        kind
+    }
+}
@@ -0,0 +1,535 @@
+use crate::lang::data::{DataConstructor, DataDecl};
+use crate::lang::{CoreIndex, CorePrismExpr, ValueOrigin};
+use crate::parser::named_env::NamedEnv;
+use crate::parser::{ParsedIndex, ParsedPrismExpr, ParserPrismEnv};
+use prism_diag_derive::Diagnostic;
+use prism_input::input::Input;
+use prism_input::span::Span;
+use std::sync::Arc;
+
+/// Name of the binders generated for the eliminator, these can never be referred to from source code
+const HIDDEN: &str = "#";
+
+/// The type of a data type is not a function type that returns `Type`.
+///
+/// A data type is declared with its parameters and indices, such as `data Vec: (T: Type) -> (n: Nat) -> Type`.
+/// For example, this data type returns itself instead of `Type`:
+///
+/// ```prism
+/// data Unit: Type -> Unit {
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = "The type of a data type should be a function type returning `Type`")]
+pub(crate) struct InvalidDataSignature {
+    #[sugg]
+    span: Span,
+}
+
+/// The type of a constructor of a data type does not return the data type.
+///
+/// For example, `unit` should return a `Unit` rather than a `Type`:
+///
+/// ```prism
+/// data Unit: Type {
+///     unit: Type;
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Constructor should return the data type `{}`", self.name))]
+pub(crate) struct InvalidConstructorType {
+    #[sugg]
+    span: Span,
+    name: String,
+}
+
+/// A data type is applied to a different number of indices than its type declares.
+///
+/// For example, `Vec` takes a type and a length, so it should be applied to two indices in the type of `nil`:
+///
+/// ```prism
+/// data Nat: Type {
+///     zero: Nat;
+///     succ: Nat -> Nat;
+/// };
+///
+/// data Vec: (T: Type) -> (n: Nat) -> Type {
+///     nil: (T: Type) -> Vec T;
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Expected `{}` to be applied to {} indices", self.name, self.indices))]
+pub(crate) struct WrongIndexCount {
+    #[sugg]
+    span: Span,
+    name: String,
+    indices: usize,
+}
+
+/// A constructor argument `(y: B)`
+struct DataArg {
+    name: Input,
+    typ: ParsedIndex,
+    recursive: Option<RecursiveArg>,
+}
+
+/// A recursive argument, of the form `(z_1: C_1) -> ... -> (z_p: C_p) -> D s_1 ... s_n`
+struct RecursiveArg {
+    binders: Vec<(Input, ParsedIndex)>,
+    indices: Vec<ParsedIndex>,
+}
+
+struct DataCtor {
+    args: Vec<DataArg>,
+    /// The indices `t_1 ... t_n` of the type the constructor returns
+    indices: Vec<ParsedIndex>,
+}
+
+/// The information needed to generate the eliminator of a data type
+struct DataInfo<'d> {
+    origin: ValueOrigin,
+    indices: &'d [(Input, ParsedIndex)],
+    ctors: &'d [DataCtor],
+    /// The depth at which the data type is bound, the constructors and the eliminator follow it
+    d_depth: usize,
+}
+
+impl DataInfo<'_> {
+    fn ctor_depth(&self, k: usize) -> usize {
+        self.d_depth + 1 + k
+    }
+
+    fn elim_depth(&self) -> usize {
+        self.d_depth + 1 + self.ctors.len()
+    }
+}
+
+impl<'a> ParserPrismEnv<'a> {
+    /// Converts a data declaration, generating the type and computation rules of its eliminator.
+    ///
+    /// For a data type `D: (i: A).. -> Type` with constructors `c_k: (y: B).. -> D t..`, the eliminator has type
+    /// `(P: (i: A).. -> D i.. -> Type) -> (f_k: (y: B).. -> ih.. -> P t.. (c_k y..)).. -> (i: A).. -> (x: D i..) -> P i.. x`,
+    /// where for each recursive argument `y: (z: C).. -> D s..` there is an induction hypothesis `ih: (z: C).. -> P s.. (y z..)`.
+    pub(super) fn data_to_checked(&mut self, i: ParsedIndex, env: &NamedEnv) -> CoreIndex {
+        let origin = ValueOrigin::SourceCode(self.db.parsed_spans[*i]);
+        let ParsedPrismExpr::Data(name, typ, constructors, body) =
+            self.db.parsed_values[*i].clone()
+        else {
+            unreachable!()
+        };
+        let data_name = name.as_str(&self.db.input).to_string();
+
+        // Check the shape of the declaration
+        let (indices, typ_result) = self.split_fn_type(typ);
+        if !matches!(self.db.parsed_values[*typ_result], ParsedPrismExpr::Type(_)) {
+            self.db.push_error(InvalidDataSignature {
+                span: self.db.parsed_spans[*typ],
+            });
+            return self.db.store_checked(CorePrismExpr::Free, origin);
+        }
+        let ctors: Vec<_> = constructors
+            .iter()
+            .map(|&(_, t)| self.data_ctor_shape(t, &data_name, indices.len()))
+            .collect();
+        let Some(ctors) = ctors.into_iter().collect::<Option<Vec<_>>>() else {
+            return self.db.store_checked(CorePrismExpr::Free, origin);
+        };
+        let info = DataInfo {
+            origin,
+            indices: &indices,
+            ctors: &ctors,
+            d_depth: env.len(),
+        };
+
+        // Reserve the data node, so the constants can refer to it
+        let d = self.db.store_checked(CorePrismExpr::Free, origin);
+        let type_node = self.db.store_checked(CorePrismExpr::DataType(d), origin);
+        let elim_node = self.db.store_checked(CorePrismExpr::DataElim(d), origin);
+
+        let typ = self.parsed_to_checked_with_env(typ, env);
+        let env_d = env.insert_name(&data_name, &self.db.input);
+
+        // Parts of the eliminator are converted in scopes where the generated binders are hidden,
+        // so they can't shadow names used in the declaration
+        let hidden_ctors = self.hide(&env_d, ctors.len());
+        let hidden_all = self.hide(env, 1 + ctors.len());
+        let elim_type = self.data_elim_type(&info, &hidden_ctors, &hidden_all);
+        let hidden_elim = self.hide(&hidden_ctors, 1);
+
+        let mut decl_ctors = Vec::with_capacity(ctors.len());
+        for (k, (name, ctor_typ)) in constructors.iter().enumerate() {
+            decl_ctors.push(DataConstructor {
+                name: name.clone(),
+                typ: self.parsed_to_checked_with_env(*ctor_typ, &env_d),
+                arity: ctors[k].args.len(),
+                node: self
+                    .db
+                    .store_checked(CorePrismExpr::DataConstruct(d, k), origin),
+                step: self.data_step(&info, k, &hidden_elim),
+            });
+        }
+
+        let mut body_env = env_d;
+        for &(ref name, ctor_typ) in constructors.iter() {
+            body_env = body_env.insert_name_with_implicits(
+                &name.as_str(&self.db.input),
+                self.implicit_args_of_type(ctor_typ),
+                &self.db.input,
+            );
+        }
+        body_env = body_env.insert_name(&format!("{data_name}_elim"), &self.db.input);
+        let body = self.parsed_to_checked_with_env(body, &body_env);
+
+        let decl = DataDecl {
+            name,
+            typ,
+            indices: indices.len(),
+            type_node,
+            constructors: decl_ctors.into(),
+            elim_type,
+            elim_node,
+        };
+        self.db.checked_values[*d] = CorePrismExpr::Data(Arc::new(decl), body);
+        d
+    }
+
+    /// Checks that `typ` is of the form `(y: B).. -> D t..`
+    fn data_ctor_shape(&mut self, typ: ParsedIndex, data_name: &str, n: usize) -> Option<DataCtor> {
+        let (binders, result) = self.split_fn_type(typ);
+
+        let mut args = Vec::with_capacity(binders.len());
+        for (j, (name, arg_typ)) in binders.iter().enumerate() {
+            let (zs, arg_result) = self.split_fn_type(*arg_typ);
+            let shadowed = self.binds(&binders[..j], data_name) || self.binds(&zs, data_name);
+            let recursive = match self.split_data_spine(arg_result, data_name, shadowed) {
+                Some(s) if s.len() != n => {
+                    self.db.push_error(WrongIndexCount {
+                        span: self.db.parsed_spans[*arg_result],
+                        name: data_name.to_string(),
+                        indices: n,
+                    });
+                    return None;
+                }
+                s => s.map(|indices| RecursiveArg {
+                    binders: zs,
+                    indices,
+                }),
+            };
+            args.push(DataArg {
+                name: name.clone(),
+                typ: *arg_typ,
+                recursive,
+            });
+        }
+
+        let shadowed = self.binds(&binders, data_name);
+        let Some(indices) = self.split_data_spine(result, data_name, shadowed) else {
+            self.db.push_error(InvalidConstructorType {
+                span: self.db.parsed_spans[*result],
+                name: data_name.to_string(),
+            });
+            return None;
+        };
+        if indices.len() != n {
+            self.db.push_error(WrongIndexCount {
+                span: self.db.parsed_spans[*result],
+                name: data_name.to_string(),
+                indices: n,
+            });
+            return None;
+        }
+
+        Some(DataCtor { args, indices })
+    }
+
+    /// Splits `(x_1: A_1) -> ... -> (x_n: A_n) -> r` into its binders and `r`, binders may be implicit
+    fn split_fn_type(&self, mut i: ParsedIndex) -> (Vec<(Input, ParsedIndex)>, ParsedIndex) {
+        let mut binders = Vec::new();
+        while let &ParsedPrismExpr::FnType(ref n, a, b)
+        | &ParsedPrismExpr::ImplicitFnType(ref n, a, b) = &self.db.parsed_values[*i]
+        {
+            binders.push((n.clone(), a));
+            i = b;
+        }
+        (binders, i)
+    }
+
+    /// If `i` is of the form `D a_1 ... a_n`, returns `a_1 ... a_n`
+    fn split_data_spine(
+        &self,
+        mut i: ParsedIndex,
+        data_name: &str,
+        shadowed: bool,
+    ) -> Option<Vec<ParsedIndex>> {
+        let mut args = Vec::new();
+        while let &ParsedPrismExpr::FnDestruct(f, a) = &self.db.parsed_values[*i] {
+            args.push(a);
+            i = f;
+        }
+        let ParsedPrismExpr::Name(n) = &self.db.parsed_values[*i] else {
+            return None;
+        };
+        if shadowed || n.as_str(&self.db.input) != data_name {
+            return None;
+        }
+        args.reverse();
+        Some(args)
+    }
+
+    fn binds(&self, binders: &[(Input, ParsedIndex)], name: &str) -> bool {
+        binders
+            .iter()
+            .any(|(n, _)| n.as_str(&self.db.input) == name)
+    }
+
+    fn hide(&self, env: &NamedEnv, count: usize) -> NamedEnv {
+        let mut env = env.clone();
+        for _ in 0..count {
+            env = env.insert_name(HIDDEN, &self.db.input);
+        }
+        env
+    }
+
+    /// Inserts the names of `args` into `env`
+    fn insert_args(&self, env: &NamedEnv, args: &[DataArg]) -> NamedEnv {
+        let mut env = env.clone();
+        for arg in args {
+            env = env.insert_name(&arg.name.as_str(&self.db.input), &self.db.input);
+        }
+        env
+    }
+
+    fn var(&mut self, env: &NamedEnv, depth: usize, info: &DataInfo) -> CoreIndex {
+        self.db.store_checked(
+            CorePrismExpr::DeBruijnIndex(env.len() - depth - 1),
+            info.origin,
+        )
+    }
+
+    fn app(&mut self, f: CoreIndex, a: CoreIndex, info: &DataInfo) -> CoreIndex {
+        self.db
+            .store_checked(CorePrismExpr::FnDestruct(f, a), info.origin)
+    }
+
+    /// Creates `doms_1 -> ... -> doms_n -> r`
+    fn fn_types(&mut self, doms: Vec<CoreIndex>, mut r: CoreIndex, info: &DataInfo) -> CoreIndex {
+        for dom in doms.into_iter().rev() {
+            r = self
+                .db
+                .store_checked(CorePrismExpr::FnType(dom, r), info.origin);
+        }
+        r
+    }
+
+    /// Creates `D i..` where `i` are the variables at `index_depths`
+    fn data_applied(
+        &mut self,
+        env: &NamedEnv,
+        index_depths: &[usize],
+        info: &DataInfo,
+    ) -> CoreIndex {
+        let mut e = self.var(env, info.d_depth, info);
+        for &depth in index_depths {
+            let i = self.var(env, depth, info);
+            e = self.app(e, i, info);
+        }
+        e
+    }
+
+    /// Generates the type of the eliminator.
+    /// `env_b` is the scope with the data type visible, `env_a` has nothing of the declaration visible.
+    fn data_elim_type(&mut self, info: &DataInfo, env_b: &NamedEnv, env_a: &NamedEnv) -> CoreIndex {
+        let mut doms = Vec::new();
+
+        // Motive
+        doms.push(self.data_motive_type(info, env_a));
+        let p_depth = env_b.len();
+        let mut env_a = self.hide(env_a, 1);
+        let mut env_b = self.hide(env_b, 1);
+
+        // Cases
+        for k in 0..info.ctors.len() {
+            doms.push(self.data_case_type(info, k, p_depth, &env_b));
+            env_a = self.hide(&env_a, 1);
+            env_b = self.hide(&env_b, 1);
+        }
+
+        // Indices and the major premise
+        let mut index_depths = Vec::with_capacity(info.indices.len());
+        for (name, t) in info.indices {
+            doms.push(self.parsed_to_checked_with_env(*t, &env_a));
+            index_depths.push(env_a.len());
+            env_a = env_a.insert_name(&name.as_str(&self.db.input), &self.db.input);
+        }
+        doms.push(self.data_applied(&env_a, &index_depths, info));
+        let x_depth = env_a.len();
+        let env_a = self.hide(&env_a, 1);
+
+        // Result `P i.. x`
+        let mut r = self.var(&env_a, p_depth, info);
+        for &depth in index_depths.iter().chain([&x_depth]) {
+            let i = self.var(&env_a, depth, info);
+            r = self.app(r, i, info);
+        }
+        self.fn_types(doms, r, info)
+    }
+
+    /// Generates `(i: A).. -> D i.. -> Type`
+    fn data_motive_type(&mut self, info: &DataInfo, env_a: &NamedEnv) -> CoreIndex {
+        let mut env_a = env_a.clone();
+        let mut doms = Vec::with_capacity(info.indices.len() + 1);
+        let mut index_depths = Vec::with_capacity(info.indices.len());
+        for (name, t) in info.indices {
+            doms.push(self.parsed_to_checked_with_env(*t, &env_a));
+            index_depths.push(env_a.len());
+            env_a = env_a.insert_name(&name.as_str(&self.db.input), &self.db.input);
+        }
+        doms.push(self.data_applied(&env_a, &index_depths, info));
+        // The motive may return a type in any universe
+        let l = self
+            .db
+            .store_checked(CorePrismExpr::LevelOmega, info.origin);
+        let r = self.db.store_checked(CorePrismExpr::Type(l), info.origin);
+        self.fn_types(doms, r, info)
+    }
+
+    /// Generates `(y: B).. -> ih.. -> P t.. (c_k y..)`
+    fn data_case_type(
+        &mut self,
+        info: &DataInfo,
+        k: usize,
+        p_depth: usize,
+        env_b: &NamedEnv,
+    ) -> CoreIndex {
+        let ctor = &info.ctors[k];
+        let mut doms = Vec::new();
+
+        let mut env_y = env_b.clone();
+        let mut y_depths = Vec::with_capacity(ctor.args.len());
+        for arg in &ctor.args {
+            doms.push(self.parsed_to_checked_with_env(arg.typ, &env_y));
+            y_depths.push(env_y.len());
+            env_y = env_y.insert_name(&arg.name.as_str(&self.db.input), &self.db.input);
+        }
+
+        // Induction hypotheses `(z: C).. -> P s.. (y z..)`
+        let mut ih_count = 0;
+        for (j, arg) in ctor.args.iter().enumerate() {
+            let Some(RecursiveArg {
+                binders: zs,
+                indices: ss,
+            }) = &arg.recursive
+            else {
+                continue;
+            };
+            // Only the arguments before `y_j` are in scope of its type
+            let env_z = self.insert_args(env_b, &ctor.args[..j]);
+            let mut env_z = self.hide(&env_z, ctor.args.len() - j + ih_count);
+
+            let mut z_doms = Vec::with_capacity(zs.len());
+            let mut z_depths = Vec::with_capacity(zs.len());
+            for (name, c) in zs {
+                z_doms.push(self.parsed_to_checked_with_env(*c, &env_z));
+                z_depths.push(env_z.len());
+                env_z = env_z.insert_name(&name.as_str(&self.db.input), &self.db.input);
+            }
+            let mut r = self.var(&env_z, p_depth, info);
+            for s in ss {
+                let s = self.parsed_to_checked_with_env(*s, &env_z);
+                r = self.app(r, s, info);
+            }
+            let mut y = self.var(&env_z, y_depths[j], info);
+            for &depth in &z_depths {
+                let z = self.var(&env_z, depth, info);
+                y = self.app(y, z, info);
+            }
+            let r = self.app(r, y, info);
+            doms.push(self.fn_types(z_doms, r, info));
+            ih_count += 1;
+        }
+
+        // Result `P t.. (c_k y..)`
+        let env_t = self.hide(&env_y, ih_count);
+        let mut r = self.var(&env_t, p_depth, info);
+        for t in &ctor.indices {
+            let t = self.parsed_to_checked_with_env(*t, &env_t);
+            r = self.app(r, t, info);
+        }
+        let mut c = self.var(&env_t, info.ctor_depth(k), info);
+        for &depth in &y_depths {
+            let y = self.var(&env_t, depth, info);
+            c = self.app(c, y, info);
+        }
+        let r = self.app(r, c, info);
+        self.fn_types(doms, r, info)
+    }
+
+    /// Generates the computation rule `P => f.. => y.. => f_k y.. ih..` of the eliminator for constructor `k`,
+    /// where for each recursive argument `ih = z.. => D_elim P f.. s.. (y z..)`.
+    fn data_step(&mut self, info: &DataInfo, k: usize, env_s: &NamedEnv) -> CoreIndex {
+        let ctor = &info.ctors[k];
+
+        let p_depth = env_s.len();
+        let f_depths: Vec<usize> = (0..info.ctors.len()).map(|k| p_depth + 1 + k).collect();
+        let env_f = self.hide(env_s, 1 + info.ctors.len());
+        let y_depths: Vec<usize> = (0..ctor.args.len()).map(|j| env_f.len() + j).collect();
+        let env_y = self.insert_args(&env_f, &ctor.args);
+
+        let mut r = self.var(&env_y, f_depths[k], info);
+        for &depth in &y_depths {
+            let y = self.var(&env_y, depth, info);
+            r = self.app(r, y, info);
+        }
+
+        for (j, arg) in ctor.args.iter().enumerate() {
+            let Some(RecursiveArg {
+                binders: zs,
+                indices: ss,
+            }) = &arg.recursive
+            else {
+                continue;
+            };
+            // Only the arguments before `y_j` are in scope of its type
+            let env_z = self.insert_args(&env_f, &ctor.args[..j]);
+            let mut env_z = self.hide(&env_z, ctor.args.len() - j);
+            let mut z_depths = Vec::with_capacity(zs.len());
+            for (name, _) in zs {
+                z_depths.push(env_z.len());
+                env_z = env_z.insert_name(&name.as_str(&self.db.input), &self.db.input);
+            }
+
+            let mut ih = self.var(&env_z, info.elim_depth(), info);
+            for &depth in [&p_depth].into_iter().chain(&f_depths) {
+                let v = self.var(&env_z, depth, info);
+                ih = self.app(ih, v, info);
+            }
+            for s in ss {
+                let s = self.parsed_to_checked_with_env(*s, &env_z);
+                ih = self.app(ih, s, info);
+            }
+            let mut y = self.var(&env_z, y_depths[j], info);
+            for &depth in &z_depths {
+                let z = self.var(&env_z, depth, info);
+                y = self.app(y, z, info);
+            }
+            ih = self.app(ih, y, info);
+            for _ in zs {
+                ih = self
+                    .db
+                    .store_checked(CorePrismExpr::FnConstruct(ih), info.origin);
+            }
+
+            r = self.app(r, ih, info);
+        }
+
+        for _ in 0..1 + info.ctors.len() + ctor.args.len() {
+            r = self
+                .db
+                .store_checked(CorePrismExpr::FnConstruct(r), info.origin);
+        }
+        r
+    }
+}
@@ -8,17 +8,28 @@ impl ParsedPrismExpr {
     fn precedence_level(&self) -> PrecedenceLevel {
         match self {
             ParsedPrismExpr::Let(..) => PrecedenceLevel::Let,
+            ParsedPrismExpr::Data(..) => PrecedenceLevel::Let,
+            ParsedPrismExpr::Import(..) => PrecedenceLevel::Let,
             ParsedPrismExpr::FnConstruct(..) => PrecedenceLevel::Construct,
+            ParsedPrismExpr::ImplicitFnConstruct(..) => PrecedenceLevel::Construct,
             ParsedPrismExpr::FnType(..) => PrecedenceLevel::FnType,
+            ParsedPrismExpr::ImplicitFnType(..) => PrecedenceLevel::FnType,
+            ParsedPrismExpr::PairType(..) => PrecedenceLevel::PairType,
             ParsedPrismExpr::TypeAssert(..) => PrecedenceLevel::TypeAssert,
             ParsedPrismExpr::FnDestruct(..) => PrecedenceLevel::Destruct,
+            ParsedPrismExpr::PairConstruct(..) => PrecedenceLevel::Base,
             ParsedPrismExpr::Free => PrecedenceLevel::Base,
-            ParsedPrismExpr::Type => PrecedenceLevel::Base,
+            ParsedPrismExpr::Type(..) => PrecedenceLevel::Base,
+            ParsedPrismExpr::LevelLiteral(..) => PrecedenceLevel::Base,
+            ParsedPrismExpr::Paramatricity(..) => PrecedenceLevel::Destruct,
+            ParsedPrismExpr::Literal(..) => PrecedenceLevel::Base,
+            ParsedPrismExpr::Hole(..) => PrecedenceLevel::Base,
             ParsedPrismExpr::Name(..) => PrecedenceLevel::Base,
             ParsedPrismExpr::GrammarValue(..) => PrecedenceLevel::Base,
             ParsedPrismExpr::GrammarType => PrecedenceLevel::Base,
             ParsedPrismExpr::ShiftTo { .. } => PrecedenceLevel::Base,
             ParsedPrismExpr::Include(..) => PrecedenceLevel::Base,
+            ParsedPrismExpr::Module(..) => PrecedenceLevel::Base,
         }
     }
 }
@@ -30,20 +41,60 @@ impl<'a> ParserPrismEnv<'a> {
         w: &mut impl Write,
         max_precedence: PrecedenceLevel,
     ) -> std::fmt::Result {
-        let e = &self.parsed_values[*i];
+        let e = &self.db.parsed_values[*i];
 
         if e.precedence_level() < max_precedence {
             write!(w, "(")?;
         }
 
         match e {
-            ParsedPrismExpr::Type => write!(w, "Type")?,
+            ParsedPrismExpr::Type(None) => write!(w, "Type")?,
+            &ParsedPrismExpr::Type(Some(l)) => {
+                write!(w, "Type.{{")?;
+                self.parse_display(l, w, PrecedenceLevel::default())?;
+                write!(w, "}}")?;
+            }
+            ParsedPrismExpr::LevelLiteral(n) => write!(w, "{n}")?,
+            &ParsedPrismExpr::Paramatricity(x) => {
+                write!(w, "paramatricity ")?;
+                self.parse_display(x, w, PrecedenceLevel::Base)?;
+            }
+            ParsedPrismExpr::Literal(l) => write!(w, "{l}")?,
+            ParsedPrismExpr::Hole(n) => write!(w, "?{}", n.as_str(&self.db.input))?,
             &ParsedPrismExpr::Let(ref n, v, b) => {
                 write!(w, "let {} = ", n.as_str(&self.db.input))?;
                 self.parse_display(v, w, PrecedenceLevel::Construct)?;
                 writeln!(w, ";")?;
                 self.parse_display(b, w, PrecedenceLevel::Let)?;
             }
+            &ParsedPrismExpr::Data(ref n, t, ref cs, b) => {
+                write!(w, "data {}: ", n.as_str(&self.db.input))?;
+                self.parse_display(t, w, PrecedenceLevel::Construct)?;
+                writeln!(w, " {{")?;
+                for (c, ct) in cs.iter() {
+                    write!(w, "    {}: ", c.as_str(&self.db.input))?;
+                    self.parse_display(*ct, w, PrecedenceLevel::default())?;
+                    writeln!(w, ";")?;
+                }
+                writeln!(w, "}};")?;
+                self.parse_display(b, w, PrecedenceLevel::Let)?;
+            }
+            &ParsedPrismExpr::Import(m, ref names, b) => {
+                write!(w, "import ")?;
+                self.parse_display(m, w, PrecedenceLevel::Base)?;
+                if let Some(names) = names {
+                    write!(w, " (")?;
+                    for (k, &n) in names.iter().enumerate() {
+                        if k > 0 {
+                            write!(w, ", ")?;
+                        }
+                        self.parse_display(n, w, PrecedenceLevel::Base)?;
+                    }
+                    write!(w, ")")?;
+                }
+                writeln!(w, ";")?;
+                self.parse_display(b, w, PrecedenceLevel::Let)?;
+            }
             ParsedPrismExpr::Name(n) => write!(w, "{}", n.as_str(&self.db.input))?,
             &ParsedPrismExpr::FnType(ref n, a, b) => {
                 write!(w, "({}: ", n.as_str(&self.db.input))?;
@@ -55,11 +106,34 @@ impl<'a> ParserPrismEnv<'a> {
                 write!(w, "{} => ", n.as_str(&self.db.input))?;
                 self.parse_display(b, w, PrecedenceLevel::Construct)?;
             }
+            &ParsedPrismExpr::ImplicitFnType(ref n, a, b) => {
+                write!(w, "{{{}: ", n.as_str(&self.db.input))?;
+                self.parse_display(a, w, PrecedenceLevel::TypeAssert)?;
+                write!(w, "}} -> ")?;
+                self.parse_display(b, w, PrecedenceLevel::FnType)?;
+            }
+            &ParsedPrismExpr::ImplicitFnConstruct(ref n, b) => {
+                write!(w, "{{{}}} => ", n.as_str(&self.db.input))?;
+                self.parse_display(b, w, PrecedenceLevel::Construct)?;
+            }
             &ParsedPrismExpr::FnDestruct(a, b) => {
                 self.parse_display(a, w, PrecedenceLevel::Destruct)?;
                 write!(w, " ")?;
                 self.parse_display(b, w, PrecedenceLevel::Base)?;
             }
+            &ParsedPrismExpr::PairType(ref n, a, b) => {
+                write!(w, "({}: ", n.as_str(&self.db.input))?;
+                self.parse_display(a, w, PrecedenceLevel::TypeAssert)?;
+                write!(w, ") * ")?;
+                self.parse_display(b, w, PrecedenceLevel::PairType)?;
+            }
+            &ParsedPrismExpr::PairConstruct(a, b) => {
+                write!(w, "(")?;
+                self.parse_display(a, w, PrecedenceLevel::default())?;
+                write!(w, ", ")?;
+                self.parse_display(b, w, PrecedenceLevel::default())?;
+                write!(w, ")")?;
+            }
             ParsedPrismExpr::Free => write!(w, "{{{}}}", i.0)?,
             &ParsedPrismExpr::TypeAssert(e, typ) => {
                 self.parse_display(e, w, PrecedenceLevel::Destruct)?;
@@ -93,6 +167,7 @@ impl<'a> ParserPrismEnv<'a> {
             ParsedPrismExpr::Include(n, _) => {
                 write!(w, "include!({})", n.as_str(&self.db.input))?;
             }
+            ParsedPrismExpr::Module(n, _) => write!(w, "{}", n.as_str(&self.db.input))?,
         }
 
         if e.precedence_level() < max_precedence {
@@ -0,0 +1,199 @@
+use crate::lang::env::{DbEnv, EnvEntry};
+use crate::lang::modules::Export;
+use crate::lang::{CoreIndex, CorePrismExpr, ValueOrigin};
+use crate::parser::named_env::NamedEnv;
+use crate::parser::{ParsedIndex, ParsedPrismExpr, ParserPrismEnv};
+use crate::type_check::UniqueVariableId;
+use prism_diag_derive::Diagnostic;
+use prism_input::span::Span;
+use std::sync::Arc;
+
+/// A name is imported from a module that does not export it.
+///
+/// A module exports the names defined by its top-level `let` and `data` statements.
+/// For example, if `lib.pr` does not define `question`:
+///
+/// ```text
+/// import lib (answer, question);
+/// answer
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Module `{}` does not export `{}`", self.module, self.name))]
+pub(crate) struct UnknownImport {
+    #[sugg]
+    span: Span,
+    module: String,
+    name: String,
+}
+
+impl<'a> ParserPrismEnv<'a> {
+    /// Converts the file `parsed`, returning the converted file and the statements it exports
+    pub fn file_to_checked(&mut self, parsed: ParsedIndex) -> (CoreIndex, Arc<[Export]>) {
+        let core = self.parsed_to_checked_with_env(parsed, &NamedEnv::default());
+        (core, self.exports(parsed, core).into())
+    }
+
+    /// Returns the top-level statements of the file `parsed`, which was converted to `core`
+    fn exports(&self, mut parsed: ParsedIndex, mut core: CoreIndex) -> Vec<Export> {
+        let input = &self.db.input;
+        let mut exports = Vec::new();
+        loop {
+            match (
+                &self.db.parsed_values[*parsed],
+                &self.db.checked_values[*core],
+            ) {
+                (&ParsedPrismExpr::Let(ref n, v, b), &CorePrismExpr::Let(_, cb)) => {
+                    // The grammar of an `adapt` can't be referred to by name
+                    let n = n.as_str(input);
+                    let name = if n.contains('#') { "_" } else { &n };
+                    exports.push(Export {
+                        node: core,
+                        names: vec![(name.to_string(), self.implicit_args_of_value(v))],
+                    });
+                    (parsed, core) = (b, cb);
+                }
+                (&ParsedPrismExpr::Data(ref n, _, ref cs, b), &CorePrismExpr::Data(_, cb)) => {
+                    let n = n.as_str(input);
+                    let mut names = vec![(n.to_string(), 0)];
+                    names.extend(cs.iter().map(|&(ref c, t)| {
+                        (c.as_str(input).to_string(), self.implicit_args_of_type(t))
+                    }));
+                    names.push((format!("{n}_elim"), 0));
+                    exports.push(Export { node: core, names });
+                    (parsed, core) = (b, cb);
+                }
+                (&ParsedPrismExpr::Import(m, _, b), _) => {
+                    // The statements of an imported module are not exported again
+                    for export in self.module(m).map(|(_, e)| e).unwrap_or_default().iter() {
+                        exports.push(Export {
+                            node: core,
+                            names: vec![("_".to_string(), 0); export.names.len()],
+                        });
+                        core = self.statement_body(core);
+                    }
+                    parsed = b;
+                }
+                (&ParsedPrismExpr::ShiftTo { expr, .. }, _) => parsed = expr,
+                _ => break,
+            }
+        }
+        exports
+    }
+
+    /// Converts the `import` statement `i`, the statements of the imported module are placed in front of its body
+    pub(super) fn import_to_checked(&mut self, i: ParsedIndex, env: &NamedEnv) -> CoreIndex {
+        let origin = ValueOrigin::SourceCode(self.db.parsed_spans[*i]);
+        let ParsedPrismExpr::Import(m, ref names, b) = self.db.parsed_values[*i] else {
+            unreachable!()
+        };
+        let names = names.clone();
+        let Some((module, exports)) = self.module(m) else {
+            return self.parsed_to_checked_with_env(b, env);
+        };
+
+        for &n in names.iter().flat_map(|names| names.iter()) {
+            let name = self.import_name(n);
+            if name == "_"
+                || !exports
+                    .iter()
+                    .any(|e| e.names.iter().any(|(x, _)| *x == name))
+            {
+                self.db.push_error(UnknownImport {
+                    span: self.db.parsed_spans[*n],
+                    module: module.clone(),
+                    name,
+                });
+            }
+        }
+
+        let body_env = self.import_env(m, names.as_deref(), env);
+        let body = self.parsed_to_checked_with_env(b, &body_env);
+        exports.iter().rev().fold(body, |b, export| {
+            let e = match &self.db.checked_values[*export.node] {
+                &CorePrismExpr::Let(v, _) => CorePrismExpr::Let(v, b),
+                CorePrismExpr::Data(decl, _) => CorePrismExpr::Data(decl.clone(), b),
+                _ => unreachable!("Exported statements are `let` or `data` statements"),
+            };
+            self.db.store_checked(e, origin)
+        })
+    }
+
+    /// Returns `env` with the names bound by importing the module `m`.
+    /// If `names` is given only those names are in scope, otherwise each name `x` is in scope as `m.x`.
+    pub(super) fn import_env(
+        &self,
+        m: ParsedIndex,
+        names: Option<&[ParsedIndex]>,
+        env: &NamedEnv,
+    ) -> NamedEnv {
+        let Some((module, exports)) = self.module(m) else {
+            return env.clone();
+        };
+        let names: Option<Vec<String>> =
+            names.map(|names| names.iter().map(|&n| self.import_name(n)).collect());
+
+        let mut env = env.clone();
+        for (name, implicit_args) in exports.iter().flat_map(|e| e.names.iter()) {
+            let name = match &names {
+                _ if name == "_" => "_".to_string(),
+                None => format!("{module}.{name}"),
+                Some(names) if names.contains(name) => name.clone(),
+                Some(_) => "_".to_string(),
+            };
+            env = env.insert_name_with_implicits(&name, *implicit_args, &self.db.input);
+        }
+        env
+    }
+
+    /// Returns `env` with the statements of the module `m`.
+    /// The values of `let` statements are known, the names bound by `data` statements are dummy entries.
+    pub(super) fn import_db_env(&self, m: ParsedIndex, mut env: DbEnv) -> DbEnv {
+        let Some((_, exports)) = self.module(m) else {
+            return env;
+        };
+        // The values of the module are closed apart from the statements before them
+        let mut module_env = DbEnv::default();
+        for export in exports.iter() {
+            match self.db.checked_values[*export.node] {
+                CorePrismExpr::Let(v, _) => {
+                    let entry = EnvEntry::RSubst(v, module_env.clone());
+                    module_env = module_env.cons(entry.clone());
+                    env = env.cons(entry);
+                }
+                _ => {
+                    for _ in &export.names {
+                        module_env = module_env.cons(EnvEntry::RType(UniqueVariableId::DUMMY));
+                        env = env.cons(EnvEntry::RType(UniqueVariableId::DUMMY));
+                    }
+                }
+            }
+        }
+        env
+    }
+
+    /// Returns the name and the exports of the imported module `m`, if it could be imported
+    fn module(&self, m: ParsedIndex) -> Option<(String, Arc<[Export]>)> {
+        match &self.db.parsed_values[*m] {
+            ParsedPrismExpr::Module(name, exports) => {
+                Some((name.as_str(&self.db.input).to_string(), exports.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the name imported by `import m (n)`, `n` is `Free` if it is `_`
+    fn import_name(&self, n: ParsedIndex) -> String {
+        match &self.db.parsed_values[*n] {
+            ParsedPrismExpr::Name(n) => n.as_str(&self.db.input).to_string(),
+            _ => "_".to_string(),
+        }
+    }
+
+    /// Returns the expression that the `let` or `data` statement `core` is followed by
+    fn statement_body(&self, core: CoreIndex) -> CoreIndex {
+        match self.db.checked_values[*core] {
+            CorePrismExpr::Let(_, b) | CorePrismExpr::Data(_, b) => b,
+            _ => unreachable!("Imported statements are `let` or `data` statements"),
+        }
+    }
+}
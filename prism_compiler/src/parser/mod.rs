@@ -1,26 +1,32 @@
+use crate::lang::literal::Literal;
+use crate::lang::modules::Export;
 use crate::lang::{CoreIndex, PrismDb};
+use crate::parser::named_env::NamedEnv;
 use prism_diag_derive::Diagnostic;
 use prism_input::input::Input;
 use prism_input::input_table::{InputTable, InputTableIndex};
 use prism_input::span::Span;
 use prism_parser::core::tokens::Tokens;
-use prism_parser::error::ParseError;
 use prism_parser::error::set_error::SetError;
 use prism_parser::grammar::grammar_file::GrammarFile;
 use prism_parser::parsable::parsable_dyn::ParsableDyn;
 use prism_parser::parse_grammar;
 use prism_parser::parser::VarMap;
-use prism_parser::parser::instance::run_parser_rule;
-use std::collections::HashMap;
+use prism_parser::parser::instance::IncrementalParser;
+use std::collections::{HashMap, HashSet};
 use std::io;
+use std::io::Read;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::{Arc, LazyLock};
 
+pub(crate) mod check_grammar;
+pub(crate) mod data_to_checked;
 mod display;
+pub(crate) mod imports;
 pub mod named_env;
 pub mod parse_expr;
-mod parsed_to_checked;
+pub(crate) mod parsed_to_checked;
 
 pub static GRAMMAR: LazyLock<(InputTable, Arc<GrammarFile>)> = LazyLock::new(|| {
     let (table, grammar, _tokens, errs) =
@@ -29,19 +35,64 @@ pub static GRAMMAR: LazyLock<(InputTable, Arc<GrammarFile>)> = LazyLock::new(||
     (table.deep_clone(), grammar)
 });
 
+/// A file could not be read, for example because it does not exist.
+///
+/// Files are read when they are given on the command line, and when they are included or imported.
+/// The paths of included and imported files are relative to the file that includes them:
+///
+/// ```prism
+/// include!(does_not_exist)
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Failed to read file `{:?}`: {}", self.path, self.error))]
+pub(crate) struct FailedToRead {
+    path: PathBuf,
+    error: io::Error,
+    /// The `include!` or `import` that read the file, if it was not given on the command line
+    #[sugg(label = if self.imported { "Imported here" } else { "Included here" })]
+    read_at: Option<Span>,
+    imported: bool,
+}
+
 impl PrismDb {
     pub fn load_file(&mut self, path: PathBuf) -> Option<InputTableIndex> {
-        #[derive(Diagnostic)]
-        #[diag(title = format!("Failed to read file `{:?}`: {}", self.path, self.error))]
-        struct FailedToRead {
-            path: PathBuf,
-            error: io::Error,
-        }
+        self.load_file_read_at(path, None, false)
+    }
 
+    /// Loads the file at `path`, which is read because of the `include!` or `import` at `read_at`
+    pub(crate) fn load_file_read_at(
+        &mut self,
+        path: PathBuf,
+        read_at: Option<Span>,
+        imported: bool,
+    ) -> Option<InputTableIndex> {
         match std::fs::read_to_string(&path) {
             Ok(program) => Some(self.load_input(program, path)),
             Err(error) => {
-                self.push_error(FailedToRead { path, error });
+                self.push_error(FailedToRead {
+                    path,
+                    error,
+                    read_at,
+                    imported,
+                });
+                None
+            }
+        }
+    }
+
+    /// Loads the program read from stdin, under the path `<stdin>`
+    pub fn load_stdin(&mut self) -> Option<InputTableIndex> {
+        let path = PathBuf::from("<stdin>");
+        let mut program = String::new();
+        match io::stdin().read_to_string(&mut program) {
+            Ok(_) => Some(self.load_input(program, path)),
+            Err(error) => {
+                self.push_error(FailedToRead {
+                    path,
+                    error,
+                    read_at: None,
+                    imported: false,
+                });
                 None
             }
         }
@@ -54,18 +105,20 @@ impl PrismDb {
 
 pub struct ParserPrismEnv<'a> {
     db: &'a mut PrismDb,
-
-    // Parsed Values
-    pub parsed_values: Vec<ParsedPrismExpr>,
-    pub parsed_spans: Vec<Span>,
+    /// The spans of the adapted grammars and of the values they construct that errors were reported for.
+    /// These may be evaluated multiple times while parsing, their errors are only reported once.
+    reported_spans: HashSet<Span>,
+    /// The parsed value of which the converted value and the names in scope are kept while converting,
+    /// see `parsed_to_checked_with_scope_of`
+    scope_of: Option<(ParsedIndex, Option<(CoreIndex, NamedEnv)>)>,
 }
 
 impl<'a> ParserPrismEnv<'a> {
     pub fn new(db: &'a mut PrismDb) -> Self {
         Self {
             db,
-            parsed_values: Default::default(),
-            parsed_spans: Default::default(),
+            reported_spans: HashSet::new(),
+            scope_of: None,
         }
     }
 
@@ -74,29 +127,49 @@ impl<'a> ParserPrismEnv<'a> {
     }
 
     fn store_parsed(&mut self, e: ParsedPrismExpr, origin: Span) -> ParsedIndex {
-        self.parsed_values.push(e);
-        self.parsed_spans.push(origin);
-        ParsedIndex(self.parsed_values.len() - 1)
+        self.db.parsed_values.push(e);
+        self.db.parsed_spans.push(origin);
+        ParsedIndex(self.db.parsed_values.len() - 1)
+    }
+
+    /// Counts the changes that parsing made to the database, besides storing parsed values.
+    /// Parses that change the database are not reused, since their changes would not be made again.
+    fn effects(&self) -> usize {
+        self.db.diags.len() + self.db.checked_values.len() + self.db.modules.include_count()
     }
 
     pub fn parse_file(&mut self, file: InputTableIndex) -> (ParsedIndex, Arc<Tokens>) {
         let mut parsables = HashMap::new();
         parsables.insert("Expr", ParsableDyn::new::<ParsedIndex>());
 
-        let (expr, tokens, errs) = run_parser_rule::<ParserPrismEnv<'a>, ParsedIndex, SetError>(
-            &GRAMMAR.1,
+        // Reuse what is still valid of the last parse of the file, see `PrismDb::edit_file`
+        let mut parser = match self.db.parsers.remove(&file) {
+            Some(parser) => parser,
+            None => IncrementalParser::new(&self.db.input, &GRAMMAR.1)
+                .expect("The Prism grammar can be adapted"),
+        };
+        let (pv, errs) = parser.run(
             "expr",
             self.db.input.clone(),
             file,
             parsables,
             self,
+            Self::effects,
         );
+        self.db.parsers.insert(file, parser);
 
-        for err in errs.errors {
-            self.db.diags.push(err.diag());
-        }
+        self.db.diags.extend(errs.diags());
 
-        (*expr, tokens)
+        // If the file could not be parsed at all, continue with an unknown value
+        let expr = match pv.parsed.try_into_value::<ParsedIndex>() {
+            Some(expr) => *expr,
+            None => {
+                assert!(!self.db.diags.is_empty());
+                let span = self.db.input.inner().span_of(file);
+                self.store_from_source(ParsedPrismExpr::Free, span)
+            }
+        };
+        (expr, pv.tokens)
     }
 }
 
@@ -115,12 +188,28 @@ impl Deref for ParsedIndex {
 pub enum ParsedPrismExpr {
     // Real expressions
     Free,
-    Type,
+    /// A universe, with the level if it is given explicitly
+    Type(Option<ParsedIndex>),
     Let(Input, ParsedIndex, ParsedIndex),
     FnType(Input, ParsedIndex, ParsedIndex),
     FnConstruct(Input, ParsedIndex),
+    /// A function type `{x: A} -> B`, its argument is inserted automatically when a function of this type is used
+    ImplicitFnType(Input, ParsedIndex, ParsedIndex),
+    /// A function `{x} => b`, its argument is inserted automatically when it is used
+    ImplicitFnConstruct(Input, ParsedIndex),
     FnDestruct(ParsedIndex, ParsedIndex),
     TypeAssert(ParsedIndex, ParsedIndex),
+    PairType(Input, ParsedIndex, ParsedIndex),
+    PairConstruct(ParsedIndex, ParsedIndex),
+    Data(Input, ParsedIndex, Arc<[(Input, ParsedIndex)]>, ParsedIndex),
+    /// An `import m;` statement, or `import m (x, y);` if the imported names are given.
+    /// The module is a `Module`, or `Free` if it could not be imported.
+    Import(ParsedIndex, Option<Arc<[ParsedIndex]>>, ParsedIndex),
+    LevelLiteral(usize),
+    Paramatricity(ParsedIndex),
+    Literal(Literal),
+    /// A named hole `?goal`, its expected type is reported after type checking
+    Hole(Input),
 
     // Temporary expressions after parsing
     Name(Input),
@@ -133,4 +222,6 @@ pub enum ParsedPrismExpr {
     GrammarValue(Arc<GrammarFile>),
     GrammarType,
     Include(Input, CoreIndex),
+    /// The module imported by an `import`, with the statements it exports
+    Module(Input, Arc<[Export]>),
 }
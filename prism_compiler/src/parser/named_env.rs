@@ -3,7 +3,7 @@ use prism_input::input_table::InputTable;
 use prism_parser::env::GenericEnv;
 use prism_parser::grammar::grammar_file::GrammarFile;
 use prism_parser::parsable::parsed::Parsed;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Default, Clone)]
 pub struct NamedEnv {
@@ -16,26 +16,44 @@ pub type NamesEnv = GenericEnv<String, NamesEntry>;
 
 #[derive(Clone)]
 pub enum NamesEntry {
-    FromEnv(usize),
+    /// A variable bound at the given depth, which expects the given number of implicit arguments
+    FromEnv(usize, usize),
     FromGrammarEnv {
         grammar_env_len: usize,
         adapt_env_len: usize,
         prev_env_len: usize,
+        implicit_args: usize,
     },
     FromParsed(Parsed, NamesEnv),
 }
 
 impl NamedEnv {
     pub fn insert_name(&self, name: &str, input: &InputTable) -> Self {
-        let mut s = self.insert_name_at(name, self.env_len, input);
+        self.insert_name_with_implicits(name, 0, input)
+    }
+
+    /// Inserts a name, for which `implicit_args` arguments are inserted automatically when it is used
+    pub fn insert_name_with_implicits(
+        &self,
+        name: &str,
+        implicit_args: usize,
+        input: &InputTable,
+    ) -> Self {
+        let mut s = self.insert_name_at(name, self.env_len, implicit_args, input);
         s.env_len += 1;
         s
     }
 
-    pub fn insert_name_at(&self, name: &str, depth: usize, input: &InputTable) -> Self {
+    pub fn insert_name_at(
+        &self,
+        name: &str,
+        depth: usize,
+        implicit_args: usize,
+        input: &InputTable,
+    ) -> Self {
         let names = self
             .names
-            .insert(name.to_string(), NamesEntry::FromEnv(depth));
+            .insert(name.to_string(), NamesEntry::FromEnv(depth, implicit_args));
         let hygienic_names = if let Some(NamesEntry::FromParsed(ar, _)) = self.names.get(name) {
             let new_name = ar.value_ref::<Input>().as_str(input).to_string();
             self.hygienic_names.insert(new_name, depth)
@@ -54,6 +72,49 @@ impl NamedEnv {
         self.names.get(name)
     }
 
+    /// Returns the name of each variable in scope, the last name being the innermost variable.
+    /// Variables that are shadowed or that can't be referred to by name are `None`.
+    pub fn names_in_scope(&self) -> Vec<Option<String>> {
+        let mut names = vec![None; self.env_len];
+        let mut seen = HashSet::new();
+        for (name, entry) in self.names.iter() {
+            if !seen.insert(name.as_str()) {
+                continue;
+            }
+            if let &NamesEntry::FromEnv(depth, _) = entry
+                && depth < self.env_len
+                && name != "_"
+                && !name.contains('#')
+            {
+                names[depth] = Some(name.clone());
+            }
+        }
+        names
+    }
+
+    /// Returns the name in scope that is closest to `name`, if one is close enough to likely be a typo of it.
+    /// The boolean is `true` if no other name in scope is as close.
+    pub fn closest_name(&self, name: &str) -> Option<(&str, bool)> {
+        let max_distance = (name.chars().count() / 3).max(1);
+        let mut closest: Option<(&str, usize, bool)> = None;
+        for (candidate, _) in self.names.iter() {
+            if candidate == "_" || candidate.contains('#') {
+                continue;
+            }
+            let distance = edit_distance(name, candidate);
+            if distance > max_distance {
+                continue;
+            }
+            closest = match closest {
+                // Inner names are seen first, and shadowed names are the same name
+                Some((c, d, unique)) if d < distance => Some((c, d, unique)),
+                Some((c, d, unique)) if d == distance => Some((c, d, unique && c == candidate)),
+                _ => Some((candidate, distance, true)),
+            };
+        }
+        closest.map(|(c, _, unique)| (c, unique))
+    }
+
     pub fn len(&self) -> usize {
         self.env_len
     }
@@ -62,12 +123,13 @@ impl NamedEnv {
         self.env_len == 0
     }
 
+    /// Remembers the names in scope of `grammar`, which are in scope of the code that is parsed by it
     pub fn insert_shift_label(
         &self,
         grammar: &GrammarFile,
-        jump_labels: &mut HashMap<*const GrammarFile, NamesEnv>,
+        grammar_scopes: &mut HashMap<usize, NamesEnv>,
     ) {
-        jump_labels.insert(grammar as *const GrammarFile, self.names.clone());
+        grammar_scopes.insert(grammar_id(grammar), self.names.clone());
     }
 
     pub fn shift_back(&self, old_names: &NamesEnv, input: &InputTable) -> Self {
@@ -79,9 +141,68 @@ impl NamedEnv {
         };
 
         for (name, db_idx) in self.hygienic_names.iter() {
-            new_env = new_env.insert_name_at(name, *db_idx, input);
+            new_env = new_env.insert_name_at(name, *db_idx, 0, input);
         }
 
         new_env
     }
 }
+
+/// The number of characters that need to be inserted, removed, replaced or swapped with the next character to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // The distances between prefixes of `a` and `b`, of the current and the previous two rows
+    let mut prev2: Vec<usize> = vec![];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 0..a.len() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for j in 0..b.len() {
+            cur[j + 1] = (prev[j] + usize::from(a[i] != b[j]))
+                .min(prev[j + 1] + 1)
+                .min(cur[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                cur[j + 1] = cur[j + 1].min(prev2[j - 1] + 1);
+            }
+        }
+        prev2 = prev;
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// Grammars are identified by their address, they are kept alive by the parsed values of the `PrismDb`
+pub fn grammar_id(grammar: &GrammarFile) -> usize {
+    grammar as *const GrammarFile as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_with(names: &[&str]) -> NamedEnv {
+        let input = InputTable::default();
+        names.iter().fold(NamedEnv::default(), |env, name| {
+            env.insert_name(name, &input)
+        })
+    }
+
+    #[test]
+    fn closest_name_unique() {
+        let env = env_with(&["valu", "value1"]);
+        assert_eq!(env.closest_name("valuex"), Some(("value1", true)));
+    }
+
+    #[test]
+    fn closest_name_tie_then_farther() {
+        // `value2` and `value1` are equally close, and are seen before the farther `valu`
+        let env = env_with(&["valu", "value1", "value2"]);
+        assert_eq!(env.closest_name("valuex"), Some(("value2", false)));
+    }
+
+    #[test]
+    fn closest_name_shadowed() {
+        let env = env_with(&["value", "value"]);
+        assert_eq!(env.closest_name("valu"), Some(("value", true)));
+    }
+}
@@ -1,8 +1,10 @@
-use crate::lang::CorePrismExpr;
 use crate::lang::env::{DbEnv, EnvEntry};
+use crate::lang::literal::{Literal, unescape};
+use crate::lang::{CorePrismExpr, ValueOrigin};
 use crate::parser::named_env::NamedEnv;
 use crate::parser::{ParsedIndex, ParsedPrismExpr, ParserPrismEnv};
 use crate::type_check::UniqueVariableId;
+use prism_diag_derive::Diagnostic;
 use prism_input::input::Input;
 use prism_input::input_table::InputTable;
 use prism_input::span::Span;
@@ -11,10 +13,74 @@ use prism_parser::grammar::grammar_file::GrammarFile;
 use prism_parser::parsable::Parsable;
 use prism_parser::parsable::parsed::{ArcExt, Parsed};
 use prism_parser::parser::VarMap;
+use prism_parser::parser::parsed_list::ParsedList;
 use prism_parser::parser::placeholder_store::{ParsedPlaceholder, PlaceholderStore};
+use std::fmt::{Display, Formatter};
 use std::sync::Arc;
 
-pub type PrismEvalCtx = GenericEnv<ParsedPlaceholder, Option<ParsedPlaceholder>>;
+pub type PrismEvalCtx = GenericEnv<ParsedPlaceholder, PrismEvalCtxEntry>;
+
+#[derive(Copy, Clone)]
+pub enum PrismEvalCtxEntry {
+    /// A `let` binding, with the placeholder of the bound value
+    Let(Option<ParsedPlaceholder>),
+    /// A `data` declaration, with the placeholder of its constructors
+    Data(ParsedPlaceholder),
+    /// An `import`, with the placeholder of the imported names if they are given
+    Import(Option<ParsedPlaceholder>),
+}
+
+/// A universe level such as the `0` in `Type 0` is too large to be represented.
+///
+/// ```prism
+/// Type 18446744073709551616
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = "Level is too large")]
+pub(crate) struct LevelTooLarge {
+    #[sugg]
+    span: Span,
+}
+
+/// A number literal is too large to be represented, literals of type `Nat` can be at most `18446744073709551615`.
+///
+/// ```prism
+/// 18446744073709551616
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = "Literal is too large")]
+pub(crate) struct LiteralTooLarge {
+    #[sugg]
+    span: Span,
+}
+
+/// A string literal contains a `\` that is not followed by a valid escape sequence.
+///
+/// The valid escape sequences are `\n`, `\r`, `\\` and `\"`.
+/// The grammar of string literals only accepts these, so this indicates a grammar that parses string literals
+/// in another way.
+#[derive(Diagnostic)]
+#[diag(title = "Invalid escape sequence in string literal")]
+pub(crate) struct InvalidEscape {
+    #[sugg]
+    span: Span,
+}
+
+/// The value given to `adapt` is not a grammar, or it can't be evaluated to one while parsing.
+///
+/// The grammar is evaluated before the rest of the file is parsed, so it can only refer to values that are known
+/// at that point. For example, `Type` is not a grammar:
+///
+/// ```prism
+/// adapt Type;
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = "Expected a grammar that can be evaluated while parsing")]
+pub(crate) struct NotAGrammar {
+    #[sugg]
+    span: Span,
+}
 
 pub fn eval_ctx_to_envs(
     env: &PrismEvalCtx,
@@ -30,6 +96,45 @@ pub fn eval_ctx_to_envs(
             let dummy_named_env = named_env.insert_name("_", &prism_env.db.input);
             let dummy_db_env = db_env.cons(EnvEntry::RType(UniqueVariableId::DUMMY));
 
+            let value = match *value {
+                PrismEvalCtxEntry::Let(value) => value,
+                PrismEvalCtxEntry::Import(names) => {
+                    let m = *placeholders
+                        .get(*key)
+                        .expect("The module of an import is parsed before its body")
+                        .value_ref::<ParsedIndex>();
+                    let names: Option<Vec<_>> = names.map(|names| {
+                        placeholders
+                            .get(names)
+                            .expect("The names of an import are parsed before its body")
+                            .value_ref::<ParsedList>()
+                            .iter()
+                            .map(|(_, n)| *n.value_ref::<ParsedIndex>())
+                            .collect()
+                    });
+                    let named_env = prism_env.import_env(m, names.as_deref(), &named_env);
+                    let db_env = prism_env.import_db_env(m, db_env);
+                    return (named_env, db_env);
+                }
+                PrismEvalCtxEntry::Data(constructors) => {
+                    // A data declaration binds the data type, its constructors and its eliminator.
+                    // These are never needed to evaluate a grammar, so they are all dummy entries.
+                    let constructors = placeholders
+                        .get(constructors)
+                        .expect("Constructors of a data declaration are parsed before its body")
+                        .value_ref::<ParsedList>()
+                        .iter()
+                        .count();
+                    let mut named_env = named_env;
+                    let mut db_env = db_env;
+                    for _ in 0..constructors + 2 {
+                        named_env = named_env.insert_name("_", &prism_env.db.input);
+                        db_env = db_env.cons(EnvEntry::RType(UniqueVariableId::DUMMY));
+                    }
+                    return (named_env, db_env);
+                }
+            };
+
             // If the name or value of this entry is not known, continue
             let Some(key) = placeholders.get(*key) else {
                 return (dummy_named_env, dummy_db_env);
@@ -40,12 +145,11 @@ pub fn eval_ctx_to_envs(
             let Some(value) = value else {
                 return (dummy_named_env, dummy_db_env);
             };
-            let Some(value) = placeholders.get(*value) else {
+            let Some(value) = placeholders.get(value) else {
                 return (dummy_named_env, dummy_db_env);
             };
             let value = *value.value_ref::<ParsedIndex>();
-            let value =
-                prism_env.parsed_to_checked_with_env(value, &named_env, &mut Default::default());
+            let value = prism_env.parsed_to_checked_with_env(value, &named_env);
 
             let named_env = named_env.insert_name(&key, &prism_env.db.input);
             let db_env = db_env.cons(EnvEntry::RSubst(value, db_env.clone()));
@@ -54,6 +158,53 @@ pub fn eval_ctx_to_envs(
     }
 }
 
+/// The kind of value that is passed as an argument to an expression constructor
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub(crate) enum ArgKind {
+    /// A piece of the input, such as a name or the digits of a literal
+    Input,
+    /// An `Expr`
+    Expr,
+    /// A list, such as a `#repeat` or a list constructed using `..` and `[]`
+    List,
+    /// A grammar, such as the grammar of a `grammar { ... }` value
+    Grammar,
+    /// Any value
+    Any,
+}
+
+impl Display for ArgKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ArgKind::Input => "a string",
+            ArgKind::Expr => "an expression",
+            ArgKind::List => "a list",
+            ArgKind::Grammar => "a grammar",
+            ArgKind::Any => "a value",
+        })
+    }
+}
+
+/// Returns the possible arguments of the expression constructor `name`, or `None` if grammars cannot construct it.
+/// These are the arguments that `from_construct` and `create_eval_ctx` expect, see `check_construct`.
+pub(crate) fn expr_constructor_args(name: &str) -> Option<&'static [&'static [ArgKind]]> {
+    use ArgKind::*;
+    Some(match name {
+        "Type" => &[&[], &[Expr]],
+        "LevelLiteral" | "NatLiteral" | "IntLiteral" | "StringLiteral" | "Name" | "Hole"
+        | "Include" | "Module" => &[&[Input]],
+        "Let" | "FnType" | "ImplicitFnType" | "PairType" => &[&[Input, Expr, Expr]],
+        "FnConstruct" | "ImplicitFnConstruct" => &[&[Input, Expr]],
+        "FnDestruct" | "TypeAssert" | "PairConstruct" | "Import" => &[&[Expr, Expr]],
+        "Data" => &[&[Input, Expr, List, Expr]],
+        "ImportNames" => &[&[Expr, List, Expr]],
+        "Paramatricity" => &[&[Expr]],
+        "GrammarValue" => &[&[Grammar]],
+        "GrammarType" => &[&[]],
+        _ => return None,
+    })
+}
+
 impl Parsable<ParserPrismEnv<'_>> for ParsedIndex {
     type EvalCtx = PrismEvalCtx;
 
@@ -64,14 +215,49 @@ impl Parsable<ParserPrismEnv<'_>> for ParsedIndex {
         env: &mut ParserPrismEnv<'_>,
         input: &InputTable,
     ) -> Self {
-        let expr: ParsedPrismExpr = match constructor {
-            "Type" => {
-                assert_eq!(args.len(), 0);
+        // `EnvCapture` is constructed by the parser itself, rather than by a grammar
+        if constructor != "EnvCapture" && !env.check_construct(span, constructor, args) {
+            return env.store_from_source(ParsedPrismExpr::Free, span);
+        }
 
-                ParsedPrismExpr::Type
+        let expr: ParsedPrismExpr = match constructor {
+            "Type" => ParsedPrismExpr::Type(args.first().map(|l| *l.value_ref::<ParsedIndex>())),
+            "LevelLiteral" => {
+                let n = args[0].value_ref::<Input>().as_str(input);
+                match n.parse() {
+                    Ok(n) => ParsedPrismExpr::LevelLiteral(n),
+                    Err(_) => {
+                        env.db.push_error(LevelTooLarge { span });
+                        ParsedPrismExpr::Free
+                    }
+                }
+            }
+            "NatLiteral" | "IntLiteral" => {
+                let n = args[0].value_ref::<Input>().as_str(input);
+                let literal = if constructor == "NatLiteral" {
+                    n.parse().map(Literal::Nat).ok()
+                } else {
+                    n.parse().map(Literal::Int).ok()
+                };
+                match literal {
+                    Some(l) => ParsedPrismExpr::Literal(l),
+                    None => {
+                        env.db.push_error(LiteralTooLarge { span });
+                        ParsedPrismExpr::Free
+                    }
+                }
+            }
+            "StringLiteral" => {
+                let s = args[0].value_ref::<Input>().as_str(input);
+                match unescape(&s) {
+                    Some(s) => ParsedPrismExpr::Literal(Literal::String(s.into())),
+                    None => {
+                        env.db.push_error(InvalidEscape { span });
+                        ParsedPrismExpr::Free
+                    }
+                }
             }
             "Name" => {
-                assert_eq!(args.len(), 1);
                 let parsed = &args[0];
                 let name = parsed.value_ref::<Input>().clone();
                 if name.as_str(input) == "_" {
@@ -80,51 +266,97 @@ impl Parsable<ParserPrismEnv<'_>> for ParsedIndex {
                     ParsedPrismExpr::Name(name)
                 }
             }
+            "Hole" => ParsedPrismExpr::Hole(args[0].value_ref::<Input>().clone()),
             "Let" => {
-                assert_eq!(args.len(), 3);
                 let parsed = &args[0];
                 let name = parsed.value_ref::<Input>().clone();
                 let v = *args[1].value_ref::<ParsedIndex>();
                 let b = *args[2].value_ref::<ParsedIndex>();
                 ParsedPrismExpr::Let(name, v, b)
             }
-            "FnType" => {
-                assert_eq!(args.len(), 3);
+            "FnType" | "ImplicitFnType" => {
                 let parsed = &args[0];
                 let name = parsed.value_ref::<Input>().clone();
                 let v = *args[1].value_ref::<ParsedIndex>();
                 let b = *args[2].value_ref::<ParsedIndex>();
-                ParsedPrismExpr::FnType(name, v, b)
+                if constructor == "FnType" {
+                    ParsedPrismExpr::FnType(name, v, b)
+                } else {
+                    ParsedPrismExpr::ImplicitFnType(name, v, b)
+                }
             }
-            "FnConstruct" => {
-                assert_eq!(args.len(), 2);
+            "FnConstruct" | "ImplicitFnConstruct" => {
                 let parsed = &args[0];
                 let name = parsed.value_ref::<Input>().clone();
                 let b = *args[1].value_ref::<ParsedIndex>();
-                ParsedPrismExpr::FnConstruct(name, b)
+                if constructor == "FnConstruct" {
+                    ParsedPrismExpr::FnConstruct(name, b)
+                } else {
+                    ParsedPrismExpr::ImplicitFnConstruct(name, b)
+                }
             }
             "FnDestruct" => {
-                assert_eq!(args.len(), 2);
                 let f = *args[0].value_ref::<ParsedIndex>();
                 let v = *args[1].value_ref::<ParsedIndex>();
                 ParsedPrismExpr::FnDestruct(f, v)
             }
             "TypeAssert" => {
-                assert_eq!(args.len(), 2);
-
                 let e = *args[0].value_ref::<ParsedIndex>();
                 let typ = *args[1].value_ref::<ParsedIndex>();
                 ParsedPrismExpr::TypeAssert(e, typ)
             }
+            "PairType" => {
+                let parsed = &args[0];
+                let name = parsed.value_ref::<Input>().clone();
+                let a = *args[1].value_ref::<ParsedIndex>();
+                let b = *args[2].value_ref::<ParsedIndex>();
+                ParsedPrismExpr::PairType(name, a, b)
+            }
+            "PairConstruct" => {
+                let a = *args[0].value_ref::<ParsedIndex>();
+                let b = *args[1].value_ref::<ParsedIndex>();
+                ParsedPrismExpr::PairConstruct(a, b)
+            }
+            "Data" => {
+                let name = args[0].value_ref::<Input>().clone();
+                let typ = *args[1].value_ref::<ParsedIndex>();
+                let constructors = args[2]
+                    .value_ref::<ParsedList>()
+                    .iter()
+                    .map(|(_, c)| {
+                        let mut c = c.value_ref::<ParsedList>().iter().map(|(_, v)| v);
+                        let name = c.next().unwrap().value_ref::<Input>().clone();
+                        let typ = *c.next().unwrap().value_ref::<ParsedIndex>();
+                        (name, typ)
+                    })
+                    .collect();
+                let b = *args[3].value_ref::<ParsedIndex>();
+                ParsedPrismExpr::Data(name, typ, constructors, b)
+            }
+            "Import" => {
+                let m = *args[0].value_ref::<ParsedIndex>();
+                let b = *args[1].value_ref::<ParsedIndex>();
+                ParsedPrismExpr::Import(m, None, b)
+            }
+            "ImportNames" => {
+                let m = *args[0].value_ref::<ParsedIndex>();
+                let names = args[1]
+                    .value_ref::<ParsedList>()
+                    .iter()
+                    .map(|(_, n)| *n.value_ref::<ParsedIndex>())
+                    .collect();
+                let b = *args[2].value_ref::<ParsedIndex>();
+                ParsedPrismExpr::Import(m, Some(names), b)
+            }
+            "Paramatricity" => {
+                let x = *args[0].value_ref::<ParsedIndex>();
+                ParsedPrismExpr::Paramatricity(x)
+            }
             "GrammarValue" => {
-                assert_eq!(args.len(), 1);
                 let grammar = args[0].value_cloned();
                 ParsedPrismExpr::GrammarValue(grammar)
             }
-            "GrammarType" => {
-                assert_eq!(args.len(), 0);
-                ParsedPrismExpr::GrammarType
-            }
+            "GrammarType" => ParsedPrismExpr::GrammarType,
             "EnvCapture" => {
                 assert_eq!(args.len(), 2);
                 let value = args[0].value_ref::<EnvWrapper>();
@@ -140,22 +372,31 @@ impl Parsable<ParserPrismEnv<'_>> for ParsedIndex {
                 }
             }
             "Include" => {
-                assert_eq!(args.len(), 1);
                 let parsed = &args[0];
                 let name = parsed.value_ref::<Input>().clone();
 
-                let current_file = span.start_pos().file();
+                // Values are also constructed without a span while determining the context of grammar adaptations,
+                // the include is only resolved once it is known which file it is in
+                if span == Span::dummy() {
+                    return env.store_from_source(ParsedPrismExpr::Free, span);
+                }
+
+                match env.db.process_include(span, &name.as_str(input), false) {
+                    None => ParsedPrismExpr::Free,
+                    Some(processed_file) => ParsedPrismExpr::Include(name, processed_file.core),
+                }
+            }
+            "Module" => {
+                let name = args[0].value_ref::<Input>().clone();
 
-                let mut path = env.db.input.inner().get_path(current_file).to_path_buf();
-                assert!(path.pop());
-                path.push(format!("{}.pr", name.as_str(input)));
+                // Like an include, the module is only resolved once it is known which file it is in
+                if span == Span::dummy() {
+                    return env.store_from_source(ParsedPrismExpr::Free, span);
+                }
 
-                match env.db.load_file(path) {
+                match env.db.process_include(span, &name.as_str(input), true) {
                     None => ParsedPrismExpr::Free,
-                    Some(next_file) => {
-                        let processed_file = env.db.process_file(next_file);
-                        ParsedPrismExpr::Include(name, processed_file.core)
-                    }
+                    Some(processed_file) => ParsedPrismExpr::Module(name, processed_file.exports),
                 }
             }
             _ => unreachable!(),
@@ -170,49 +411,86 @@ impl Parsable<ParserPrismEnv<'_>> for ParsedIndex {
         arg_placeholders: &[ParsedPlaceholder],
         _env: &mut ParserPrismEnv<'_>,
     ) -> impl Iterator<Item = Option<Self::EvalCtx>> {
+        // The arguments of an invalid value are not evaluated, it is reported by `from_construct`
+        let count = arg_placeholders.len();
+        if !expr_constructor_args(constructor).is_some_and(|s| s.iter().any(|s| s.len() == count)) {
+            return vec![None; count].into_iter();
+        }
         match constructor {
             "Type" => {
-                assert_eq!(arg_placeholders.len(), 0);
-                vec![]
+                vec![Some(parent_ctx.clone()); arg_placeholders.len()]
             }
-            "Name" => {
-                assert_eq!(arg_placeholders.len(), 1);
+            "LevelLiteral" | "NatLiteral" | "IntLiteral" | "StringLiteral" => {
+                vec![None]
+            }
+            "Name" | "Hole" => {
                 vec![None]
             }
             "Let" => {
-                assert_eq!(arg_placeholders.len(), 3);
                 vec![
                     None,
                     Some(parent_ctx.clone()),
-                    Some(parent_ctx.insert(arg_placeholders[0], Some(arg_placeholders[1]))),
+                    Some(parent_ctx.insert(
+                        arg_placeholders[0],
+                        PrismEvalCtxEntry::Let(Some(arg_placeholders[1])),
+                    )),
+                ]
+            }
+            "Data" => {
+                vec![
+                    None,
+                    Some(parent_ctx.clone()),
+                    None,
+                    Some(parent_ctx.insert(
+                        arg_placeholders[0],
+                        PrismEvalCtxEntry::Data(arg_placeholders[2]),
+                    )),
+                ]
+            }
+            "Import" => {
+                vec![
+                    None,
+                    Some(parent_ctx.insert(arg_placeholders[0], PrismEvalCtxEntry::Import(None))),
                 ]
             }
-            "FnType" => {
-                assert_eq!(arg_placeholders.len(), 3);
+            "ImportNames" => {
+                vec![
+                    None,
+                    None,
+                    Some(parent_ctx.insert(
+                        arg_placeholders[0],
+                        PrismEvalCtxEntry::Import(Some(arg_placeholders[1])),
+                    )),
+                ]
+            }
+            "FnType" | "ImplicitFnType" => {
                 vec![None, Some(parent_ctx.clone()), Some(parent_ctx.clone())]
             }
-            "FnConstruct" => {
-                assert_eq!(arg_placeholders.len(), 2);
+            "FnConstruct" | "ImplicitFnConstruct" => {
                 vec![None, Some(parent_ctx.clone())]
             }
+            "PairType" => {
+                vec![None, Some(parent_ctx.clone()), Some(parent_ctx.clone())]
+            }
+            "PairConstruct" => {
+                vec![Some(parent_ctx.clone()), Some(parent_ctx.clone())]
+            }
             "FnDestruct" => {
-                assert_eq!(arg_placeholders.len(), 2);
                 vec![Some(parent_ctx.clone()), Some(parent_ctx.clone())]
             }
             "TypeAssert" => {
-                assert_eq!(arg_placeholders.len(), 2);
                 vec![Some(parent_ctx.clone()), Some(parent_ctx.clone())]
             }
+            "Paramatricity" => {
+                vec![Some(parent_ctx.clone())]
+            }
             "GrammarValue" => {
-                assert_eq!(arg_placeholders.len(), 1);
                 vec![None]
             }
             "GrammarType" => {
-                assert_eq!(arg_placeholders.len(), 0);
                 vec![]
             }
-            "Include" => {
-                assert_eq!(arg_placeholders.len(), 1);
+            "Include" | "Module" => {
                 vec![None]
             }
             _ => unreachable!(),
@@ -232,46 +510,75 @@ impl Parsable<ParserPrismEnv<'_>> for ParsedIndex {
         env.db.diags.truncate(error_count);
 
         // Get original grammar function
-        let original_e =
-            env.parsed_to_checked_with_env(**self, &named_env, &mut Default::default());
+        let original_e = env.parsed_to_checked_with_env(**self, &named_env);
         let origin = env.db.checked_origins[original_e.0];
 
-        // Evaluate this to the grammar function
-        let (grammar_fn_value, grammar_fn_env) = env.db.beta_reduce_head(original_e, &db_env);
-
-        // Create expression that takes first element from this function
-        let e = env
-            .db
-            .store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
-        let mut e = env.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
-        for _ in 0..grammar_fn_env.len() {
-            e = env.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
-        }
-        let free_returntype = env.db.store_checked(CorePrismExpr::Free, origin);
-        let grammar_fn_value = env.db.store_checked(
-            CorePrismExpr::FnDestruct(grammar_fn_value, free_returntype),
-            origin,
-        );
-        let e = env
+        // Evaluate this to the grammar function, which is of the form `\\T. \\f. ...`
+        let (grammar_fn_value, grammar_fn_env) = env
             .db
-            .store_checked(CorePrismExpr::FnDestruct(grammar_fn_value, e), origin);
-
-        // Evaluate this further
-        let (reduced_value, _reduced_env) = env.db.beta_reduce_head(e, &db_env);
-
-        let CorePrismExpr::GrammarValue(grammar) = &env.db.checked_values[reduced_value.0] else {
-            panic!(
-                "Tried to reduce expression which was not a grammar: {} / {} / {}",
-                env.parse_index_to_string(**self),
-                env.db.index_to_string(e),
-                env.db.index_to_string(reduced_value)
-            )
+            .with_fuel(|db| db.beta_reduce_head(original_e, &db_env));
+        let reduced_value = match env.db.checked_values[*grammar_fn_value] {
+            CorePrismExpr::FnConstruct(b)
+                if matches!(env.db.checked_values[*b], CorePrismExpr::FnConstruct(_)) =>
+            {
+                // Create expression that takes first element from this function
+                let e = env
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let mut e = env.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
+                for _ in 0..grammar_fn_env.len() {
+                    e = env.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
+                }
+                let free_returntype = env.db.store_checked(CorePrismExpr::Free, origin);
+                let grammar_fn_value = env.db.store_checked(
+                    CorePrismExpr::FnDestruct(grammar_fn_value, free_returntype),
+                    origin,
+                );
+                let e = env
+                    .db
+                    .store_checked(CorePrismExpr::FnDestruct(grammar_fn_value, e), origin);
+
+                // Evaluate this further
+                Some(env.db.with_fuel(|db| db.beta_reduce_head(e, &db_env)).0)
+            }
+            _ => None,
         };
 
+        // Point at the value that was adapted, rather than at the name of the grammar
+        let span = match env.db.checked_origins[*grammar_fn_value] {
+            ValueOrigin::SourceCode(span) => span,
+            _ => env.db.parsed_spans[***self],
+        };
+
+        let Some(CorePrismExpr::GrammarValue(grammar)) =
+            reduced_value.map(|v| &env.db.checked_values[*v])
+        else {
+            // Continue parsing with the current grammar
+            if env.reported_spans.insert(span) {
+                env.db.push_error(NotAGrammar { span });
+            }
+            return Arc::new(GrammarFile {
+                rules: Arc::new([]),
+            });
+        };
+        let grammar = grammar.clone();
+
+        // Adapting to a grammar that constructs invalid values would fail while parsing
+        let error_count = env.db.diags.len();
+        if !env.check_grammar(&grammar, span) {
+            if !env.reported_spans.insert(span) {
+                env.db.diags.truncate(error_count);
+            }
+            return Arc::new(GrammarFile {
+                rules: Arc::new([]),
+            });
+        }
+
         // Insert the scope into the grammar, so we can find the scope again later in `reduce_expr`
-        grammar.map_actions(&|e| {
-            Arc::new(EnvWrapper(e.clone(), eval_ctx.len(), grammar.clone())).to_parsed()
-        })
+        // A data declaration is a single entry in the `eval_ctx`, so take the length of the actual env
+        let env_len = db_env.len();
+        grammar
+            .map_actions(&|e| Arc::new(EnvWrapper(e.clone(), env_len, grammar.clone())).to_parsed())
     }
 }
 
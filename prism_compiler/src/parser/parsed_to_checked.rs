@@ -1,125 +1,161 @@
-use crate::lang::{CoreIndex, CorePrismExpr, ValueOrigin};
-use crate::parser::named_env::{NamedEnv, NamesEntry, NamesEnv};
+use crate::lang::{CoreIndex, CorePrismExpr, Hole, ValueOrigin};
+use crate::parser::named_env::{NamedEnv, NamesEntry, NamesEnv, grammar_id};
 use crate::parser::{ParsedIndex, ParsedPrismExpr, ParserPrismEnv};
+use prism_diag::sugg::{Applicability, Suggestion};
 use prism_diag_derive::Diagnostic;
+use prism_input::input::Input;
 use prism_input::span::Span;
-use prism_parser::grammar::grammar_file::GrammarFile;
-use std::collections::HashMap;
+
+/// A name is used that is not defined in its scope.
+///
+/// Names are defined by `let`, `data`, `import` and by the parameters of functions, and are in scope
+/// of the code after them. For example, `x` is only in scope of the body of the function:
+///
+/// ```prism
+/// let f = (x: Type) => x;
+/// x
+/// ```
+///
+/// If a name that is in scope is similar to the name that is used, it is suggested.
+#[derive(Diagnostic)]
+#[diag(title = "Undefined name within this scope.")]
+pub(crate) struct UnknownName {
+    #[sugg]
+    span: Span,
+    #[fix]
+    closest: Option<Suggestion>,
+}
 
 impl<'a> ParserPrismEnv<'a> {
-    pub fn parsed_to_checked(&mut self, i: ParsedIndex) -> CoreIndex {
-        self.parsed_to_checked_with_env(i, &NamedEnv::default(), &mut Default::default())
+    /// Converts `i` in the scope `env`, like `parsed_to_checked_with_env`.
+    /// Also returns the converted value of `scope_of`, which is part of `i`, and the names in scope of it.
+    pub(crate) fn parsed_to_checked_with_scope_of(
+        &mut self,
+        i: ParsedIndex,
+        env: &NamedEnv,
+        scope_of: ParsedIndex,
+    ) -> (CoreIndex, Option<(CoreIndex, NamedEnv)>) {
+        self.scope_of = Some((scope_of, None));
+        let core = self.parsed_to_checked_with_env(i, env);
+        let (_, scope) = self.scope_of.take().unwrap();
+        (core, scope)
     }
 
     pub(super) fn parsed_to_checked_with_env(
         &mut self,
         i: ParsedIndex,
         env: &NamedEnv,
-        jump_labels: &mut HashMap<*const GrammarFile, NamesEnv>,
     ) -> CoreIndex {
-        let origin = ValueOrigin::SourceCode(self.parsed_spans[*i]);
-        let e = match &self.parsed_values[*i] {
+        let core = self.parsed_to_checked_inner(i, env);
+        if let Some((scope_of, scope)) = &mut self.scope_of
+            && *scope_of == i
+        {
+            *scope = Some((core, env.clone()));
+        }
+        core
+    }
+
+    fn parsed_to_checked_inner(&mut self, i: ParsedIndex, env: &NamedEnv) -> CoreIndex {
+        let origin = ValueOrigin::SourceCode(self.db.parsed_spans[*i]);
+        let e = match &self.db.parsed_values[*i] {
             ParsedPrismExpr::Free => CorePrismExpr::Free,
-            ParsedPrismExpr::Type => CorePrismExpr::Type,
+            // Without an explicit level, the level of the universe is inferred
+            &ParsedPrismExpr::Type(None) => {
+                CorePrismExpr::Type(self.db.store_checked(CorePrismExpr::Free, origin))
+            }
+            &ParsedPrismExpr::Type(Some(l)) => {
+                CorePrismExpr::Type(self.parsed_to_checked_with_env(l, env))
+            }
+            &ParsedPrismExpr::LevelLiteral(n) => CorePrismExpr::LevelConst(n),
+            ParsedPrismExpr::Literal(l) => CorePrismExpr::Literal(l.clone()),
+            ParsedPrismExpr::Hole(n) => {
+                let hole = Hole {
+                    name: n.as_str(&self.db.input).to_string(),
+                    names: env.names_in_scope(),
+                };
+                let i = self.db.store_checked(CorePrismExpr::Free, origin);
+                self.db.holes.insert(i, hole);
+                return i;
+            }
+            &ParsedPrismExpr::Paramatricity(x) => {
+                let x = self.parsed_to_checked_with_env(x, env);
+                self.db.scope_names.insert(x, env.names_in_scope());
+                CorePrismExpr::Paramatricity(x)
+            }
             &ParsedPrismExpr::Let(ref n, v, b) => {
                 let n = n.as_str(&self.db.input);
-                let new_env = env.insert_name(&n, &self.db.input);
+                let new_env = env.insert_name_with_implicits(
+                    &n,
+                    self.implicit_args_of_value(v),
+                    &self.db.input,
+                );
                 CorePrismExpr::Let(
-                    self.parsed_to_checked_with_env(v, env, jump_labels),
-                    self.parsed_to_checked_with_env(b, &new_env, jump_labels),
+                    self.parsed_to_checked_with_env(v, env),
+                    self.parsed_to_checked_with_env(b, &new_env),
                 )
             }
-            &ParsedPrismExpr::FnType(ref n, a, b) => {
+            &ParsedPrismExpr::FnType(ref n, a, b)
+            | &ParsedPrismExpr::ImplicitFnType(ref n, a, b) => {
                 let n = n.as_str(&self.db.input);
-                let new_env = env.insert_name(&n, &self.db.input);
+                let new_env = env.insert_name_with_implicits(
+                    &n,
+                    self.implicit_args_of_type(a),
+                    &self.db.input,
+                );
                 CorePrismExpr::FnType(
-                    self.parsed_to_checked_with_env(a, env, jump_labels),
-                    self.parsed_to_checked_with_env(b, &new_env, jump_labels),
+                    self.parsed_to_checked_with_env(a, env),
+                    self.parsed_to_checked_with_env(b, &new_env),
                 )
             }
-            &ParsedPrismExpr::FnConstruct(ref n, b) => {
+            &ParsedPrismExpr::PairType(ref n, a, b) => {
                 let n = n.as_str(&self.db.input);
-                CorePrismExpr::FnConstruct(self.parsed_to_checked_with_env(
-                    b,
-                    &env.insert_name(&n, &self.db.input),
-                    jump_labels,
-                ))
+                let new_env = env.insert_name_with_implicits(
+                    &n,
+                    self.implicit_args_of_type(a),
+                    &self.db.input,
+                );
+                CorePrismExpr::PairType(
+                    self.parsed_to_checked_with_env(a, env),
+                    self.parsed_to_checked_with_env(b, &new_env),
+                )
             }
-            &ParsedPrismExpr::FnDestruct(f, a) => CorePrismExpr::FnDestruct(
-                self.parsed_to_checked_with_env(f, env, jump_labels),
-                self.parsed_to_checked_with_env(a, env, jump_labels),
-            ),
-            &ParsedPrismExpr::TypeAssert(v, t) => CorePrismExpr::TypeAssert(
-                self.parsed_to_checked_with_env(v, env, jump_labels),
-                self.parsed_to_checked_with_env(t, env, jump_labels),
+            &ParsedPrismExpr::PairConstruct(a, b) => CorePrismExpr::PairConstruct(
+                self.parsed_to_checked_with_env(a, env),
+                self.parsed_to_checked_with_env(b, env),
             ),
-            ParsedPrismExpr::Name(name) => {
-                assert_ne!(name.as_str(&self.db.input), "_");
-
-                match env.resolve_name_use(&name.as_str(&self.db.input)) {
-                    Some(NamesEntry::FromEnv(prev_env_len)) => {
-                        CorePrismExpr::DeBruijnIndex(env.len() - prev_env_len - 1)
-                    }
-                    Some(&NamesEntry::FromGrammarEnv {
-                        grammar_env_len,
-                        adapt_env_len,
-                        prev_env_len,
-                    }) => {
-                        let adapt_env_len = adapt_env_len - 1;
-                        let grammar_expr = self.db.store_checked(
-                            CorePrismExpr::DeBruijnIndex(env.len() - adapt_env_len - 1),
-                            origin,
-                        );
-
-                        // println!("{adapt_env_len} {prev_env_len}");
-                        let idx = prev_env_len + 1;
-                        let e = self
-                            .db
-                            .store_checked(CorePrismExpr::DeBruijnIndex(idx), origin);
-                        let mut e = self.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
-                        for _ in 0..grammar_env_len {
-                            e = self.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
-                        }
-                        let free_return_type = self.db.store_checked(CorePrismExpr::Free, origin);
-                        let grammar_expr = self.db.store_checked(
-                            CorePrismExpr::FnDestruct(grammar_expr, free_return_type),
-                            origin,
-                        );
-                        CorePrismExpr::FnDestruct(grammar_expr, e)
-                    }
-                    Some(NamesEntry::FromParsed(parsed, old_names)) => {
-                        if let Some(&expr) = parsed.try_value_ref::<ParsedIndex>() {
-                            return self.parsed_to_checked_with_env(
-                                expr,
-                                &env.shift_back(old_names, &self.db.input),
-                                jump_labels,
-                            );
-                        } else {
-                            unreachable!(
-                                "Found name `{}` referring to {parsed:?}",
-                                name.as_str(&self.db.input),
-                            );
-                        }
-                    }
-                    None => {
-                        #[derive(Diagnostic)]
-                        #[diag(title = "Undefined name within this scope.")]
-                        struct UnknownName {
-                            #[sugg]
-                            span: Span,
-                        }
-
-                        self.db.push_error(UnknownName {
-                            span: self.parsed_spans[*i],
-                        });
-                        CorePrismExpr::Free
-                    }
+            &ParsedPrismExpr::FnConstruct(ref n, b)
+            | &ParsedPrismExpr::ImplicitFnConstruct(ref n, b) => {
+                let implicit_args = self.implicit_args_of_binder(n, b);
+                let n = n.as_str(&self.db.input);
+                let env = env.insert_name_with_implicits(&n, implicit_args, &self.db.input);
+                if n == "_" {
+                    return self.unnamed_binder_to_checked(i, b, &env);
                 }
+                CorePrismExpr::FnConstruct(self.parsed_to_checked_with_env(b, &env))
             }
+            &ParsedPrismExpr::FnDestruct(f, a) => CorePrismExpr::FnDestruct(
+                self.parsed_to_checked_with_env(f, env),
+                self.parsed_to_checked_with_env(a, env),
+            ),
+            &ParsedPrismExpr::TypeAssert(v, t) => {
+                // A name that is asserted to have implicit arguments is used as is
+                let v = if matches!(self.db.parsed_values[*v], ParsedPrismExpr::Name(_))
+                    && self.implicit_args_of_type(t) > 0
+                {
+                    self.name_to_checked(v, env, false)
+                } else {
+                    self.parsed_to_checked_with_env(v, env)
+                };
+                self.db.scope_names.insert(v, env.names_in_scope());
+                CorePrismExpr::TypeAssert(v, self.parsed_to_checked_with_env(t, env))
+            }
+            ParsedPrismExpr::Data(..) => return self.data_to_checked(i, env),
+            ParsedPrismExpr::Import(..) => return self.import_to_checked(i, env),
+            ParsedPrismExpr::Name(_) => return self.name_to_checked(i, env, true),
             ParsedPrismExpr::GrammarType => CorePrismExpr::GrammarType,
             ParsedPrismExpr::GrammarValue(grammar) => {
-                env.insert_shift_label(grammar, jump_labels);
                 let grammar = grammar.clone();
+                env.insert_shift_label(&grammar, &mut self.db.grammar_scopes);
 
                 // Create \T: Type. \f. ((f [env0] [env1] grammar ...): T)
                 let mut e = self
@@ -155,11 +191,11 @@ impl<'a> ParserPrismEnv<'a> {
                 adapt_env_len,
                 grammar,
             } => {
-                let old_names = &jump_labels[&(grammar.as_ref() as *const GrammarFile)];
+                let old_names = &self.db.grammar_scopes[&grammar_id(grammar)];
 
                 let mut names = NamesEnv::default();
                 for (name, entry) in old_names.iter().collect::<Vec<_>>().into_iter().rev() {
-                    let NamesEntry::FromEnv(i) = entry else {
+                    let &NamesEntry::FromEnv(i, implicit_args) = entry else {
                         //TODO this is probably possible to hit but niche
                         unreachable!()
                     };
@@ -169,7 +205,8 @@ impl<'a> ParserPrismEnv<'a> {
                         NamesEntry::FromGrammarEnv {
                             grammar_env_len: old_names.len(),
                             adapt_env_len: *adapt_env_len,
-                            prev_env_len: *i,
+                            prev_env_len: i,
+                            implicit_args,
                         },
                     );
                 }
@@ -188,9 +225,186 @@ impl<'a> ParserPrismEnv<'a> {
                     hygienic_names: Default::default(),
                 };
 
-                return self.parsed_to_checked_with_env(*expr, &env, jump_labels);
+                return self.parsed_to_checked_with_env(*expr, &env);
+            }
+            // The included file does not refer to the variables in scope here
+            &ParsedPrismExpr::Include(_, v) => CorePrismExpr::Shift(v, env.len()),
+            ParsedPrismExpr::Module(..) => {
+                unreachable!("Modules only occur in `import` statements")
+            }
+        };
+        self.db.store_checked(e, origin)
+    }
+
+    /// Applies `e` to `implicit_args` free variables, which are solved during type checking
+    fn insert_implicit_args(
+        &mut self,
+        mut e: CorePrismExpr,
+        implicit_args: usize,
+        origin: ValueOrigin,
+    ) -> CorePrismExpr {
+        for _ in 0..implicit_args {
+            let f = self.db.store_checked(e, origin);
+            let arg = self.db.store_checked(CorePrismExpr::Free, origin);
+            e = CorePrismExpr::FnDestruct(f, arg);
+        }
+        e
+    }
+
+    /// Returns the number of leading implicit arguments of the value `i`, which is known from its syntax
+    pub(super) fn implicit_args_of_value(&self, i: ParsedIndex) -> usize {
+        match self.db.parsed_values[*i] {
+            ParsedPrismExpr::ImplicitFnConstruct(_, b) => 1 + self.implicit_args_of_value(b),
+            ParsedPrismExpr::Let(_, _, b) => self.implicit_args_of_value(b),
+            ParsedPrismExpr::TypeAssert(_, t) => self.implicit_args_of_type(t),
+            _ => 0,
+        }
+    }
+
+    /// Returns the number of leading implicit arguments of values of the type `i`
+    pub(super) fn implicit_args_of_type(&self, i: ParsedIndex) -> usize {
+        match self.db.parsed_values[*i] {
+            ParsedPrismExpr::ImplicitFnType(_, _, b) => 1 + self.implicit_args_of_type(b),
+            _ => 0,
+        }
+    }
+
+    /// Returns the number of leading implicit arguments of the argument `n` of a function with body `b`.
+    /// This is only known if the argument has a type annotation, which is of the form `let _ = n: t; ...`.
+    fn implicit_args_of_binder(&self, n: &Input, b: ParsedIndex) -> usize {
+        let ParsedPrismExpr::Let(_, v, _) = self.db.parsed_values[*b] else {
+            return 0;
+        };
+        let ParsedPrismExpr::TypeAssert(x, t) = self.db.parsed_values[*v] else {
+            return 0;
+        };
+        match &self.db.parsed_values[*x] {
+            ParsedPrismExpr::Name(m) if m.as_str(&self.db.input) == n.as_str(&self.db.input) => {
+                self.implicit_args_of_type(t)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Converts the function `i` with an unnamed argument and body `b`, `env` contains the argument.
+    /// The type annotation `(_: t)` is of the form `let _ = _: t; ...`, where `_` refers to the argument.
+    fn unnamed_binder_to_checked(
+        &mut self,
+        i: ParsedIndex,
+        b: ParsedIndex,
+        env: &NamedEnv,
+    ) -> CoreIndex {
+        let origin = ValueOrigin::SourceCode(self.db.parsed_spans[*i]);
+        let b = if let ParsedPrismExpr::Let(_, v, r) = self.db.parsed_values[*b]
+            && let ParsedPrismExpr::TypeAssert(x, t) = self.db.parsed_values[*v]
+            && let ParsedPrismExpr::Free = self.db.parsed_values[*x]
+        {
+            let x = self.db.store_checked(
+                CorePrismExpr::DeBruijnIndex(0),
+                ValueOrigin::SourceCode(self.db.parsed_spans[*x]),
+            );
+            let t = self.parsed_to_checked_with_env(t, env);
+            let v = self.db.store_checked(
+                CorePrismExpr::TypeAssert(x, t),
+                ValueOrigin::SourceCode(self.db.parsed_spans[*v]),
+            );
+            let r = self.parsed_to_checked_with_env(r, &env.insert_name("_", &self.db.input));
+            self.db.store_checked(
+                CorePrismExpr::Let(v, r),
+                ValueOrigin::SourceCode(self.db.parsed_spans[*b]),
+            )
+        } else {
+            self.parsed_to_checked_with_env(b, env)
+        };
+        self.db.store_checked(CorePrismExpr::FnConstruct(b), origin)
+    }
+
+    /// Converts the name `i`.
+    /// If `insert_implicits` is set, the implicit arguments of the variable it refers to are inserted.
+    fn name_to_checked(
+        &mut self,
+        i: ParsedIndex,
+        env: &NamedEnv,
+        insert_implicits: bool,
+    ) -> CoreIndex {
+        let origin = ValueOrigin::SourceCode(self.db.parsed_spans[*i]);
+        let ParsedPrismExpr::Name(name) = self.db.parsed_values[*i].clone() else {
+            unreachable!()
+        };
+        assert_ne!(name.as_str(&self.db.input), "_");
+
+        let e = match env.resolve_name_use(&name.as_str(&self.db.input)) {
+            Some(&NamesEntry::FromEnv(prev_env_len, implicit_args)) => self.insert_implicit_args(
+                CorePrismExpr::DeBruijnIndex(env.len() - prev_env_len - 1),
+                if insert_implicits { implicit_args } else { 0 },
+                origin,
+            ),
+            Some(&NamesEntry::FromGrammarEnv {
+                grammar_env_len,
+                adapt_env_len,
+                prev_env_len,
+                implicit_args,
+            }) => {
+                let adapt_env_len = adapt_env_len - 1;
+                let grammar_expr = self.db.store_checked(
+                    CorePrismExpr::DeBruijnIndex(env.len() - adapt_env_len - 1),
+                    origin,
+                );
+
+                // println!("{adapt_env_len} {prev_env_len}");
+                let idx = prev_env_len + 1;
+                let e = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(idx), origin);
+                let mut e = self.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
+                for _ in 0..grammar_env_len {
+                    e = self.db.store_checked(CorePrismExpr::FnConstruct(e), origin);
+                }
+                let free_return_type = self.db.store_checked(CorePrismExpr::Free, origin);
+                let grammar_expr = self.db.store_checked(
+                    CorePrismExpr::FnDestruct(grammar_expr, free_return_type),
+                    origin,
+                );
+                self.insert_implicit_args(
+                    CorePrismExpr::FnDestruct(grammar_expr, e),
+                    if insert_implicits { implicit_args } else { 0 },
+                    origin,
+                )
+            }
+            Some(NamesEntry::FromParsed(parsed, old_names)) => {
+                if let Some(&expr) = parsed.try_value_ref::<ParsedIndex>() {
+                    return self.parsed_to_checked_with_env(
+                        expr,
+                        &env.shift_back(old_names, &self.db.input),
+                    );
+                } else {
+                    unreachable!(
+                        "Found name `{}` referring to {parsed:?}",
+                        name.as_str(&self.db.input),
+                    );
+                }
+            }
+            None => {
+                let name = name.as_str(&self.db.input);
+                if let Some(e) = self.db.builtin(&name, origin) {
+                    e
+                } else {
+                    let span = self.db.parsed_spans[*i];
+                    // A similar name may not be the intended one, so it is never applied by `--fix`
+                    let closest = env.closest_name(&name).map(|(closest, unique)| Suggestion {
+                        message: if unique {
+                            format!("A similar name is in scope: `{closest}`")
+                        } else {
+                            format!("One of the similar names in scope: `{closest}`")
+                        },
+                        span,
+                        replacement: closest.to_string(),
+                        applicability: Applicability::MaybeIncorrect,
+                    });
+                    self.db.push_error(UnknownName { span, closest });
+                    CorePrismExpr::Free
+                }
             }
-            ParsedPrismExpr::Include(_, v) => return *v,
         };
         self.db.store_checked(e, origin)
     }
@@ -0,0 +1,265 @@
+use crate::lang::PrismDb;
+use crate::lang::statements::{ProcessedRest, StatementsScope};
+use crate::parser::ParsedIndex;
+use prism_input::input_table::InputTableIndex;
+use prism_input::span::Span;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// An interactive session, in which statements extend the scope of the inputs after them
+pub struct Repl {
+    db: PrismDb,
+    /// The file the inputs are processed in, after the statements entered so far.
+    /// Includes and imports are relative to the directory of the last loaded file, or to the current directory.
+    file: InputTableIndex,
+    /// The statements entered so far, each on its own line
+    statements: String,
+    /// The scope of `statements`, inputs are processed in it without processing the statements again
+    scope: StatementsScope,
+}
+
+impl Repl {
+    pub fn new(mut db: PrismDb) -> Self {
+        let dir = std::env::current_dir().unwrap_or_default();
+        let file = db.load_input(String::new(), dir.join("<repl>"));
+        Self {
+            db,
+            file,
+            statements: String::new(),
+            scope: StatementsScope::default(),
+        }
+    }
+
+    /// Reads inputs from `input` until it ends
+    pub fn run(&mut self, input: impl BufRead) -> std::io::Result<()> {
+        print!("> ");
+        std::io::stdout().flush()?;
+        for line in input.lines() {
+            if let Some(output) = self.handle_input(&line?) {
+                println!("{output}");
+            }
+            print!("> ");
+            std::io::stdout().flush()?;
+        }
+        println!();
+        Ok(())
+    }
+
+    /// Handles a single input, which is either a command such as `:type e`, a statement ending in `;` or an expression.
+    /// Returns the output to show, the reported diagnostics are printed.
+    pub fn handle_input(&mut self, input: &str) -> Option<String> {
+        let input = input.trim();
+        let output = if let Some(command) = input.strip_prefix(':') {
+            let (command, arg) = command
+                .split_once(char::is_whitespace)
+                .unwrap_or((command, ""));
+            match command {
+                "type" => self.show_expr(arg, false, true),
+                "eval" => self.show_expr(arg, true, false),
+                "load" => {
+                    self.load(arg.trim());
+                    None
+                }
+                _ => {
+                    eprintln!(
+                        "Unknown command `:{command}`, expected one of `:type`, `:eval` or `:load`"
+                    );
+                    None
+                }
+            }
+        } else if input.ends_with(';') {
+            self.add_statements(input);
+            None
+        } else if !input.is_empty() {
+            self.show_expr(input, true, true)
+        } else {
+            None
+        };
+
+        // The values of previous inputs are no longer needed
+        self.db.collect_garbage_keeping(&mut self.scope);
+        output
+    }
+
+    /// Shows the normal form and/or the type of `expr`
+    fn show_expr(&mut self, expr: &str, eval: bool, typ: bool) -> Option<String> {
+        let processed = self.process(expr)?;
+        let env = self.scope.env().clone();
+        let eval = eval.then(|| self.db.index_to_br_string(processed.core, &env));
+        let typ = typ.then(|| self.db.index_to_br_string(processed.typ, &env));
+        let has_errors = self.db.has_errors();
+        self.db.eprint_errors();
+        if has_errors {
+            return None;
+        }
+        match (eval, typ) {
+            (Some(eval), Some(typ)) => Some(format!("{eval} : {typ}")),
+            (Some(s), None) | (None, Some(s)) => Some(s),
+            (None, None) => None,
+        }
+    }
+
+    /// Adds `statements` to the session if they are valid, returns whether they were added
+    fn add_statements(&mut self, statements: &str) -> bool {
+        // A file always ends in an expression
+        let Some(scope) = self
+            .process(&format!("{statements}\nType"))
+            .and_then(|processed| processed.scope)
+        else {
+            return false;
+        };
+        self.statements.push_str(statements);
+        self.statements.push('\n');
+        self.scope = scope;
+        true
+    }
+
+    /// Adds the statements of the file at `path` to the session, ignoring the expression it ends in
+    fn load(&mut self, path: &str) {
+        let path = PathBuf::from(path);
+        // The file is read again, since it may have changed since it was last loaded
+        let Ok(source) = std::fs::read_to_string(&path) else {
+            // Report why the file can't be read
+            self.db.load_file(path);
+            self.db.eprint_errors();
+            return;
+        };
+        let file = self.db.load_input(source.clone(), path.clone());
+        self.db.update_file(file, source);
+
+        let processed = self.db.process_file(file);
+        let has_errors = self.db.has_errors();
+        self.db.eprint_errors();
+        if has_errors {
+            return;
+        }
+        let end = self.statements_end(processed.parsed);
+        let statements = self.db.input.inner().get_str(file)[..end]
+            .trim()
+            .to_string();
+
+        // Includes and imports in the loaded statements are relative to the loaded file,
+        // so the session continues next to it
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let previous_path = self.db.input.inner().get_path(self.file).to_path_buf();
+        self.db
+            .input
+            .inner_mut()
+            .update_path(self.file, dir.join("<repl>"));
+        if !self.add_statements(&statements) {
+            self.db
+                .input
+                .inner_mut()
+                .update_path(self.file, previous_path);
+        }
+    }
+
+    /// Returns the offset of the expression that the statements of `parsed` end in
+    fn statements_end(&self, mut parsed: ParsedIndex) -> usize {
+        while let Some(body) = self.db.statement_body(parsed) {
+            parsed = body;
+        }
+        self.db.parsed_spans[*parsed].start_pos().idx_in_file()
+    }
+
+    /// Processes `expr` in the scope of the statements of the session, the statements are not processed again.
+    /// Returns `None` if errors were reported. The reported diagnostics are printed.
+    fn process(&mut self, expr: &str) -> Option<ProcessedRest> {
+        // Replace the previous input, the parse of the statements is reused
+        let (start, len) = {
+            let input = self.db.input.inner();
+            (input.start_of(self.file), input.get_str(self.file).len())
+        };
+        let previous = Span::new(start + self.statements.len(), len - self.statements.len());
+        self.db.edit_file(previous, expr);
+
+        let processed = self.db.process_after(self.file, &self.scope);
+        let has_errors = self.db.has_errors();
+        self.db.eprint_errors();
+        processed.filter(|_| !has_errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Handles `inputs` in a new session, returning the output of each input
+    fn run(inputs: &[&str]) -> Vec<Option<String>> {
+        let mut repl = Repl::new(PrismDb::default());
+        inputs
+            .iter()
+            .map(|input| repl.handle_input(input))
+            .collect()
+    }
+
+    fn output(s: &str) -> Option<String> {
+        Some(s.to_string())
+    }
+
+    #[test]
+    fn statements_accumulate() {
+        let outputs = run(&["let x = 1;", "let y = nat_add x x;", "y"]);
+        assert_eq!(outputs, [None, None, output("2 : Nat")]);
+    }
+
+    #[test]
+    fn invalid_statements_are_not_added() {
+        let outputs = run(&["let x = 1;", "let x = y;", "x"]);
+        assert_eq!(outputs, [None, None, output("1 : Nat")]);
+    }
+
+    #[test]
+    fn type_and_eval() {
+        let outputs = run(&["let x = 1;", ":type nat_add x 1", ":eval nat_add x 1"]);
+        assert_eq!(outputs, [None, output("Nat"), output("2")]);
+    }
+
+    #[test]
+    fn adapt_persists() {
+        let outputs = run(&[
+            r#"adapt grammar { adapt rule expr { adapt group base { 42 <- "%"; } } };"#,
+            "let x = %;",
+            "(%, x)",
+        ]);
+        assert_eq!(outputs[2], output("(42, 42) : Nat * Nat"));
+    }
+
+    #[test]
+    fn load_resolves_imports_next_to_the_file() {
+        // `names.pr` imports `lib.pr`, which is in the same directory
+        let outputs = run(&[
+            "let x = 1;",
+            ":load uitests/modules/names.pr",
+            "nat_add x answer",
+        ]);
+        assert_eq!(outputs, [None, None, output("43 : Nat")]);
+    }
+
+    #[test]
+    fn load_reuses_the_session_file() {
+        let mut repl = Repl::new(PrismDb::default());
+        let file = repl.file;
+        for _ in 0..3 {
+            repl.handle_input(":load uitests/modules/names.pr");
+            repl.handle_input(":load uitests/modules/lib.pr");
+        }
+        assert_eq!(repl.file, file);
+        assert_eq!(repl.handle_input("answer"), output("42 : Nat"));
+    }
+
+    #[test]
+    fn statements_are_not_processed_again() {
+        // Returns the number of checked values that processing `expr` after `count` statements creates
+        let values_created = |count: usize| {
+            let mut repl = Repl::new(PrismDb::default());
+            for k in 0..count {
+                repl.handle_input(&format!("let x{k} = nat_add {k} 1;"));
+            }
+            let before = repl.db.checked_values.len();
+            assert!(repl.process("nat_add x0 1").is_some());
+            repl.db.checked_values.len() - before
+        };
+        assert_eq!(values_created(1), values_created(20));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::lang::data::DataDecl;
+use crate::lang::env::DbEnv;
+use crate::lang::env::EnvEntry::*;
+use crate::lang::{CoreIndex, CorePrismExpr, ValueOrigin};
+use crate::type_check::TypecheckPrismEnv;
+use crate::type_check::errors::{ConstructorUniverse, NonPositiveOccurrence};
+use crate::type_check::level::Subtype;
+use std::collections::HashMap;
+
+impl TypecheckPrismEnv<'_> {
+    /// Type checks a data declaration and the body `b` in its scope. Returns the type of the body.
+    pub(super) fn check_data(&mut self, decl: &DataDecl, b: CoreIndex, env: &DbEnv) -> CoreIndex {
+        let err_count = self.db.diags.len();
+        let tt = self._type_check(decl.typ, env);
+        self.expect_beq_type(tt, env);
+
+        let env_d = env.cons(CSubst(decl.type_node, decl.typ));
+        let level = self.data_level(decl);
+        for c in decl.constructors.iter() {
+            let ct_err_count = self.db.diags.len();
+            let ct = self._type_check(c.typ, &env_d);
+            if self.db.diags.len() == ct_err_count {
+                self.expect_beq_type(ct, &env_d);
+            }
+            // The arguments of the constructors should fit in the universe of the data type
+            if self.db.diags.len() == ct_err_count {
+                let universe = self
+                    .db
+                    .store_checked(CorePrismExpr::Type(level), ValueOrigin::TypeOf(decl.typ));
+                if !self.expect_subtype_internal(
+                    (ct, &env_d, &mut HashMap::new()),
+                    (universe, &env_d, &mut HashMap::new()),
+                    Subtype::Left,
+                    0,
+                ) {
+                    self.db.push_error(ConstructorUniverse {
+                        constructor: c.typ,
+                        constructor_type: ct,
+                        data_type: decl.typ,
+                    });
+                }
+            }
+            self.check_strictly_positive(c.typ);
+        }
+
+        let mut s = env_d;
+        for (k, c) in decl.constructors.iter().enumerate() {
+            // The type of the constructor is valid in the scope of `D`, skip the constructors before it
+            let t = self.db.store_checked(
+                CorePrismExpr::Shift(c.typ, k),
+                self.db.checked_origins[*c.typ],
+            );
+            s = s.cons(CSubst(c.node, t));
+        }
+
+        // The type of the eliminator is generated, so only check it if the declaration itself is valid
+        if self.db.diags.len() == err_count {
+            let et = self._type_check(decl.elim_type, &s);
+            self.expect_beq_type(et, &s);
+        }
+        let s = s.cons(CSubst(decl.elim_node, decl.elim_type));
+
+        self._type_check(b, &s)
+    }
+
+    /// Returns the level of the universe the data type lives in, valid in the scope of the data type
+    fn data_level(&mut self, decl: &DataDecl) -> CoreIndex {
+        let origin = ValueOrigin::TypeOf(decl.typ);
+        let mut typ = decl.typ;
+        for _ in 0..decl.indices {
+            let CorePrismExpr::FnType(_, b) = self.db.checked_values[*typ] else {
+                unreachable!("The type of a data type has a binder for each index")
+            };
+            typ = b;
+        }
+        let CorePrismExpr::Type(mut level) = self.db.checked_values[*typ] else {
+            unreachable!("The type of a data type returns a universe")
+        };
+        // The level is in the scope of the indices, if it depends on them we take the level `ω`
+        for _ in 0..decl.indices {
+            let omega = self.db.store_checked(CorePrismExpr::LevelOmega, origin);
+            level = self
+                .db
+                .store_checked(CorePrismExpr::Let(omega, level), origin);
+        }
+        self.db
+            .store_checked(CorePrismExpr::Shift(level, 1), origin)
+    }
+
+    /// Checks that the data type, which is the variable 0 in scope of the constructor type `typ`,
+    /// only occurs strictly positively in `typ`
+    fn check_strictly_positive(&mut self, mut typ: CoreIndex) {
+        let mut index = 0;
+        while let CorePrismExpr::FnType(a, b) = self.db.checked_values[*typ] {
+            self.check_strictly_positive_arg(a, index);
+            index += 1;
+            typ = b;
+        }
+        self.check_strictly_positive_spine(typ, index);
+    }
+
+    /// A constructor argument may only be of the form `(z: C).. -> D s..`, where `D` does not occur in `C` and `s`
+    fn check_strictly_positive_arg(&mut self, mut arg: CoreIndex, mut index: usize) {
+        while let CorePrismExpr::FnType(a, b) = self.db.checked_values[*arg] {
+            if self.mentions(a, index) {
+                self.db.push_error(NonPositiveOccurrence { occurrence: a });
+            }
+            index += 1;
+            arg = b;
+        }
+        self.check_strictly_positive_spine(arg, index);
+    }
+
+    /// Checks that `i` is either `D s..`, where `D` does not occur in `s`, or does not mention `D` at all
+    fn check_strictly_positive_spine(&mut self, mut i: CoreIndex, index: usize) {
+        while let CorePrismExpr::FnDestruct(f, a) = self.db.checked_values[*i] {
+            if self.mentions(a, index) {
+                self.db.push_error(NonPositiveOccurrence { occurrence: a });
+            }
+            i = f;
+        }
+        if !matches!(self.db.checked_values[*i], CorePrismExpr::DeBruijnIndex(v) if v == index)
+            && self.mentions(i, index)
+        {
+            self.db.push_error(NonPositiveOccurrence { occurrence: i });
+        }
+    }
+
+    /// Returns whether `i` syntactically refers to the variable `index`
+    fn mentions(&self, i: CoreIndex, index: usize) -> bool {
+        match self.db.checked_values[*i] {
+            CorePrismExpr::Free
+            | CorePrismExpr::Level
+            | CorePrismExpr::LevelConst(_)
+            | CorePrismExpr::LevelOmega
+            | CorePrismExpr::GrammarValue(_)
+            | CorePrismExpr::GrammarType
+            | CorePrismExpr::DataType(_)
+            | CorePrismExpr::DataConstruct(_, _)
+            | CorePrismExpr::DataElim(_)
+            | CorePrismExpr::LiteralType(_)
+            | CorePrismExpr::Literal(_)
+            | CorePrismExpr::PrimOp(_) => false,
+            CorePrismExpr::DeBruijnIndex(v) => v == index,
+            CorePrismExpr::Let(v, b)
+            | CorePrismExpr::FnType(v, b)
+            | CorePrismExpr::PairType(v, b) => {
+                self.mentions(v, index) || self.mentions(b, index + 1)
+            }
+            CorePrismExpr::FnConstruct(b) => self.mentions(b, index + 1),
+            CorePrismExpr::Type(l) | CorePrismExpr::LevelSucc(l) => self.mentions(l, index),
+            CorePrismExpr::FnDestruct(a, b)
+            | CorePrismExpr::TypeAssert(a, b)
+            | CorePrismExpr::PairConstruct(a, b)
+            | CorePrismExpr::LevelMax(a, b) => self.mentions(a, index) || self.mentions(b, index),
+            CorePrismExpr::PairFst(p)
+            | CorePrismExpr::PairSnd(p)
+            | CorePrismExpr::Paramatricity(p) => self.mentions(p, index),
+            CorePrismExpr::Shift(v, shift) => index >= shift && self.mentions(v, index - shift),
+            CorePrismExpr::Data(ref decl, b) => {
+                let ctors = decl.constructors.len();
+                self.mentions(decl.typ, index)
+                    || decl
+                        .constructors
+                        .iter()
+                        .any(|c| self.mentions(c.typ, index + 1))
+                    || self.mentions(decl.elim_type, index + 1 + ctors)
+                    || self.mentions(b, index + 2 + ctors)
+            }
+        }
+    }
+}
@@ -17,6 +17,14 @@ impl SuggestionArgument<PrismDb> for CoreIndex {
     }
 }
 
+/// A value was used as a type, but its type is not `Type`.
+///
+/// The type of a function argument, the type in a type assertion and the result of a function type
+/// must all be types. For example, `1` is a value of type `Nat` and not a type:
+///
+/// ```prism
+/// (x: 1) => x
+/// ```
 #[derive(Diagnostic)]
 #[diag(title = "Expected type", env = PrismDb)]
 pub struct ExpectedType {
@@ -24,6 +32,13 @@ pub struct ExpectedType {
     pub index: CoreIndex,
 }
 
+/// A value was applied to an argument, but it is not a function.
+///
+/// For example, `Type` is not a function, so it can't be applied to `Type`:
+///
+/// ```prism
+/// Type Type
+/// ```
 #[derive(Diagnostic)]
 #[diag(title = "Expected function", env = PrismDb)]
 pub struct ExpectedFn {
@@ -31,26 +46,82 @@ pub struct ExpectedFn {
     pub index: CoreIndex,
 }
 
+/// The first or second element of a value was taken, but it is not a pair.
+///
+/// `fst` and `snd` are functions that take a pair, so applying them to a value that is not a pair
+/// is usually reported as an argument of the wrong type instead.
+#[derive(Diagnostic)]
+#[diag(title = "Expected pair", env = PrismDb)]
+pub struct ExpectedPair {
+    #[sugg(label = format!("Expected a pair, found value of type: {}", env.index_to_sm_string(self.index)))]
+    pub index: CoreIndex,
+}
+
+/// A function was applied to an argument of the wrong type.
+///
+/// The type of the argument should be equal to the type of the parameter of the function.
+/// For example, `nat_add` expects two values of type `Nat`, but `"two"` is a `String`:
+///
+/// ```prism
+/// nat_add 1 "two"
+/// ```
 #[derive(Diagnostic)]
 #[diag(title = "Argument type mismatch in function application", env = PrismDb)]
 pub struct ExpectedFnArg {
     #[sugg(label = format!("Found an argument of type: {}", env.index_to_sm_string(self.arg_type)))]
     pub arg_type: CoreIndex,
-    #[sugg(label = format!("Function expects an argument of type: {}", env.index_to_sm_string(self.function_arg_type)))]
+    #[sugg(label = format!("Function expects an argument of type: {}", env.index_to_sm_string(self.function_arg_type)), secondary)]
     pub function_type: CoreIndex,
     pub function_arg_type: CoreIndex,
 }
 
+/// The type of a value is not the type it was asserted to have with `value : type`.
+///
+/// For example, `Type` has type `Type`, which is not a function type:
+///
+/// ```prism
+/// Type : (Type -> Type)
+/// ```
 #[derive(Diagnostic)]
 #[diag(title = "Failed type assert", env = PrismDb)]
 pub struct FailedTypeAssert {
-    #[sugg(label = format!("Found a value of type: {}", env.index_to_sm_string(self.expr_type)))]
+    #[sugg(label = format!("Found a value of type: {}", self.found))]
     pub expr: CoreIndex,
-    pub expr_type: CoreIndex,
-    #[sugg(label = format!("Expected a value of type: {}", env.index_to_sm_string(self.expected_type)))]
+    pub found: String,
+    #[sugg(label = format!("Expected a value of type: {}", self.expected), secondary)]
     pub expected_type: CoreIndex,
+    pub expected: String,
 }
 
+/// The universe levels that are inferred can't satisfy all constraints between them.
+///
+/// Levels that are not given explicitly are inferred from how the types are used.
+/// A type is in a larger universe than the values of the type, so a type can't be a value of itself:
+///
+/// ```prism
+/// let T = Type;
+/// (T : T)
+/// ```
+///
+/// Use types of different levels to fix this.
+#[derive(Diagnostic)]
+#[diag(title = "Inconsistent universe levels", env = PrismDb)]
+pub struct InconsistentLevels {
+    #[sugg(label = "This level should be at most the other level")]
+    pub smaller: CoreIndex,
+    #[sugg(label = "This level should be at least the other level", secondary)]
+    pub larger: CoreIndex,
+}
+
+/// Checking whether two values are equal took too many steps.
+///
+/// This happens when a value is inferred to contain itself, such as the type of `v` in:
+///
+/// ```prism
+/// (v: _) -> v v
+/// ```
+///
+/// Give the types of the values explicitly to avoid this.
 #[derive(Diagnostic)]
 #[diag(title = "Recursion limit reached during beta solving", env = PrismDb)]
 pub struct RecursionLimit {
@@ -60,6 +131,10 @@ pub struct RecursionLimit {
     pub right: CoreIndex,
 }
 
+/// The type checker inferred a value that refers to variables that are not in scope of it.
+///
+/// This is a problem with the type checker rather than with the program.
+/// Giving the inferred values explicitly usually avoids it.
 #[derive(Diagnostic)]
 #[diag(title = "Internal problem when inferring this variable", env = PrismDb)]
 pub struct BadInfer {
@@ -68,3 +143,66 @@ pub struct BadInfer {
     #[sugg(label = "Inferred variable")]
     pub inferred_var: CoreIndex,
 }
+
+/// A constructor of a data type has a type that is in a larger universe than the data type.
+///
+/// A data type in `Type 0` can only contain values whose types are in `Type 0`.
+/// For example, `box` contains a type, which is in `Type 1`:
+///
+/// ```prism
+/// data Box: Type 0 {
+///     box: Type 0 -> Box;
+/// };
+/// Box
+/// ```
+///
+/// Declare the data type in a larger universe, such as `Type 1`, to fix this.
+#[derive(Diagnostic)]
+#[diag(title = "Constructor does not fit in the universe of its data type", env = PrismDb)]
+pub struct ConstructorUniverse {
+    #[sugg(label = format!("The type of this constructor is: {}", env.index_to_sm_string(self.constructor_type)))]
+    pub constructor: CoreIndex,
+    pub constructor_type: CoreIndex,
+    #[sugg(label = "The universe of the data type is declared here")]
+    pub data_type: CoreIndex,
+}
+
+/// The free theorem of a value was requested with `paramatricity`, but it can't be generated.
+///
+/// Free theorems can't be generated for every kind of value, such as values of data types:
+///
+/// ```prism
+/// data Bool: Type {
+///     true: Bool;
+///     false: Bool;
+/// };
+/// paramatricity true
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = "Cannot generate the free theorem of this value", env = PrismDb)]
+pub struct ParamatricityUnsupported {
+    #[sugg(label = format!("The type of this value is: {}", self.value_type))]
+    pub value: CoreIndex,
+    pub value_type: String,
+    #[sugg(label = self.reason)]
+    pub term: CoreIndex,
+    pub reason: &'static str,
+}
+
+/// A data type occurs to the left of an arrow in the argument of one of its constructors.
+///
+/// Such data types make it possible to write programs that don't terminate, so they are not allowed.
+/// For example, `bad` takes a function that takes a `Bad`:
+///
+/// ```prism
+/// data Bad: Type {
+///     bad: (Bad -> Type) -> Bad;
+/// };
+/// Type
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = "Data type occurs in a non-strictly positive position", env = PrismDb)]
+pub struct NonPositiveOccurrence {
+    #[sugg(label = "Constructor arguments may only refer to the data type in their return type")]
+    pub occurrence: CoreIndex,
+}
@@ -1,14 +1,17 @@
 use crate::lang::CoreIndex;
 use crate::lang::CorePrismExpr;
-use crate::lang::ValueOrigin::FreeSub;
+use crate::lang::ValueOrigin::{FreeSub, TypeOf};
 use crate::lang::env::DbEnv;
 use crate::lang::env::EnvEntry::*;
 use crate::type_check::TypecheckPrismEnv;
-use crate::type_check::errors::{ExpectedFn, ExpectedFnArg, ExpectedType, FailedTypeAssert};
+use crate::type_check::errors::{
+    ExpectedFn, ExpectedFnArg, ExpectedPair, ExpectedType, FailedTypeAssert,
+};
+use crate::type_check::level::Subtype;
 use std::collections::HashMap;
 
 impl<'a> TypecheckPrismEnv<'a> {
-    /// Expect `i1` to be equal to `i2` in `s`
+    /// Expect `expr_type` to be a subtype of `expected_type` in `s`
     pub fn expect_beq_assert(
         &mut self,
         expr: CoreIndex,
@@ -16,33 +19,40 @@ impl<'a> TypecheckPrismEnv<'a> {
         expected_type: CoreIndex,
         s: &DbEnv,
     ) {
-        if !self.expect_beq_internal(
+        if !self.expect_subtype_internal(
             (expr_type, s, &mut HashMap::new()),
             (expected_type, s, &mut HashMap::new()),
+            Subtype::Left,
             0,
         ) {
+            let found = self.display_type_of(expr, expr_type, s);
+            let expected = self.display_type_of(expr, expected_type, s);
             self.db.push_error(FailedTypeAssert {
                 expr,
-                expr_type,
+                found,
                 expected_type,
+                expected,
             });
         }
     }
 
-    /// Expect `io` to be equal to `Type`.
-    pub fn expect_beq_type(&mut self, io: CoreIndex, s: &DbEnv) {
-        let (i, s) = self.db.beta_reduce_head(io, s);
-        match self.db.checked_values[*i] {
-            CorePrismExpr::Type => {}
-            CorePrismExpr::Free => {
-                self.db.checked_values[*i] = CorePrismExpr::Type;
-                if !self.handle_constraints(i, &s, 0) {
-                    self.db.push_error(ExpectedType { index: io });
-                }
-            }
-            _ => {
-                self.db.push_error(ExpectedType { index: io });
-            }
+    /// Expect `io` to be a universe `Type l`. Returns the level `l`, which is valid in `s`.
+    pub fn expect_beq_type(&mut self, io: CoreIndex, s: &DbEnv) -> CoreIndex {
+        let l = self.db.store_checked(CorePrismExpr::Free, FreeSub(io));
+        let t = self.db.store_checked(CorePrismExpr::Type(l), FreeSub(io));
+        if !self.expect_beq_internal((io, s, &mut HashMap::new()), (t, s, &mut HashMap::new()), 0) {
+            self.db.push_error(ExpectedType { index: io });
+        }
+        l
+    }
+
+    /// Expect `l` to be a level
+    pub fn expect_level(&mut self, l: CoreIndex, s: &DbEnv) {
+        let err_count = self.db.diags.len();
+        let lt = self._type_check(l, s);
+        if self.db.diags.len() == err_count {
+            let level = self.db.store_checked(CorePrismExpr::Level, TypeOf(l));
+            self.expect_beq_assert(l, lt, level, s);
         }
     }
 
@@ -54,9 +64,10 @@ impl<'a> TypecheckPrismEnv<'a> {
         match self.db.checked_values[*fr] {
             CorePrismExpr::FnType(f_at, f_rt) => {
                 // Check
-                if !self.expect_beq_internal(
+                if !self.expect_subtype_internal(
                     (f_at, &sr, &mut HashMap::new()),
                     (at, s, &mut HashMap::new()),
+                    Subtype::Right,
                     0,
                 ) {
                     self.db.push_error(ExpectedFnArg {
@@ -117,4 +128,47 @@ impl<'a> TypecheckPrismEnv<'a> {
             }
         }
     }
+
+    /// Expect `pt` to be a pair type, with the type of the first value `at` and of the second value `bt`.
+    /// `at` and `bt` should be free, `bt` is valid in `s` with the first value added.
+    pub fn expect_beq_pair_type(&mut self, pt: CoreIndex, at: CoreIndex, bt: CoreIndex, s: &DbEnv) {
+        let (pr, sr) = self.db.beta_reduce_head(pt, s);
+
+        let (p_at, p_bt) = match self.db.checked_values[*pr] {
+            CorePrismExpr::PairType(p_at, p_bt) => (p_at, p_bt),
+            CorePrismExpr::Free => {
+                let p_at = self.db.store_checked(CorePrismExpr::Free, FreeSub(pr));
+                let p_bt = self.db.store_checked(CorePrismExpr::Free, FreeSub(pr));
+                self.db.checked_values[*pr] = CorePrismExpr::PairType(p_at, p_bt);
+
+                if !self.handle_constraints(pr, &sr, 0) {
+                    self.db.push_error(ExpectedPair { index: pt });
+                }
+                (p_at, p_bt)
+            }
+            _ => {
+                self.db.push_error(ExpectedPair { index: pt });
+                return;
+            }
+        };
+
+        let is_beq_free = self.expect_beq_free(
+            (p_at, &sr, &mut HashMap::new()),
+            (at, s, &mut HashMap::new()),
+            0,
+        );
+        assert!(is_beq_free);
+
+        let mut var_map1 = HashMap::new();
+        let mut var_map2 = HashMap::new();
+        let id = self.new_tc_id();
+        var_map1.insert(id, sr.len());
+        var_map2.insert(id, s.len());
+        let is_beq_free = self.expect_beq_free(
+            (p_bt, &sr.cons(RType(id)), &mut var_map1),
+            (bt, &s.cons(RType(id)), &mut var_map2),
+            0,
+        );
+        assert!(is_beq_free);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::interp::nbe::FreeMode;
 use crate::lang::CoreIndex;
 use crate::lang::CorePrismExpr;
 use crate::lang::ValueOrigin::FreeSub;
@@ -27,15 +28,31 @@ impl<'a> TypecheckPrismEnv<'a> {
             return true;
         }
 
+        // Values that don't depend on free values are compared by evaluating them, which does not build new terms.
+        // Otherwise they are unified below, which may solve the free values.
+        if self
+            .db
+            .convertible((i1o, s1), (i2o, s2), FreeMode::Block, None)
+            == Some(true)
+        {
+            return true;
+        }
+
         // Brh and reduce i1 and i2
         let (i1, s1) = self.db.beta_reduce_head(i1o, s1);
         let (i2, s2) = self.db.beta_reduce_head(i2o, s2);
+        let free_headed1 = self.db.is_free_headed(i1, &s1);
+        let free_headed2 = self.db.is_free_headed(i2, &s2);
 
         match (&self.db.checked_values[*i1], &self.db.checked_values[*i2]) {
-            // Type is always equal to Type
-            (CorePrismExpr::Type, CorePrismExpr::Type) => {
-                // If beta_reduce returns a Type, we're done. Easy work!
-                true
+            // Universes are equal if their levels are equal
+            (&CorePrismExpr::Type(l1), &CorePrismExpr::Type(l2)) => {
+                self.expect_level_eq((l1, &s1, var_map1), (l2, &s2, var_map2), depth + 1)
+            }
+            (CorePrismExpr::Level, CorePrismExpr::Level) => true,
+            // Levels are compared using their normal form, which may also solve free levels
+            (e1, e2) if e1.is_level_value() || e2.is_level_value() => {
+                self.expect_level_eq((i1, &s1, var_map1), (i2, &s2, var_map2), depth + 1)
             }
             // ParsedType is always equal to ParsedType
             (CorePrismExpr::GrammarType, CorePrismExpr::GrammarType) => true,
@@ -95,12 +112,66 @@ impl<'a> TypecheckPrismEnv<'a> {
                     self.expect_beq_internal((a1, &s1, var_map1), (a2, &s2, var_map2), depth + 1);
                 f_equal && b_equal
             }
+            // Pair types work the same as function types
+            (&CorePrismExpr::PairType(a1, b1), &CorePrismExpr::PairType(a2, b2)) => {
+                let a_equal =
+                    self.expect_beq_internal((a1, &s1, var_map1), (a2, &s2, var_map2), depth + 1);
+
+                let id = self.new_tc_id();
+                var_map1.insert(id, s1.len());
+                var_map2.insert(id, s2.len());
+                let b_equal = self.expect_beq_internal(
+                    (b1, &s1.cons(RType(id)), var_map1),
+                    (b2, &s2.cons(RType(id)), var_map2),
+                    depth + 1,
+                );
+
+                a_equal && b_equal
+            }
+            (&CorePrismExpr::PairConstruct(a1, b1), &CorePrismExpr::PairConstruct(a2, b2)) => {
+                let a_equal =
+                    self.expect_beq_internal((a1, &s1, var_map1), (a2, &s2, var_map2), depth + 1);
+                let b_equal =
+                    self.expect_beq_internal((b1, &s1, var_map1), (b2, &s2, var_map2), depth + 1);
+                a_equal && b_equal
+            }
+            // Projections can only occur in this position when the pair is an argument to a function in the original scope
+            (&CorePrismExpr::PairFst(p1), &CorePrismExpr::PairFst(p2))
+            | (&CorePrismExpr::PairSnd(p1), &CorePrismExpr::PairSnd(p2))
+            | (&CorePrismExpr::Paramatricity(p1), &CorePrismExpr::Paramatricity(p2)) => {
+                self.expect_beq_internal((p1, &s1, var_map1), (p2, &s2, var_map2), depth + 1)
+            }
+            // Data constants are equal if they come from the same declaration
+            (&CorePrismExpr::DataType(d1), &CorePrismExpr::DataType(d2))
+            | (&CorePrismExpr::DataElim(d1), &CorePrismExpr::DataElim(d2)) => d1 == d2,
+            // Literals are only equal to the same literal, primitive operations that are stuck are compared as constants
+            (CorePrismExpr::LiteralType(t1), CorePrismExpr::LiteralType(t2)) => t1 == t2,
+            (CorePrismExpr::Literal(l1), CorePrismExpr::Literal(l2)) => l1 == l2,
+            (CorePrismExpr::PrimOp(op1), CorePrismExpr::PrimOp(op2)) => op1 == op2,
+            (&CorePrismExpr::DataConstruct(d1, k1), &CorePrismExpr::DataConstruct(d2, k2)) => {
+                d1 == d2 && k1 == k2
+            }
             (_, CorePrismExpr::Free) => {
                 self.expect_beq_free((i1, &s1, var_map1), (i2, &s2, var_map2), depth + 1)
             }
             (CorePrismExpr::Free, _) => {
                 self.expect_beq_free((i2, &s2, var_map2), (i1, &s1, var_map1), depth + 1)
             }
+            // Eta for functions, `x => b` is equal to `f` if `b` is equal to `f x`
+            // If the other side is an application of a free variable, we instead try to solve the free variable below
+            (&CorePrismExpr::FnConstruct(b1), _) if !free_headed2 => {
+                self.expect_beq_fn_eta((b1, &s1, var_map1), (i2, &s2, var_map2), depth)
+            }
+            (_, &CorePrismExpr::FnConstruct(b2)) if !free_headed1 => {
+                self.expect_beq_fn_eta((b2, &s2, var_map2), (i1, &s1, var_map1), depth)
+            }
+            // Eta for pairs, `(a, b)` is equal to `p` if `a` is equal to `fst p` and `b` to `snd p`
+            (&CorePrismExpr::PairConstruct(a1, b1), _) if !free_headed2 => {
+                self.expect_beq_pair_eta((a1, b1, &s1, var_map1), (i2, &s2, var_map2), depth)
+            }
+            (_, &CorePrismExpr::PairConstruct(a2, b2)) if !free_headed1 => {
+                self.expect_beq_pair_eta((a2, b2, &s2, var_map2), (i1, &s1, var_map1), depth)
+            }
             (&CorePrismExpr::FnDestruct(f, _), _) => {
                 self.expect_beq_in_destruct(f, &s1, var_map1, (i2, &s2, var_map2))
             }
@@ -111,6 +182,50 @@ impl<'a> TypecheckPrismEnv<'a> {
         }
     }
 
+    fn expect_beq_pair_eta(
+        &mut self,
+        (a1, b1, s1, var_map1): (
+            CoreIndex,
+            CoreIndex,
+            &DbEnv,
+            &mut HashMap<UniqueVariableId, usize>,
+        ),
+        (p2, s2, var_map2): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        depth: usize,
+    ) -> bool {
+        let origin = self.db.checked_origins[*p2];
+        let a2 = self.db.store_checked(CorePrismExpr::PairFst(p2), origin);
+        let b2 = self.db.store_checked(CorePrismExpr::PairSnd(p2), origin);
+        let a_equal = self.expect_beq_internal((a1, s1, var_map1), (a2, s2, var_map2), depth + 1);
+        let b_equal = self.expect_beq_internal((b1, s1, var_map1), (b2, s2, var_map2), depth + 1);
+        a_equal && b_equal
+    }
+
+    fn expect_beq_fn_eta(
+        &mut self,
+        (b1, s1, var_map1): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        (f2, s2, var_map2): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        depth: usize,
+    ) -> bool {
+        let origin = self.db.checked_origins[*f2];
+        let f = self.db.store_checked(CorePrismExpr::Shift(f2, 1), origin);
+        let x = self
+            .db
+            .store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+        let b2 = self
+            .db
+            .store_checked(CorePrismExpr::FnDestruct(f, x), origin);
+
+        let id = self.new_tc_id();
+        var_map1.insert(id, s1.len());
+        var_map2.insert(id, s2.len());
+        self.expect_beq_internal(
+            (b1, &s1.cons(RType(id)), var_map1),
+            (b2, &s2.cons(RType(id)), var_map2),
+            depth + 1,
+        )
+    }
+
     pub fn expect_beq_in_destruct(
         &mut self,
         f1: CoreIndex,
@@ -119,12 +234,32 @@ impl<'a> TypecheckPrismEnv<'a> {
         (i2, s2, var_map2): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
     ) -> bool {
         let (f1, f1s) = self.db.beta_reduce_head(f1, s1);
+        // If `f1` is not headed by a free variable, it is neutral and can never reduce to `i2`
+        if !self.db.is_free_headed(f1, &f1s) {
+            return false;
+        }
         assert!(matches!(
             self.db.checked_values[*i2],
-            CorePrismExpr::Type
+            CorePrismExpr::Type(_)
                 | CorePrismExpr::FnType(_, _)
                 | CorePrismExpr::FnConstruct(_)
                 | CorePrismExpr::DeBruijnIndex(_)
+                | CorePrismExpr::PairType(_, _)
+                | CorePrismExpr::PairConstruct(_, _)
+                | CorePrismExpr::PairFst(_)
+                | CorePrismExpr::PairSnd(_)
+                | CorePrismExpr::DataType(_)
+                | CorePrismExpr::DataConstruct(_, _)
+                | CorePrismExpr::DataElim(_)
+                | CorePrismExpr::Level
+                | CorePrismExpr::LevelConst(_)
+                | CorePrismExpr::LevelSucc(_)
+                | CorePrismExpr::LevelMax(_, _)
+                | CorePrismExpr::LevelOmega
+                | CorePrismExpr::Paramatricity(_)
+                | CorePrismExpr::LiteralType(_)
+                | CorePrismExpr::Literal(_)
+                | CorePrismExpr::PrimOp(_)
         ));
 
         // We are in the case `f1 a1 = i2`
@@ -159,9 +294,14 @@ impl<'a> TypecheckPrismEnv<'a> {
 
         // We deliberately don't beta-reduce i1 here since we want to keep the inferred value small
         match &self.db.checked_values[*i1] {
-            CorePrismExpr::Type => {
-                self.db.checked_values[*i2] = CorePrismExpr::Type;
-                self.handle_constraints(i2, s2, depth + 1)
+            &CorePrismExpr::Type(l1) => {
+                let l2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::Type(l2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+                let l_eq =
+                    self.expect_beq_internal((l1, s1, var_map1), (l2, s2, var_map2), depth + 1);
+                constraints_eq && l_eq
             }
             &CorePrismExpr::Let(v1, b1) => {
                 let v2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
@@ -316,6 +456,66 @@ impl<'a> TypecheckPrismEnv<'a> {
                     self.expect_beq_internal((a1, s1, var_map1), (a2, s2, var_map2), depth + 1);
                 constraints_eq && f_eq && a_eq
             }
+            &CorePrismExpr::PairType(a1, b1) => {
+                let a2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                let b2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::PairType(a2, b2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+
+                let a_eq =
+                    self.expect_beq_internal((a1, s1, var_map1), (a2, s2, var_map2), depth + 1);
+                let id = self.new_tc_id();
+                var_map1.insert(id, s1.len());
+                var_map2.insert(id, s2.len());
+                let b_eq = self.expect_beq_internal(
+                    (b1, &s1.cons(RType(id)), var_map1),
+                    (b2, &s2.cons(RType(id)), var_map2),
+                    depth + 1,
+                );
+
+                constraints_eq && a_eq && b_eq
+            }
+            &CorePrismExpr::PairConstruct(a1, b1) => {
+                let a2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                let b2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::PairConstruct(a2, b2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+
+                let a_eq =
+                    self.expect_beq_internal((a1, s1, var_map1), (a2, s2, var_map2), depth + 1);
+                let b_eq =
+                    self.expect_beq_internal((b1, s1, var_map1), (b2, s2, var_map2), depth + 1);
+                constraints_eq && a_eq && b_eq
+            }
+            &CorePrismExpr::PairFst(p1) => {
+                let p2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::PairFst(p2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+                let p_eq =
+                    self.expect_beq_internal((p1, s1, var_map1), (p2, s2, var_map2), depth + 1);
+                constraints_eq && p_eq
+            }
+            &CorePrismExpr::PairSnd(p1) => {
+                let p2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::PairSnd(p2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+                let p_eq =
+                    self.expect_beq_internal((p1, s1, var_map1), (p2, s2, var_map2), depth + 1);
+                constraints_eq && p_eq
+            }
+            &CorePrismExpr::Paramatricity(x1) => {
+                let x2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::Paramatricity(x2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+                let x_eq =
+                    self.expect_beq_internal((x1, s1, var_map1), (x2, s2, var_map2), depth + 1);
+                constraints_eq && x_eq
+            }
             CorePrismExpr::Free => {
                 self.queued_beq_free.entry(i1).or_default().push((
                     (s1.clone(), var_map1.clone()),
@@ -341,6 +541,58 @@ impl<'a> TypecheckPrismEnv<'a> {
                 self.db.checked_values[*i2] = CorePrismExpr::GrammarValue(g.clone());
                 self.handle_constraints(i2, s2, depth + 1)
             }
+            e @ (CorePrismExpr::Level
+            | CorePrismExpr::LevelConst(_)
+            | CorePrismExpr::LevelOmega
+            | CorePrismExpr::LiteralType(_)
+            | CorePrismExpr::Literal(_)
+            | CorePrismExpr::PrimOp(_)) => {
+                self.db.checked_values[*i2] = e.clone();
+                self.handle_constraints(i2, s2, depth + 1)
+            }
+            &CorePrismExpr::LevelSucc(a1) => {
+                let a2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::LevelSucc(a2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+                let a_eq =
+                    self.expect_beq_internal((a1, s1, var_map1), (a2, s2, var_map2), depth + 1);
+                constraints_eq && a_eq
+            }
+            &CorePrismExpr::LevelMax(a1, b1) => {
+                let a2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                let b2 = self.db.store_checked(CorePrismExpr::Free, FreeSub(i2));
+                self.db.checked_values[*i2] = CorePrismExpr::LevelMax(a2, b2);
+
+                let constraints_eq = self.handle_constraints(i2, s2, depth + 1);
+                let a_eq =
+                    self.expect_beq_internal((a1, s1, var_map1), (a2, s2, var_map2), depth + 1);
+                let b_eq =
+                    self.expect_beq_internal((b1, s1, var_map1), (b2, s2, var_map2), depth + 1);
+                constraints_eq && a_eq && b_eq
+            }
+            &CorePrismExpr::Data(ref decl, b1) => {
+                // The declaration can't be copied to the scope of `i2`, so infer the body with the declaration substituted
+                let s1 = decl.reduce_env(s1);
+                self.expect_beq_free((b1, &s1, var_map1), (i2, s2, var_map2), depth + 1)
+            }
+            CorePrismExpr::DataType(_)
+            | CorePrismExpr::DataConstruct(_, _)
+            | CorePrismExpr::DataElim(_) => {
+                // Data constants have their type in the scope of their declaration, so they are referred to through that scope
+                let Some(v2) = s2
+                    .iter()
+                    .position(|(_, e)| matches!(e, CSubst(v, _) | RSubst(v, _) if *v == i1))
+                else {
+                    self.db.push_error(BadInfer {
+                        free_var: i2,
+                        inferred_var: i1,
+                    });
+                    return true;
+                };
+                self.db.checked_values[*i2] = CorePrismExpr::DeBruijnIndex(v2);
+                self.handle_constraints(i2, s2, depth + 1)
+            }
         }
     }
 
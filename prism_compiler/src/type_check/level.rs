@@ -0,0 +1,291 @@
+use crate::interp::nbe::FreeMode;
+use crate::lang::CoreIndex;
+use crate::lang::CorePrismExpr;
+use crate::lang::ValueOrigin;
+use crate::lang::env::DbEnv;
+use crate::lang::env::EnvEntry::*;
+use crate::lang::level::LevelAtom;
+use crate::type_check::errors::InconsistentLevels;
+use crate::type_check::{QueuedLevelConstraint, TypecheckPrismEnv, UniqueVariableId};
+use std::collections::HashMap;
+use std::mem;
+
+/// Which side of a constraint should be the subtype
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Subtype {
+    Left,
+    Right,
+}
+
+impl TypecheckPrismEnv<'_> {
+    /// Expect the type `i1` to be a subtype of `i2` if `sub` is `Left`, or the other way around if it is `Right`.
+    /// Types are subtypes if they are equal, except that a universe is a subtype of all universes with a higher level.
+    /// This extends to the return type of function types and to pair types.
+    #[must_use]
+    pub fn expect_subtype_internal(
+        &mut self,
+        (i1o, s1, var_map1): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        (i2o, s2, var_map2): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        sub: Subtype,
+        depth: usize,
+    ) -> bool {
+        // Like `expect_beq_internal`, types that don't depend on free values are compared by evaluating them
+        if self
+            .db
+            .convertible((i1o, s1), (i2o, s2), FreeMode::Block, Some(sub))
+            == Some(true)
+        {
+            return true;
+        }
+
+        let (i1, s1) = self.db.beta_reduce_head(i1o, s1);
+        let (i2, s2) = self.db.beta_reduce_head(i2o, s2);
+
+        match (&self.db.checked_values[*i1], &self.db.checked_values[*i2]) {
+            (&CorePrismExpr::Type(l1), &CorePrismExpr::Type(l2)) => match sub {
+                Subtype::Left => {
+                    self.expect_level_leq((l1, &s1, var_map1), (l2, &s2, var_map2), depth + 1)
+                }
+                Subtype::Right => {
+                    self.expect_level_leq((l2, &s2, var_map2), (l1, &s1, var_map1), depth + 1)
+                }
+            },
+            // The argument types should be equal, the return types are compared with the same variance
+            (&CorePrismExpr::FnType(a1, b1), &CorePrismExpr::FnType(a2, b2)) => {
+                let a_equal =
+                    self.expect_beq_internal((a1, &s1, var_map1), (a2, &s2, var_map2), depth + 1);
+
+                let id = self.new_tc_id();
+                var_map1.insert(id, s1.len());
+                var_map2.insert(id, s2.len());
+                let b_sub = self.expect_subtype_internal(
+                    (b1, &s1.cons(RType(id)), var_map1),
+                    (b2, &s2.cons(RType(id)), var_map2),
+                    sub,
+                    depth + 1,
+                );
+
+                a_equal && b_sub
+            }
+            (&CorePrismExpr::PairType(a1, b1), &CorePrismExpr::PairType(a2, b2)) => {
+                let a_sub = self.expect_subtype_internal(
+                    (a1, &s1, var_map1),
+                    (a2, &s2, var_map2),
+                    sub,
+                    depth + 1,
+                );
+
+                let id = self.new_tc_id();
+                var_map1.insert(id, s1.len());
+                var_map2.insert(id, s2.len());
+                let b_sub = self.expect_subtype_internal(
+                    (b1, &s1.cons(RType(id)), var_map1),
+                    (b2, &s2.cons(RType(id)), var_map2),
+                    sub,
+                    depth + 1,
+                );
+
+                a_sub && b_sub
+            }
+            _ => self.expect_beq_internal((i1, &s1, var_map1), (i2, &s2, var_map2), depth),
+        }
+    }
+
+    /// Returns the level of the function or pair type `i`, given the level `al` of the type of the first value,
+    /// and the level `bl` of the type of the second value which is valid with the first value bound.
+    /// If `bl` depends on the first value, which is the case if it is a level, this is the level `ω`.
+    pub(super) fn binder_level(&mut self, i: CoreIndex, al: CoreIndex, bl: CoreIndex) -> CoreIndex {
+        let origin = ValueOrigin::TypeOf(i);
+        let omega = self.db.store_checked(CorePrismExpr::LevelOmega, origin);
+        let bl = self.db.store_checked(CorePrismExpr::Let(omega, bl), origin);
+        self.db
+            .store_checked(CorePrismExpr::LevelMax(al, bl), origin)
+    }
+
+    /// Expect the level `l1` to be at most `l2`.
+    /// If this depends on levels that are not known yet, they are inferred.
+    #[must_use]
+    pub fn expect_level_leq(
+        &mut self,
+        (l1, s1, var_map1): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        (l2, s2, var_map2): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        depth: usize,
+    ) -> bool {
+        if self.db.args.type_in_type {
+            return true;
+        }
+        let nf1 = self.db.level_nf(l1, s1);
+        let nf2 = self.db.level_nf(l2, s2);
+        if nf1.leq(&nf2) {
+            return true;
+        }
+
+        let (l1, s1) = self.db.beta_reduce_head(l1, s1);
+        let (l2, s2) = self.db.beta_reduce_head(l2, s2);
+        match (&self.db.checked_values[*l1], &self.db.checked_values[*l2]) {
+            // `lmax a b` is at most `l2` if both `a` and `b` are
+            (&CorePrismExpr::LevelMax(a, b), _) => {
+                let a_leq =
+                    self.expect_level_leq((a, &s1, var_map1), (l2, &s2, var_map2), depth + 1);
+                let b_leq =
+                    self.expect_level_leq((b, &s1, var_map1), (l2, &s2, var_map2), depth + 1);
+                a_leq && b_leq
+            }
+            (&CorePrismExpr::LevelSucc(a), &CorePrismExpr::LevelSucc(b)) => {
+                self.expect_level_leq((a, &s1, var_map1), (b, &s2, var_map2), depth + 1)
+            }
+            (&CorePrismExpr::LevelSucc(a), &CorePrismExpr::LevelConst(n)) if n > 0 => {
+                let b = self.db.store_checked(
+                    CorePrismExpr::LevelConst(n - 1),
+                    self.db.checked_origins[*l2],
+                );
+                self.expect_level_leq((a, &s1, var_map1), (b, &s2, var_map2), depth + 1)
+            }
+            (&CorePrismExpr::LevelConst(n), &CorePrismExpr::LevelSucc(b)) if n > 0 => {
+                let a = self.db.store_checked(
+                    CorePrismExpr::LevelConst(n - 1),
+                    self.db.checked_origins[*l1],
+                );
+                self.expect_level_leq((a, &s1, var_map1), (b, &s2, var_map2), depth + 1)
+            }
+            // Infer the level if the other side is known
+            (_, CorePrismExpr::Free) if !nf1.has_free() => {
+                self.expect_beq_free((l1, &s1, var_map1), (l2, &s2, var_map2), depth + 1)
+            }
+            (CorePrismExpr::Free, _) if !nf2.has_free() => {
+                self.expect_beq_free((l2, &s2, var_map2), (l1, &s1, var_map1), depth + 1)
+            }
+            // If both sides are not known yet, picking a solution may make later constraints unsatisfiable,
+            // so the constraint is checked when type checking is done
+            _ if nf1.has_free() && nf2.has_free() => {
+                self.queued_levels.push(((l1, s1), (l2, s2)));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Expect the levels `l1` and `l2` to be equal.
+    /// If this depends on levels that are not known yet, they are inferred.
+    #[must_use]
+    pub fn expect_level_eq(
+        &mut self,
+        (l1, s1, var_map1): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        (l2, s2, var_map2): (CoreIndex, &DbEnv, &mut HashMap<UniqueVariableId, usize>),
+        depth: usize,
+    ) -> bool {
+        if self.db.args.type_in_type {
+            return true;
+        }
+        let nf1 = self.db.level_nf(l1, s1);
+        let nf2 = self.db.level_nf(l2, s2);
+        if nf1.leq(&nf2) && nf2.leq(&nf1) {
+            return true;
+        }
+
+        let (l1, s1) = self.db.beta_reduce_head(l1, s1);
+        let (l2, s2) = self.db.beta_reduce_head(l2, s2);
+        match (&self.db.checked_values[*l1], &self.db.checked_values[*l2]) {
+            // This is not complete, but it is the common case when both levels are inferred from the same structure
+            (&CorePrismExpr::LevelMax(a1, b1), &CorePrismExpr::LevelMax(a2, b2)) => {
+                let a_eq =
+                    self.expect_level_eq((a1, &s1, var_map1), (a2, &s2, var_map2), depth + 1);
+                let b_eq =
+                    self.expect_level_eq((b1, &s1, var_map1), (b2, &s2, var_map2), depth + 1);
+                a_eq && b_eq
+            }
+            (&CorePrismExpr::LevelSucc(a), &CorePrismExpr::LevelSucc(b)) => {
+                self.expect_level_eq((a, &s1, var_map1), (b, &s2, var_map2), depth + 1)
+            }
+            (&CorePrismExpr::LevelSucc(a), &CorePrismExpr::LevelConst(n)) if n > 0 => {
+                let b = self.db.store_checked(
+                    CorePrismExpr::LevelConst(n - 1),
+                    self.db.checked_origins[*l2],
+                );
+                self.expect_level_eq((a, &s1, var_map1), (b, &s2, var_map2), depth + 1)
+            }
+            (&CorePrismExpr::LevelConst(n), &CorePrismExpr::LevelSucc(b)) if n > 0 => {
+                let a = self.db.store_checked(
+                    CorePrismExpr::LevelConst(n - 1),
+                    self.db.checked_origins[*l1],
+                );
+                self.expect_level_eq((a, &s1, var_map1), (b, &s2, var_map2), depth + 1)
+            }
+            // Two levels that are not known yet are queued to be equal
+            (CorePrismExpr::Free, CorePrismExpr::Free) => {
+                self.expect_beq_free((l1, &s1, var_map1), (l2, &s2, var_map2), depth + 1)
+            }
+            (_, CorePrismExpr::Free) if !nf1.has_free() => {
+                self.expect_beq_free((l1, &s1, var_map1), (l2, &s2, var_map2), depth + 1)
+            }
+            (CorePrismExpr::Free, _) if !nf2.has_free() => {
+                self.expect_beq_free((l2, &s2, var_map2), (l1, &s1, var_map1), depth + 1)
+            }
+            // Solving a level in terms of other unknown levels may not terminate, see `expect_level_leq`
+            _ if nf1.has_free() && nf2.has_free() => {
+                self.queued_levels
+                    .push(((l1, s1.clone()), (l2, s2.clone())));
+                self.queued_levels.push(((l2, s2), (l1, s1)));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl TypecheckPrismEnv<'_> {
+    /// Checks the constraints between levels that were not known yet when they were encountered.
+    /// Each constraint `max(c, a_1 + k_1, ...) <= b + l` makes `b` at least `a_i + k_i - l`,
+    /// which can be satisfied unless these bounds form a cycle in which a level should be larger than itself.
+    pub(super) fn check_level_constraints(&mut self) {
+        let constraints = mem::take(&mut self.queued_levels);
+        let mut edges = vec![];
+        for (i, ((l1, s1), (l2, s2))) in constraints.iter().enumerate() {
+            let nf1 = self.db.level_nf(*l1, s1);
+            let nf2 = self.db.level_nf(*l2, s2);
+            if !nf1.has_free() && !nf2.has_free() && !nf1.leq(&nf2) {
+                self.report_inconsistent_levels(&constraints[i]);
+                return;
+            }
+            // A maximum of multiple levels can be satisfied by either of them, so it gives no bound
+            let [(b, l)] = nf2.atoms[..] else {
+                continue;
+            };
+            for &(a, k) in &nf1.atoms {
+                // `a + k` is at most the constant of the right side if `a` is small enough
+                if !nf2.omega && k > nf2.constant {
+                    edges.push((a, b, k as isize - l as isize, i));
+                }
+            }
+        }
+
+        // The smallest solution for each level, this keeps growing if there is a cycle
+        let mut lowest: HashMap<LevelAtom, isize> = HashMap::new();
+        for round in 0..=edges.len() {
+            let mut changed = None;
+            for &(a, b, k, i) in &edges {
+                let bound = lowest.get(&a).copied().unwrap_or(0) + k;
+                if lowest.get(&b).copied().unwrap_or(0) < bound {
+                    lowest.insert(b, bound);
+                    changed = Some(i);
+                }
+            }
+            match changed {
+                None => return,
+                // A solution is found in at most one round per edge, unless there is a cycle
+                Some(i) if round == edges.len() => {
+                    self.report_inconsistent_levels(&constraints[i]);
+                    return;
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn report_inconsistent_levels(&mut self, ((l1, _), (l2, _)): &QueuedLevelConstraint) {
+        self.db.push_error(InconsistentLevels {
+            smaller: *l1,
+            larger: *l2,
+        });
+    }
+}
@@ -0,0 +1,36 @@
+use crate::lang::literal::PrimOp;
+use crate::lang::{CoreIndex, CorePrismExpr, ValueOrigin};
+use crate::type_check::TypecheckPrismEnv;
+
+impl TypecheckPrismEnv<'_> {
+    /// Returns the type of the primitive operation `op`, which is the node `i`.
+    /// Arithmetic is of the form `T -> T -> T`, comparisons are of the form `T -> T -> (R: Type) -> R -> R -> R`.
+    pub(super) fn prim_op_type(&mut self, i: CoreIndex, op: PrimOp) -> CorePrismExpr {
+        let origin = ValueOrigin::TypeOf(i);
+        let operand = self
+            .db
+            .store_checked(CorePrismExpr::LiteralType(op.operand_type()), origin);
+
+        let result = if op.is_comparison() {
+            let l = self.db.store_checked(CorePrismExpr::Free, origin);
+            let r = self.db.store_checked(CorePrismExpr::Type(l), origin);
+            let mut rt = self
+                .db
+                .store_checked(CorePrismExpr::DeBruijnIndex(2), origin);
+            for v in [1, 0] {
+                let a = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(v), origin);
+                rt = self.db.store_checked(CorePrismExpr::FnType(a, rt), origin);
+            }
+            self.db.store_checked(CorePrismExpr::FnType(r, rt), origin)
+        } else {
+            operand
+        };
+
+        let t = self
+            .db
+            .store_checked(CorePrismExpr::FnType(operand, result), origin);
+        CorePrismExpr::FnType(operand, t)
+    }
+}
@@ -1,6 +1,12 @@
-mod errors;
+mod data;
+pub(crate) mod errors;
 mod expect_beq;
 mod expect_beq_internal;
+pub(crate) mod level;
+mod literal;
+mod pair;
+mod paramatricity;
+pub(crate) mod unsolved;
 
 use crate::lang::PrismDb;
 use crate::lang::ValueOrigin;
@@ -21,6 +27,9 @@ type QueuedConstraint = (
     (CoreIndex, DbEnv, HashMap<UniqueVariableId, usize>),
 );
 
+/// A constraint that the first level is at most the second, see `expect_level_leq`
+type QueuedLevelConstraint = ((CoreIndex, DbEnv), (CoreIndex, DbEnv));
+
 pub struct TypecheckPrismEnv<'a> {
     pub db: &'a mut PrismDb,
 
@@ -28,6 +37,9 @@ pub struct TypecheckPrismEnv<'a> {
     tc_id: usize,
     queued_beq_free: HashMap<CoreIndex, Vec<QueuedConstraint>>,
     queued_tc: HashMap<CoreIndex, (DbEnv, CoreIndex)>,
+    queued_levels: Vec<QueuedLevelConstraint>,
+    /// The value of which the env is kept while type checking, see `type_check_with_scope_of`
+    scope_of: Option<(CoreIndex, Option<DbEnv>)>,
 }
 
 impl<'a> TypecheckPrismEnv<'a> {
@@ -38,6 +50,8 @@ impl<'a> TypecheckPrismEnv<'a> {
             tc_id: Default::default(),
             queued_beq_free: Default::default(),
             queued_tc: Default::default(),
+            queued_levels: Default::default(),
+            scope_of: None,
         }
     }
 
@@ -47,11 +61,52 @@ impl<'a> TypecheckPrismEnv<'a> {
         id
     }
 
+    /// Displays the type `t` of `value`, which is valid in `s`, for an error.
+    /// The variables are named by the names in scope of `value`, if these are known and line up with `s`.
+    pub(crate) fn display_type_of(&mut self, value: CoreIndex, t: CoreIndex, s: &DbEnv) -> String {
+        match self.db.scope_names.get(&value) {
+            Some(names) if names.len() == s.len() => {
+                let names = names.clone();
+                self.display_in_env(t, s, &names)
+            }
+            _ => self.db.index_to_sm_string(t),
+        }
+    }
+
+    /// Displays `i` in the type checking env `env`, using `names` as the names of the variables in `env`
+    fn display_in_env(&mut self, i: CoreIndex, env: &DbEnv, names: &[Option<String>]) -> String {
+        let i = self.db.normalize(i, env);
+        // Variables of which the value is known are substituted by `normalize`
+        let names: Vec<_> = env
+            .iter()
+            .zip(names.iter().rev())
+            .filter(|((_, e), _)| matches!(e, CType(..)))
+            .map(|(_, n)| n.clone())
+            .collect();
+        let names: Vec<_> = names.into_iter().rev().collect();
+        self.db.index_to_named_string(i, &names)
+    }
+
     /// Type checkes `i` in scope `s`. Returns the type.
     /// Invariant: Returned UnionIndex is valid in Env `s`
     pub fn _type_check(&mut self, i: CoreIndex, env: &DbEnv) -> CoreIndex {
+        if let Some((scope_of, scope)) = &mut self.scope_of
+            && *scope_of == i
+        {
+            *scope = Some(env.clone());
+        }
         let t = match self.db.checked_values[*i] {
-            CorePrismExpr::Type => CorePrismExpr::Type,
+            CorePrismExpr::Type(l) => {
+                self.expect_level(l, env);
+                if self.db.args.type_in_type {
+                    CorePrismExpr::Type(l)
+                } else {
+                    CorePrismExpr::Type(
+                        self.db
+                            .store_checked(CorePrismExpr::LevelSucc(l), ValueOrigin::TypeOf(i)),
+                    )
+                }
+            }
             CorePrismExpr::Let(mut v, b) => {
                 // Check `v`
                 let err_count = self.db.diags.len();
@@ -79,7 +134,7 @@ impl<'a> TypecheckPrismEnv<'a> {
             CorePrismExpr::FnType(mut a, b) => {
                 let err_count = self.db.diags.len();
                 let at = self._type_check(a, env);
-                self.expect_beq_type(at, env);
+                let al = self.expect_beq_type(at, env);
                 if self.db.diags.len() > err_count {
                     a = self
                         .db
@@ -91,11 +146,14 @@ impl<'a> TypecheckPrismEnv<'a> {
                 let bt = self._type_check(b, &bs);
 
                 // Check if `b` typechecked without errors.
-                if self.db.diags.len() == err_count {
-                    self.expect_beq_type(bt, &bs);
-                }
+                let bl = if self.db.diags.len() == err_count {
+                    self.expect_beq_type(bt, &bs)
+                } else {
+                    self.db
+                        .store_checked(CorePrismExpr::Free, ValueOrigin::Failure)
+                };
 
-                CorePrismExpr::Type
+                CorePrismExpr::Type(self.binder_level(i, al, bl))
             }
             CorePrismExpr::FnConstruct(b) => {
                 let a = self
@@ -126,6 +184,68 @@ impl<'a> TypecheckPrismEnv<'a> {
 
                 CorePrismExpr::Let(a, rt)
             }
+            CorePrismExpr::PairType(mut a, b) => {
+                let err_count = self.db.diags.len();
+                let at = self._type_check(a, env);
+                let al = self.expect_beq_type(at, env);
+                if self.db.diags.len() > err_count {
+                    a = self
+                        .db
+                        .store_checked(CorePrismExpr::Free, ValueOrigin::Failure);
+                }
+
+                let err_count = self.db.diags.len();
+                let bs = env.cons(CType(self.new_tc_id(), a));
+                let bt = self._type_check(b, &bs);
+
+                // Check if `b` typechecked without errors.
+                let bl = if self.db.diags.len() == err_count {
+                    self.expect_beq_type(bt, &bs)
+                } else {
+                    self.db
+                        .store_checked(CorePrismExpr::Free, ValueOrigin::Failure)
+                };
+
+                CorePrismExpr::Type(self.binder_level(i, al, bl))
+            }
+            CorePrismExpr::PairConstruct(a, b) => {
+                // Without an expected type, the type of the second value can't depend on the first
+                let at = self._type_check(a, env);
+                let bt = self._type_check(b, env);
+                let bt = self
+                    .db
+                    .store_checked(CorePrismExpr::Shift(bt, 1), ValueOrigin::TypeOf(i));
+                CorePrismExpr::PairType(at, bt)
+            }
+            CorePrismExpr::PairFst(p) | CorePrismExpr::PairSnd(p) => {
+                let at = self
+                    .db
+                    .store_checked(CorePrismExpr::Free, ValueOrigin::TypeOf(i));
+                let bt = self
+                    .db
+                    .store_checked(CorePrismExpr::Free, ValueOrigin::TypeOf(i));
+
+                let err_count = self.db.diags.len();
+                let pt = self._type_check(p, env);
+                if self.db.diags.len() == err_count {
+                    self.expect_beq_pair_type(pt, at, bt, env);
+                }
+
+                if let CorePrismExpr::PairFst(_) = self.db.checked_values[*i] {
+                    self.db.checked_types.insert(i, at);
+                    return at;
+                }
+                // The type of the second value may depend on the first
+                let fst = self
+                    .db
+                    .store_checked(CorePrismExpr::PairFst(p), ValueOrigin::TypeOf(i));
+                CorePrismExpr::Let(fst, bt)
+            }
+            CorePrismExpr::TypeAssert(e, typ)
+                if matches!(self.db.checked_values[*e], CorePrismExpr::PairConstruct(..)) =>
+            {
+                return self.check_pair_assert(e, typ, env);
+            }
             CorePrismExpr::TypeAssert(e, typ) => {
                 let err_count1 = self.db.diags.len();
                 let et = self._type_check(e, env);
@@ -153,7 +273,39 @@ impl<'a> TypecheckPrismEnv<'a> {
                 CorePrismExpr::Shift(self._type_check(v, &env.shift(shift)), shift)
             }
             CorePrismExpr::GrammarValue(_) => CorePrismExpr::GrammarType,
-            CorePrismExpr::GrammarType => CorePrismExpr::Type,
+            CorePrismExpr::GrammarType | CorePrismExpr::Level | CorePrismExpr::LiteralType(_) => {
+                CorePrismExpr::Type(
+                    self.db
+                        .store_checked(CorePrismExpr::LevelConst(0), ValueOrigin::TypeOf(i)),
+                )
+            }
+            CorePrismExpr::Literal(ref l) => CorePrismExpr::LiteralType(l.typ()),
+            CorePrismExpr::PrimOp(op) => self.prim_op_type(i, op),
+            CorePrismExpr::LevelConst(_) | CorePrismExpr::LevelOmega => CorePrismExpr::Level,
+            CorePrismExpr::LevelSucc(l) => {
+                self.expect_level(l, env);
+                CorePrismExpr::Level
+            }
+            CorePrismExpr::LevelMax(a, b) => {
+                self.expect_level(a, env);
+                self.expect_level(b, env);
+                CorePrismExpr::Level
+            }
+            CorePrismExpr::Data(ref decl, b) => {
+                let decl = decl.clone();
+                let bt = self.check_data(&decl, b, env);
+                CorePrismExpr::Data(decl, bt)
+            }
+            CorePrismExpr::DataType(_)
+            | CorePrismExpr::DataConstruct(_, _)
+            | CorePrismExpr::DataElim(_) => {
+                unreachable!("Data constants are only referred to through the env")
+            }
+            CorePrismExpr::Paramatricity(x) => {
+                let t = self.check_paramatricity(i, x, env);
+                self.db.checked_types.insert(i, t);
+                return t;
+            }
         };
         let tid = self.db.store_checked(t, ValueOrigin::TypeOf(i));
         self.db.checked_types.insert(i, tid);
@@ -163,7 +315,25 @@ impl<'a> TypecheckPrismEnv<'a> {
 
 impl PrismDb {
     pub fn type_check(&mut self, root: CoreIndex) -> CoreIndex {
-        let mut env = TypecheckPrismEnv::new(self);
-        env._type_check(root, &DbEnv::default())
+        self.type_check_with_scope_of(root, &DbEnv::default(), None)
+            .0
+    }
+
+    /// Type checks `root` in the env `env`.
+    /// Also returns the env of `scope_of`, which is part of `root`, if it was type checked.
+    pub fn type_check_with_scope_of(
+        &mut self,
+        root: CoreIndex,
+        env: &DbEnv,
+        scope_of: Option<CoreIndex>,
+    ) -> (CoreIndex, Option<DbEnv>) {
+        self.with_fuel(|db| {
+            let mut tc_env = TypecheckPrismEnv::new(db);
+            tc_env.scope_of = scope_of.map(|i| (i, None));
+            let typ = tc_env._type_check(root, env);
+            tc_env.check_level_constraints();
+            tc_env.report_unsolved(root);
+            (typ, tc_env.scope_of.and_then(|(_, scope)| scope))
+        })
     }
 }
@@ -0,0 +1,83 @@
+use crate::lang::env::DbEnv;
+use crate::lang::env::EnvEntry::*;
+use crate::lang::{CoreIndex, CorePrismExpr};
+use crate::type_check::TypecheckPrismEnv;
+use crate::type_check::errors::FailedTypeAssert;
+use crate::type_check::level::Subtype;
+use std::collections::HashMap;
+
+impl TypecheckPrismEnv<'_> {
+    /// Type checks the pair `e` against the type `typ`.
+    /// Unlike inferring the type of a pair, this allows the type of the second value to depend on the first.
+    pub(super) fn check_pair_assert(
+        &mut self,
+        e: CoreIndex,
+        typ: CoreIndex,
+        env: &DbEnv,
+    ) -> CoreIndex {
+        let CorePrismExpr::PairConstruct(a, b) = self.db.checked_values[*e] else {
+            unreachable!()
+        };
+
+        let err_count = self.db.diags.len();
+        let typt = self._type_check(typ, env);
+        if self.db.diags.len() == err_count {
+            self.expect_beq_type(typt, env);
+        }
+        if self.db.diags.len() > err_count {
+            return self._type_check(e, env);
+        }
+
+        let (tr, ts) = self.db.beta_reduce_head(typ, env);
+        let CorePrismExpr::PairType(ta, tb) = self.db.checked_values[*tr] else {
+            let et = self._type_check(e, env);
+            if self.db.diags.len() == err_count {
+                self.expect_beq_assert(e, et, typ, env);
+            }
+            return et;
+        };
+
+        let at = self._type_check(a, env);
+        if self.db.diags.len() == err_count
+            && !self.expect_subtype_internal(
+                (at, env, &mut HashMap::new()),
+                (ta, &ts, &mut HashMap::new()),
+                Subtype::Left,
+                0,
+            )
+        {
+            let found = self.display_type_of(a, at, env);
+            let expected = self.display_type_of(a, ta, &ts);
+            self.db.push_error(FailedTypeAssert {
+                expr: a,
+                found,
+                expected_type: ta,
+                expected,
+            });
+        }
+
+        // The expected type of `b` is `tb` with the first value substituted
+        let err_count = self.db.diags.len();
+        let bt = self._type_check(b, env);
+        if self.db.diags.len() == err_count
+            && !self.expect_subtype_internal(
+                (bt, env, &mut HashMap::new()),
+                (tb, &ts.cons(RSubst(a, env.clone())), &mut HashMap::new()),
+                Subtype::Left,
+                0,
+            )
+        {
+            let found = self.display_type_of(b, bt, env);
+            let expected = self.display_type_of(b, tb, &ts.cons(RSubst(a, env.clone())));
+            self.db.push_error(FailedTypeAssert {
+                expr: b,
+                found,
+                expected_type: tb,
+                expected,
+            });
+        }
+
+        self.db.checked_types.insert(e, typ);
+        typ
+    }
+}
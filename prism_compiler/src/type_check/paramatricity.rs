@@ -0,0 +1,349 @@
+use crate::lang::env::EnvEntry::*;
+use crate::lang::env::{DbEnv, EnvEntry};
+use crate::lang::{CoreIndex, CorePrismExpr, ValueOrigin};
+use crate::type_check::errors::ParamatricityUnsupported;
+use crate::type_check::{TypecheckPrismEnv, UniqueVariableId};
+use std::collections::HashMap;
+
+/// The part of a type that could not be translated, and why
+type Unsupported = (CoreIndex, &'static str);
+
+/// The variables that can be referred to while generating a translation.
+/// For each variable this stores the depth at which its value is bound in the generated term,
+/// and the depth at which its relation is bound, if it has one.
+struct ParamScope {
+    origin: ValueOrigin,
+    vars: HashMap<UniqueVariableId, (usize, Option<usize>)>,
+}
+
+impl TypecheckPrismEnv<'_> {
+    /// Type checks `paramatricity x`, which is the node `i`.
+    /// The type is the unary parametricity translation of the type of `x`, applied to `x`.
+    /// For a Church encoded value this is its induction principle.
+    pub(super) fn check_paramatricity(
+        &mut self,
+        i: CoreIndex,
+        x: CoreIndex,
+        env: &DbEnv,
+    ) -> CoreIndex {
+        let origin = ValueOrigin::TypeOf(i);
+        let err_count = self.db.diags.len();
+        let xt = self._type_check(x, env);
+        if self.db.diags.len() > err_count {
+            return self.db.store_checked(CorePrismExpr::Free, origin);
+        }
+
+        // Variables from outside can be referred to, but they have no relation
+        let mut scope = ParamScope {
+            origin,
+            vars: HashMap::new(),
+        };
+        for (v, (_, entry)) in env.iter().enumerate() {
+            if let CType(id, _) | RType(id) = *entry {
+                scope.vars.insert(id, (env.len() - v - 1, None));
+            }
+        }
+
+        match self.param_rel(xt, env, env.len(), &mut scope) {
+            Ok(rel) => self
+                .db
+                .store_checked(CorePrismExpr::FnDestruct(rel, x), origin),
+            Err((term, reason)) => {
+                let value_type = self.display_type_of(x, xt, env);
+                self.db.push_error(ParamatricityUnsupported {
+                    value: x,
+                    value_type,
+                    term,
+                    reason,
+                });
+                self.db.store_checked(CorePrismExpr::Free, origin)
+            }
+        }
+    }
+
+    /// Generates the relation of `t` in `s`, which is valid at `depth` in the generated term.
+    /// For a type the relation is a predicate on its values, for other values it is the proof that they are related.
+    fn param_rel(
+        &mut self,
+        t: CoreIndex,
+        s: &DbEnv,
+        depth: usize,
+        scope: &mut ParamScope,
+    ) -> Result<CoreIndex, Unsupported> {
+        let (t, s) = self.db.beta_reduce_head(t, s);
+        let origin = scope.origin;
+
+        let e = match self.db.checked_values[*t] {
+            // The relation of `Type l` is `A => A -> Type l`
+            CorePrismExpr::Type(l) => {
+                let l = self.param_copy_level(l, &s, depth + 2, scope)?;
+                let u = self.db.store_checked(CorePrismExpr::Type(l), origin);
+                let a = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let r = self.db.store_checked(CorePrismExpr::FnType(a, u), origin);
+                CorePrismExpr::FnConstruct(r)
+            }
+            CorePrismExpr::DeBruijnIndex(v) => match self.param_var(&s[v], scope) {
+                (_, Some(rel)) => CorePrismExpr::DeBruijnIndex(depth - rel - 1),
+                (_, None) => {
+                    return Err((
+                        t,
+                        "This variable is bound outside of `paramatricity`, so its relation is not known",
+                    ));
+                }
+            },
+            // The relation of `(x: A) -> B` is `f => (x: A) -> (xr: [A] x) -> [B] (f x)`
+            CorePrismExpr::FnType(a, b) => {
+                let a_copy = self.param_copy(a, &s, depth + 1, scope)?;
+                let a_rel = self.param_rel(a, &s, depth + 2, scope)?;
+                let x = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let a_rel = self
+                    .db
+                    .store_checked(CorePrismExpr::FnDestruct(a_rel, x), origin);
+
+                let id = self.new_tc_id();
+                scope.vars.insert(id, (depth + 1, Some(depth + 2)));
+                let b_rel = self.param_rel(b, &s.cons(RType(id)), depth + 3, scope)?;
+                let f = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(2), origin);
+                let x = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(1), origin);
+                let fx = self
+                    .db
+                    .store_checked(CorePrismExpr::FnDestruct(f, x), origin);
+                let b_rel = self
+                    .db
+                    .store_checked(CorePrismExpr::FnDestruct(b_rel, fx), origin);
+
+                let r = self
+                    .db
+                    .store_checked(CorePrismExpr::FnType(a_rel, b_rel), origin);
+                let r = self
+                    .db
+                    .store_checked(CorePrismExpr::FnType(a_copy, r), origin);
+                CorePrismExpr::FnConstruct(r)
+            }
+            // The relation of `x => b` is `x => xr => [b]`
+            CorePrismExpr::FnConstruct(b) => {
+                let id = self.new_tc_id();
+                scope.vars.insert(id, (depth, Some(depth + 1)));
+                let b_rel = self.param_rel(b, &s.cons(RType(id)), depth + 2, scope)?;
+                let r = self
+                    .db
+                    .store_checked(CorePrismExpr::FnConstruct(b_rel), origin);
+                CorePrismExpr::FnConstruct(r)
+            }
+            // The relation of `f a` is `[f] a [a]`
+            CorePrismExpr::FnDestruct(f, a) => {
+                let f_rel = self.param_rel(f, &s, depth, scope)?;
+                let a_copy = self.param_copy(a, &s, depth, scope)?;
+                let a_rel = self.param_rel(a, &s, depth, scope)?;
+                let r = self
+                    .db
+                    .store_checked(CorePrismExpr::FnDestruct(f_rel, a_copy), origin);
+                CorePrismExpr::FnDestruct(r, a_rel)
+            }
+            // The relation of `(x: A) * B` is `p => (xr: [A] (fst p)) * [B] (snd p)`, with `x` bound to `fst p`
+            CorePrismExpr::PairType(a, b) => {
+                let a_rel = self.param_rel(a, &s, depth + 1, scope)?;
+                let p = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(0), origin);
+                let fst = self.db.store_checked(CorePrismExpr::PairFst(p), origin);
+                let a_rel = self
+                    .db
+                    .store_checked(CorePrismExpr::FnDestruct(a_rel, fst), origin);
+
+                let id = self.new_tc_id();
+                scope.vars.insert(id, (depth + 2, Some(depth + 1)));
+                let b_rel = self.param_rel(b, &s.cons(RType(id)), depth + 3, scope)?;
+                let p = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(2), origin);
+                let snd = self.db.store_checked(CorePrismExpr::PairSnd(p), origin);
+                let b_rel = self
+                    .db
+                    .store_checked(CorePrismExpr::FnDestruct(b_rel, snd), origin);
+                let p = self
+                    .db
+                    .store_checked(CorePrismExpr::DeBruijnIndex(1), origin);
+                let fst = self.db.store_checked(CorePrismExpr::PairFst(p), origin);
+                let b_rel = self
+                    .db
+                    .store_checked(CorePrismExpr::Let(fst, b_rel), origin);
+
+                let r = self
+                    .db
+                    .store_checked(CorePrismExpr::PairType(a_rel, b_rel), origin);
+                CorePrismExpr::FnConstruct(r)
+            }
+            CorePrismExpr::PairConstruct(a, b) => {
+                let a_rel = self.param_rel(a, &s, depth, scope)?;
+                let b_rel = self.param_rel(b, &s, depth, scope)?;
+                CorePrismExpr::PairConstruct(a_rel, b_rel)
+            }
+            CorePrismExpr::PairFst(p) => {
+                CorePrismExpr::PairFst(self.param_rel(p, &s, depth, scope)?)
+            }
+            CorePrismExpr::PairSnd(p) => {
+                CorePrismExpr::PairSnd(self.param_rel(p, &s, depth, scope)?)
+            }
+            CorePrismExpr::Free => {
+                return Err((t, "This part of the type is not known yet"));
+            }
+            CorePrismExpr::Level
+            | CorePrismExpr::LevelConst(_)
+            | CorePrismExpr::LevelSucc(_)
+            | CorePrismExpr::LevelMax(_, _)
+            | CorePrismExpr::LevelOmega => {
+                return Err((t, "Quantifying over universe levels is not supported"));
+            }
+            CorePrismExpr::GrammarValue(_) | CorePrismExpr::GrammarType => {
+                return Err((t, "Grammars have no relation"));
+            }
+            CorePrismExpr::DataType(_)
+            | CorePrismExpr::DataConstruct(_, _)
+            | CorePrismExpr::DataElim(_) => {
+                return Err((
+                    t,
+                    "Data types are not supported, they already have an eliminator",
+                ));
+            }
+            CorePrismExpr::Paramatricity(_) => {
+                return Err((t, "Free theorems can't be translated again"));
+            }
+            CorePrismExpr::LiteralType(_)
+            | CorePrismExpr::Literal(_)
+            | CorePrismExpr::PrimOp(_) => {
+                return Err((t, "Builtin types have no relation"));
+            }
+            CorePrismExpr::Let(..)
+            | CorePrismExpr::Shift(..)
+            | CorePrismExpr::TypeAssert(..)
+            | CorePrismExpr::Data(..) => unreachable!(),
+        };
+        Ok(self.db.store_checked(e, origin))
+    }
+
+    /// Copies `t` in `s` so it is valid at `depth` in the generated term
+    fn param_copy(
+        &mut self,
+        t: CoreIndex,
+        s: &DbEnv,
+        depth: usize,
+        scope: &mut ParamScope,
+    ) -> Result<CoreIndex, Unsupported> {
+        let (t, s) = self.db.beta_reduce_head(t, s);
+        let origin = scope.origin;
+
+        let e = match self.db.checked_values[*t] {
+            // Values that don't refer to the scope
+            CorePrismExpr::GrammarValue(_)
+            | CorePrismExpr::GrammarType
+            | CorePrismExpr::DataType(_)
+            | CorePrismExpr::DataConstruct(_, _)
+            | CorePrismExpr::DataElim(_)
+            | CorePrismExpr::Level
+            | CorePrismExpr::LevelConst(_)
+            | CorePrismExpr::LevelOmega
+            | CorePrismExpr::LiteralType(_)
+            | CorePrismExpr::Literal(_)
+            | CorePrismExpr::PrimOp(_) => return Ok(t),
+            CorePrismExpr::Type(l) => {
+                CorePrismExpr::Type(self.param_copy_level(l, &s, depth, scope)?)
+            }
+            CorePrismExpr::DeBruijnIndex(v) => {
+                let (value, _) = self.param_var(&s[v], scope);
+                CorePrismExpr::DeBruijnIndex(depth - value - 1)
+            }
+            CorePrismExpr::FnType(a, b) => {
+                let a = self.param_copy(a, &s, depth, scope)?;
+                let id = self.new_tc_id();
+                scope.vars.insert(id, (depth, None));
+                let b = self.param_copy(b, &s.cons(RType(id)), depth + 1, scope)?;
+                CorePrismExpr::FnType(a, b)
+            }
+            CorePrismExpr::FnConstruct(b) => {
+                let id = self.new_tc_id();
+                scope.vars.insert(id, (depth, None));
+                let b = self.param_copy(b, &s.cons(RType(id)), depth + 1, scope)?;
+                CorePrismExpr::FnConstruct(b)
+            }
+            CorePrismExpr::FnDestruct(f, a) => {
+                let f = self.param_copy(f, &s, depth, scope)?;
+                let a = self.param_copy(a, &s, depth, scope)?;
+                CorePrismExpr::FnDestruct(f, a)
+            }
+            CorePrismExpr::PairType(a, b) => {
+                let a = self.param_copy(a, &s, depth, scope)?;
+                let id = self.new_tc_id();
+                scope.vars.insert(id, (depth, None));
+                let b = self.param_copy(b, &s.cons(RType(id)), depth + 1, scope)?;
+                CorePrismExpr::PairType(a, b)
+            }
+            CorePrismExpr::PairConstruct(a, b) => {
+                let a = self.param_copy(a, &s, depth, scope)?;
+                let b = self.param_copy(b, &s, depth, scope)?;
+                CorePrismExpr::PairConstruct(a, b)
+            }
+            CorePrismExpr::PairFst(p) => {
+                CorePrismExpr::PairFst(self.param_copy(p, &s, depth, scope)?)
+            }
+            CorePrismExpr::PairSnd(p) => {
+                CorePrismExpr::PairSnd(self.param_copy(p, &s, depth, scope)?)
+            }
+            CorePrismExpr::LevelSucc(_) | CorePrismExpr::LevelMax(_, _) => {
+                return self.param_copy_level(t, &s, depth, scope);
+            }
+            CorePrismExpr::Paramatricity(x) => {
+                CorePrismExpr::Paramatricity(self.param_copy(x, &s, depth, scope)?)
+            }
+            CorePrismExpr::Free => {
+                return Err((t, "This part of the type is not known yet"));
+            }
+            CorePrismExpr::Let(..)
+            | CorePrismExpr::Shift(..)
+            | CorePrismExpr::TypeAssert(..)
+            | CorePrismExpr::Data(..) => unreachable!(),
+        };
+        Ok(self.db.store_checked(e, origin))
+    }
+
+    /// Copies the level `l` in `s` so it is valid at `depth` in the generated term.
+    /// Levels that are not known yet are inferred again in the generated term.
+    fn param_copy_level(
+        &mut self,
+        l: CoreIndex,
+        s: &DbEnv,
+        depth: usize,
+        scope: &mut ParamScope,
+    ) -> Result<CoreIndex, Unsupported> {
+        let (l, s) = self.db.beta_reduce_head(l, s);
+        let e = match self.db.checked_values[*l] {
+            CorePrismExpr::Free => CorePrismExpr::Free,
+            CorePrismExpr::LevelSucc(a) => {
+                CorePrismExpr::LevelSucc(self.param_copy_level(a, &s, depth, scope)?)
+            }
+            CorePrismExpr::LevelMax(a, b) => {
+                let a = self.param_copy_level(a, &s, depth, scope)?;
+                let b = self.param_copy_level(b, &s, depth, scope)?;
+                CorePrismExpr::LevelMax(a, b)
+            }
+            _ => return self.param_copy(l, &s, depth, scope),
+        };
+        Ok(self.db.store_checked(e, scope.origin))
+    }
+
+    /// Returns where the variable `entry` is bound in the generated term
+    fn param_var(&self, entry: &EnvEntry, scope: &ParamScope) -> (usize, Option<usize>) {
+        let (CType(id, _) | RType(id)) = *entry else {
+            unreachable!("Substituted variables are removed by beta reduction")
+        };
+        scope.vars[&id]
+    }
+}
@@ -0,0 +1,192 @@
+use crate::lang::env::EnvEntry::*;
+use crate::lang::{CoreIndex, CorePrismExpr, PrismDb, ValueOrigin};
+use crate::type_check::TypecheckPrismEnv;
+use prism_diag::sugg::SuggestionArgument;
+use prism_diag_derive::Diagnostic;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// A value that was left to be inferred, with `_` or as an implicit argument, could not be inferred.
+///
+/// For example, nothing determines the value of `_` in:
+///
+/// ```prism
+/// _
+/// ```
+///
+/// Give the value explicitly to fix this.
+#[derive(Diagnostic)]
+#[diag(title = "Unable to infer value", env = PrismDb)]
+pub struct UnsolvedFree {
+    #[sugg(label = "The value here could not be inferred, consider giving it explicitly")]
+    pub free: CoreIndex,
+}
+
+/// The program contains a named hole `?name`, which is reported with the type of the value that should fill it.
+///
+/// Holes can be used to find out which value is needed in a part of a program that is not finished yet.
+/// The names of the variables in scope of the hole are reported with their types. For example:
+///
+/// ```prism
+/// let add: Nat -> Nat -> Nat = n => m => ?body;
+/// add 1 2
+/// ```
+#[derive(Diagnostic)]
+#[diag(title = format!("Found goal `?{}`", self.name), env = PrismDb)]
+pub struct FoundGoal {
+    #[sugg(label = self.goal.clone())]
+    pub hole: CoreIndex,
+    pub name: String,
+    pub goal: String,
+}
+
+impl TypecheckPrismEnv<'_> {
+    /// Reports the holes and the values that could not be inferred in `root`, after `root` has been type checked.
+    /// Values that could not be inferred are only reported if there are no other errors,
+    /// since they are usually caused by those errors.
+    pub(super) fn report_unsolved(&mut self, root: CoreIndex) {
+        let mut unsolved = vec![];
+        self.find_unsolved(root, &mut HashSet::new(), &mut unsolved);
+
+        let has_errors = self.db.has_errors();
+        let mut reported_spans = HashSet::new();
+        for free in unsolved {
+            if self.db.holes.contains_key(&free) {
+                self.report_goal(free);
+                continue;
+            }
+            if has_errors || self.is_failure(free) || self.is_level(free) {
+                continue;
+            }
+            // Multiple values may be inferred for a single expression, such as implicit arguments
+            if reported_spans.insert(free.span(self.db)) {
+                self.db.push_error(UnsolvedFree { free });
+            }
+        }
+    }
+
+    /// Collects the `Free` nodes that are part of `i`, skipping levels
+    fn find_unsolved(
+        &mut self,
+        i: CoreIndex,
+        visited: &mut HashSet<CoreIndex>,
+        unsolved: &mut Vec<CoreIndex>,
+    ) {
+        if !visited.insert(i) {
+            return;
+        }
+        match self.db.checked_values[*i] {
+            CorePrismExpr::Free => unsolved.push(i),
+            CorePrismExpr::Let(a, b)
+            | CorePrismExpr::FnType(a, b)
+            | CorePrismExpr::FnDestruct(a, b)
+            | CorePrismExpr::TypeAssert(a, b)
+            | CorePrismExpr::PairType(a, b)
+            | CorePrismExpr::PairConstruct(a, b) => {
+                self.find_unsolved(a, visited, unsolved);
+                self.find_unsolved(b, visited, unsolved);
+            }
+            CorePrismExpr::FnConstruct(a)
+            | CorePrismExpr::Shift(a, _)
+            | CorePrismExpr::PairFst(a)
+            | CorePrismExpr::PairSnd(a)
+            | CorePrismExpr::Paramatricity(a) => self.find_unsolved(a, visited, unsolved),
+            CorePrismExpr::Data(ref decl, b) => {
+                let decl = decl.clone();
+                self.find_unsolved(decl.typ, visited, unsolved);
+                for c in decl.constructors.iter() {
+                    self.find_unsolved(c.typ, visited, unsolved);
+                }
+                self.find_unsolved(b, visited, unsolved);
+            }
+            // Levels that are not known are left generic
+            CorePrismExpr::Type(_)
+            | CorePrismExpr::DeBruijnIndex(_)
+            | CorePrismExpr::GrammarValue(_)
+            | CorePrismExpr::GrammarType
+            | CorePrismExpr::DataType(_)
+            | CorePrismExpr::DataConstruct(_, _)
+            | CorePrismExpr::DataElim(_)
+            | CorePrismExpr::Level
+            | CorePrismExpr::LevelConst(_)
+            | CorePrismExpr::LevelSucc(_)
+            | CorePrismExpr::LevelMax(_, _)
+            | CorePrismExpr::LevelOmega
+            | CorePrismExpr::LiteralType(_)
+            | CorePrismExpr::Literal(_)
+            | CorePrismExpr::PrimOp(_) => {}
+        }
+    }
+
+    /// Whether `i` was created because type checking failed, in which case an error was already reported
+    fn is_failure(&self, i: CoreIndex) -> bool {
+        let mut origin = self.db.checked_origins[*i];
+        loop {
+            match origin {
+                ValueOrigin::SourceCode(_) => return false,
+                ValueOrigin::TypeOf(j) | ValueOrigin::FreeSub(j) => {
+                    origin = self.db.checked_origins[*j]
+                }
+                ValueOrigin::Failure => return true,
+            }
+        }
+    }
+
+    /// Whether the free value `i` is a level
+    fn is_level(&mut self, i: CoreIndex) -> bool {
+        let Some((env, t)) = self.queued_tc.get(&i).cloned() else {
+            return false;
+        };
+        let (t, _) = self.db.beta_reduce_head(t, &env);
+        matches!(self.db.checked_values[*t], CorePrismExpr::Level)
+    }
+
+    /// Reports the hole `i`, with its expected type and the variables in scope
+    fn report_goal(&mut self, i: CoreIndex) {
+        let hole = &self.db.holes[&i];
+        let name = hole.name.clone();
+        let names = hole.names.clone();
+
+        let Some((env, t)) = self.queued_tc.get(&i).cloned() else {
+            self.db.push_error(FoundGoal {
+                hole: i,
+                name,
+                goal: "The type of this goal is not known".to_string(),
+            });
+            return;
+        };
+        // The names should line up with the env, but don't show wrong names if they don't
+        let names = if names.len() == env.len() {
+            names
+        } else {
+            vec![None; env.len()]
+        };
+
+        let mut goal = format!(
+            "Expected a value of type: {}",
+            self.display_in_env(t, &env, &names)
+        );
+        let locals: Vec<_> = env.iter().map(|(_, e)| e.clone()).collect();
+        let mut context = String::new();
+        for (idx, entry) in locals.into_iter().enumerate() {
+            let depth = env.len() - idx - 1;
+            let Some(local) = &names[depth] else {
+                continue;
+            };
+            let (CType(_, t) | CSubst(_, t)) = entry else {
+                unreachable!()
+            };
+            let t = self.display_in_env(t, &env.shift(idx + 1), &names[..depth]);
+            context = format!("\n    {local}: {t}{context}");
+        }
+        if !context.is_empty() {
+            write!(goal, "\nIn scope:{context}").expect("Writing to String shouldn't fail");
+        }
+
+        self.db.push_error(FoundGoal {
+            hole: i,
+            name,
+            goal,
+        });
+    }
+}
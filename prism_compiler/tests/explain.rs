@@ -0,0 +1,108 @@
+use prism_compiler::explain::{explain, explanations};
+use prism_compiler::lang::PrismDb;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Returns the ids of the diagnostics derived with `#[derive(Diagnostic)]` in the files in `dir`,
+/// which are the structs and enum variants with a `#[diag(title = ...)]` attribute
+fn derived_ids(dir: &Path, ids: &mut Vec<(String, PathBuf)>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            derived_ids(&path, ids);
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+        let mut lines = source.lines().map(str::trim);
+        while let Some(line) = lines.next() {
+            if !line.starts_with("#[diag(") || !line.contains("title = ") {
+                continue;
+            }
+
+            // The struct or variant follows its attributes and doc comment
+            let item = lines
+                .by_ref()
+                .find(|line| !line.starts_with("#[") && !line.starts_with("///"))
+                .unwrap();
+            let item = item
+                .trim_start_matches("pub(crate) ")
+                .trim_start_matches("pub ");
+            let name: String = item
+                .trim_start_matches("struct ")
+                .chars()
+                .take_while(|c| c.is_alphanumeric())
+                .collect();
+
+            // An explicit id is given in the `diag` attribute
+            let id = line
+                .split_once("id = ")
+                .map(|(_, id)| id.chars().take_while(|c| c.is_alphanumeric() || *c == '_'))
+                .map(|id| id.collect())
+                .unwrap_or_else(|| struct_name_to_id(&name));
+            ids.push((id, path.clone()));
+        }
+    }
+}
+
+fn struct_name_to_id(name: &str) -> String {
+    let mut buffer = String::new();
+    for char in name.chars() {
+        if char.is_uppercase() && !buffer.is_empty() {
+            buffer.push('_');
+        }
+        buffer.extend(char.to_lowercase());
+    }
+    buffer
+}
+
+#[test]
+fn every_diagnostic_has_an_explanation() {
+    let mut ids = vec![];
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    derived_ids(&manifest_dir.join("src"), &mut ids);
+    derived_ids(&manifest_dir.join("../prism_parser/src"), &mut ids);
+    assert!(!ids.is_empty());
+
+    for (id, path) in ids {
+        assert!(
+            explain(&id).is_some(),
+            "Diagnostic `{id}` in {path:?} is missing from `EXPLANATIONS`"
+        );
+    }
+
+    let mut seen = HashSet::new();
+    for explanation in explanations() {
+        assert!(
+            seen.insert(explanation.id),
+            "Diagnostic `{}` is explained twice",
+            explanation.id
+        );
+        assert!(
+            !explanation.explanation.is_empty(),
+            "Diagnostic `{}` has no explanation, document it with a doc comment",
+            explanation.id
+        );
+    }
+}
+
+#[test]
+fn examples_report_their_diagnostic() {
+    for explanation in explanations() {
+        let examples = explanation.explanation.split("```prism\n").skip(1);
+        for example in examples {
+            let (example, _) = example.split_once("```").unwrap();
+
+            let mut db = PrismDb::default();
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("example.pr");
+            let file = db.load_input(example.to_string(), path);
+            db.process_file(file);
+
+            assert!(
+                db.diags.iter().any(|diag| diag.id == explanation.id),
+                "The example of `{}` does not report it:\n{example}",
+                explanation.id
+            );
+        }
+    }
+}
@@ -3,10 +3,13 @@ use libtest_mimic::{Arguments, Failed, Trial};
 use prism_compiler::lang::env::DbEnv;
 use prism_compiler::lang::{CoreIndex, PrismDb};
 use prism_diag::RenderConfig;
+use prism_input::input_table::InputTableIndex;
+use prism_input::span::Span;
 use std::collections::VecDeque;
 use std::convert::Into;
 use std::env::args;
 use std::fmt::Write;
+use std::iter;
 use std::iter::Iterator;
 use std::mem;
 use std::path::{Path, PathBuf};
@@ -29,20 +32,12 @@ pub struct UitestArguments {
 fn run_uitest(file_path: &Path, args: &UitestArguments) -> Result<(), Failed> {
     let mut env = PrismDb::default();
 
-    let input = env.load_file(file_path.into()).unwrap();
-    let (input, _) = env.parse_prism_file(input);
-    let typ = env.type_check(input);
+    let file = env.load_file(file_path.into()).unwrap();
+    let processed_file = env.process_file(file);
+    let (input, typ) = (processed_file.core, processed_file.typ);
 
     // Compare stderr
-    let mut stderr = String::new();
-    for diag in mem::take(&mut env.diags) {
-        writeln!(
-            &mut stderr,
-            "{}\n",
-            diag.render(&RenderConfig::uitest(), &env.input.inner())
-        )
-        .unwrap();
-    }
+    let stderr = render_diags(&mut env);
     compare_output(
         file_path,
         stderr.as_bytes(),
@@ -51,7 +46,7 @@ fn run_uitest(file_path: &Path, args: &UitestArguments) -> Result<(), Failed> {
         args,
     )?;
     if !stderr.is_empty() {
-        return Ok(());
+        return check_edits(&mut env, file);
     }
 
     compare_term(file_path, &mut env, typ, "type", args)?;
@@ -59,9 +54,128 @@ fn run_uitest(file_path: &Path, args: &UitestArguments) -> Result<(), Failed> {
     let eval = env.beta_reduce(input, &DbEnv::default());
     compare_term(file_path, &mut env, eval, "eval", args)?;
 
+    // The evaluator should find the same normal form as reducing by substitution
+    let reference = env.beta_reduce_by_substitution(input, &DbEnv::default());
+    let (eval_str, reference_str) = (env.index_to_string(eval), env.index_to_string(reference));
+    if eval_str != reference_str {
+        return Err(format!(
+            "The evaluator differed from reducing by substitution. \n\n-- Evaluator:\n{eval_str}\n-- Substitution:\n{reference_str}"
+        )
+        .into());
+    }
+
+    // Conversion checking compares the evaluated values, so it should not build new terms
+    let len = env.checked_values.len();
+    if !env.is_beta_equal(input, &DbEnv::default(), eval, &DbEnv::default()) {
+        return Err("The program is not equal to its normal form".into());
+    }
+    if env.checked_values.len() != len {
+        return Err(format!(
+            "Comparing the program with its normal form built {} new terms",
+            env.checked_values.len() - len
+        )
+        .into());
+    }
+
+    // Collecting garbage should not change the processed file
+    let before = (
+        without_free_indices(&env.index_to_string(typ)),
+        without_free_indices(&eval_str),
+    );
+    env.collect_garbage();
+    let processed_file = env.process_file(file);
+    let eval = env.beta_reduce(processed_file.core, &DbEnv::default());
+    let after = (
+        without_free_indices(&env.index_to_string(processed_file.typ)),
+        without_free_indices(&env.index_to_string(eval)),
+    );
+    if before != after {
+        return Err(format!(
+            "The processed file changed after collecting garbage. \n\n-- Before:\n{}\n{}\n-- After:\n{}\n{}",
+            before.0, before.1, after.0, after.1
+        )
+        .into());
+    }
+
+    check_edits(&mut env, file)
+}
+
+fn render_diags(env: &mut PrismDb) -> String {
+    let mut stderr = String::new();
+    for diag in mem::take(&mut env.diags) {
+        writeln!(
+            &mut stderr,
+            "{}\n",
+            diag.render(&RenderConfig::uitest(), &env.input.inner())
+        )
+        .unwrap();
+    }
+    stderr
+}
+
+/// Processes `file` in `env`, returning its diagnostics and its type
+fn process_result(env: &mut PrismDb, file: InputTableIndex) -> (String, String) {
+    let typ = env.process_file(file).typ;
+    let stderr = render_diags(env);
+    (
+        without_free_indices(&stderr),
+        without_free_indices(&env.index_to_string(typ)),
+    )
+}
+
+/// Inserts an empty line in the middle of the file and removes it again.
+/// After each edit, processing the file should give the same result as when the edited text is parsed from scratch,
+/// even though the parse of the file before the edit is reused.
+fn check_edits(env: &mut PrismDb, file: InputTableIndex) -> Result<(), Failed> {
+    let text = env.input.inner().get_str(file).to_string();
+    let line_starts: Vec<usize> = iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let start = line_starts[line_starts.len() / 2];
+    let start_pos = env.input.inner().start_of(file) + start;
+
+    let inserted = format!("{}\n{}", &text[..start], &text[start..]);
+    for (span, new_text, expected_text) in [
+        (Span::new(start_pos, 0), "\n", &inserted),
+        (Span::new(start_pos, 1), "", &text),
+    ] {
+        env.edit_file(span, new_text);
+        let result = process_result(env, file);
+
+        env.update_file(file, expected_text.clone());
+        let expected = process_result(env, file);
+        env.collect_garbage();
+        if result != expected {
+            return Err(format!(
+                "Processing the file after an edit differed from parsing the edited file from scratch. \n\n-- Edited file:\n{expected_text}\n-- Expected:\n{}\n{}\n-- Actual:\n{}\n{}",
+                expected.0, expected.1, result.0, result.1
+            )
+            .into());
+        }
+    }
     Ok(())
 }
 
+/// Replaces the indices of free variables in a displayed term `{123}` by `{_}`, since these change when collecting garbage
+fn without_free_indices(s: &str) -> String {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('{') {
+        let (before, after) = rest.split_at(start + 1);
+        result.push_str(before);
+        let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        // Levels are displayed as `Type.{1}`
+        if digits > 0 && after[digits..].starts_with('}') && !before.ends_with(".{") {
+            result.push('_');
+            rest = &after[digits..];
+        } else {
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
 fn compare_term(
     file_path: &Path,
     env: &mut PrismDb,
@@ -78,7 +192,7 @@ fn compare_term(
                 String::from_utf8_lossy(expected).to_string(),
                 file_path.with_added_extension(output_ext),
             );
-            let (expected, _) = env.parse_prism_file(expected);
+            let (_, expected, _, _) = env.parse_prism_file(expected);
             env.assert_no_errors();
             env.is_beta_equal(term, &DbEnv::default(), expected, &DbEnv::default())
         },
@@ -0,0 +1,12 @@
+/// The long-form documentation of a diagnostic, which is shown by `prism explain <id>`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Explanation {
+    pub id: &'static str,
+    pub explanation: &'static str,
+}
+
+/// A diagnostic that has an explanation, or an enum of diagnostics that each have one.
+/// `#[derive(Diagnostic)]` implements this using the doc comment of the struct or of each variant.
+pub trait Explain {
+    const EXPLANATIONS: &'static [Explanation];
+}
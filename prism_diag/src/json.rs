@@ -0,0 +1,129 @@
+use crate::sugg::Applicability;
+use crate::{AnnotationKind, Diag, Level};
+use prism_input::input_table::InputTableInner;
+use prism_input::pos::Pos;
+use serde::{Deserialize, Serialize};
+
+/// A `Diag` in the schema of `--error-format json`, where each diagnostic is printed as a JSON object on its own line
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct JsonDiag {
+    pub level: Level,
+    pub title: String,
+    pub id: String,
+    pub groups: Vec<JsonAnnotationGroup>,
+    pub footers: Vec<JsonFooter>,
+    pub suggestions: Vec<JsonSuggestion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct JsonAnnotationGroup {
+    pub annotations: Vec<JsonAnnotation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct JsonAnnotation {
+    /// The path of the file the annotation is in
+    pub file: String,
+    pub start: JsonPos,
+    pub end: JsonPos,
+    pub label: Option<String>,
+    pub kind: AnnotationKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct JsonFooter {
+    pub level: Level,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct JsonSuggestion {
+    pub message: String,
+    /// The path of the file the replaced span is in
+    pub file: String,
+    pub start: JsonPos,
+    pub end: JsonPos,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct JsonPos {
+    /// The offset in bytes from the start of the file
+    pub byte: usize,
+    /// The line, starting at 1
+    pub line: usize,
+    /// The offset in bytes from the start of the line, starting at 1
+    pub column: usize,
+}
+
+impl JsonPos {
+    pub fn new(pos: Pos, input: &InputTableInner) -> Self {
+        let (line, column) = input.line_col_of(pos);
+        Self {
+            byte: pos.idx_in_file(),
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+}
+
+impl JsonDiag {
+    pub fn new(diag: &Diag, input: &InputTableInner) -> Self {
+        Self {
+            level: diag.level,
+            title: diag.title.clone(),
+            id: diag.id.clone(),
+            groups: diag
+                .groups
+                .iter()
+                .map(|group| JsonAnnotationGroup {
+                    annotations: group
+                        .annotations
+                        .iter()
+                        .map(|anno| JsonAnnotation {
+                            file: input
+                                .get_path(anno.span.start_pos().file())
+                                .to_string_lossy()
+                                .into_owned(),
+                            start: JsonPos::new(anno.span.start_pos(), input),
+                            end: JsonPos::new(anno.span.end_pos(), input),
+                            label: anno.label.clone(),
+                            kind: anno.kind,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            footers: diag
+                .footers
+                .iter()
+                .map(|footer| JsonFooter {
+                    level: footer.level,
+                    message: footer.message.clone(),
+                })
+                .collect(),
+            suggestions: diag
+                .suggestions
+                .iter()
+                .map(|sugg| JsonSuggestion {
+                    message: sugg.message.clone(),
+                    file: input
+                        .get_path(sugg.span.start_pos().file())
+                        .to_string_lossy()
+                        .into_owned(),
+                    start: JsonPos::new(sugg.span.start_pos(), input),
+                    end: JsonPos::new(sugg.span.end_pos(), input),
+                    replacement: sugg.replacement.clone(),
+                    applicability: sugg.applicability,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Diag {
+    /// Renders this diagnostic as a single line of JSON, see `JsonDiag` for the schema
+    pub fn render_json(&self, input: &InputTableInner) -> String {
+        serde_json::to_string(&JsonDiag::new(self, input)).expect("Diagnostics can be serialized")
+    }
+}
@@ -1,16 +1,56 @@
+pub mod explain;
+pub mod json;
+pub mod sarif;
 pub mod sugg;
 
-use annotate_snippets::level::ERROR;
+use crate::sugg::Suggestion;
 use annotate_snippets::renderer::DecorStyle;
-use annotate_snippets::{AnnotationKind, Group, Renderer, Snippet};
+use annotate_snippets::{Group, Patch, Renderer, Snippet};
 use prism_input::input_table::InputTableInner;
 use prism_input::span::Span;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
 pub struct Diag {
-    // pub level: Level<'static>,
+    pub level: Level,
     pub title: String,
     pub id: String,
     pub groups: Vec<AnnotationGroup>,
+    /// The `note:` and `help:` messages shown below the annotated source
+    pub footers: Vec<Footer>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+            Level::Note => write!(f, "note"),
+            Level::Help => write!(f, "help"),
+        }
+    }
+}
+
+impl Level {
+    fn to_annotate_snippets(self) -> annotate_snippets::Level<'static> {
+        match self {
+            Level::Error => annotate_snippets::Level::ERROR,
+            Level::Warning => annotate_snippets::Level::WARNING,
+            Level::Note => annotate_snippets::Level::NOTE,
+            Level::Help => annotate_snippets::Level::HELP,
+        }
+    }
 }
 
 pub struct AnnotationGroup {
@@ -20,6 +60,22 @@ pub struct AnnotationGroup {
 pub struct Annotation {
     pub span: Span,
     pub label: Option<String>,
+    pub kind: AnnotationKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationKind {
+    /// The location the diagnostic is about
+    #[default]
+    Primary,
+    /// A location that gives context to the primary annotations
+    Secondary,
+}
+
+pub struct Footer {
+    pub level: Level,
+    pub message: String,
 }
 
 #[derive(Copy, Clone, Default)]
@@ -43,9 +99,17 @@ impl RenderConfig {
 }
 
 impl Diag {
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+
     pub fn render(&self, config: &RenderConfig, input: &InputTableInner) -> String {
-        let mut diag: Group =
-            Group::with_title(ERROR.clone().primary_title(&self.title).id(&self.id));
+        let mut diag: Group = Group::with_title(
+            self.level
+                .to_annotate_snippets()
+                .primary_title(&self.title)
+                .id(&self.id),
+        );
 
         for group in &self.groups {
             let file = group.annotations[0].span.start_pos().file();
@@ -53,23 +117,48 @@ impl Diag {
                 .path(Some(input.get_path(file).to_string_lossy()));
 
             for anno in &group.annotations {
+                let kind = match anno.kind {
+                    AnnotationKind::Primary => annotate_snippets::AnnotationKind::Primary,
+                    AnnotationKind::Secondary => annotate_snippets::AnnotationKind::Context,
+                };
                 snippet = snippet.annotation(
-                    AnnotationKind::Primary
-                        .span(
-                            anno.span.start_pos().idx_in_file()..anno.span.end_pos().idx_in_file(),
-                        )
-                        .label(anno.label.clone()),
+                    kind.span(
+                        anno.span.start_pos().idx_in_file()..anno.span.end_pos().idx_in_file(),
+                    )
+                    .label(anno.label.clone()),
                 )
             }
 
             diag = diag.element(snippet);
         }
 
+        for footer in &self.footers {
+            diag = diag.element(footer.level.to_annotate_snippets().message(&footer.message));
+        }
+
+        let mut report = vec![diag];
+        for sugg in &self.suggestions {
+            let file = sugg.span.start_pos().file();
+            report.push(
+                annotate_snippets::Level::HELP
+                    .secondary_title(&sugg.message)
+                    .element(
+                        Snippet::source(input.get_str(file))
+                            .path(Some(input.get_path(file).to_string_lossy()))
+                            .patch(Patch::new(
+                                sugg.span.start_pos().idx_in_file()
+                                    ..sugg.span.end_pos().idx_in_file(),
+                                &sugg.replacement,
+                            )),
+                    ),
+            );
+        }
+
         let renderer = match config.format {
             RenderFormat::Styled => Renderer::styled().decor_style(DecorStyle::Unicode),
             RenderFormat::Plain => Renderer::plain().decor_style(DecorStyle::Ascii),
         };
-        renderer.render(&[diag])
+        renderer.render(&report)
     }
 }
 
@@ -98,15 +187,26 @@ mod tests {
         let span = Span::new(input_table.inner().start_of(file) + 6, 5);
 
         let diag = Diag {
-            // level: Level::ERROR,
+            level: Level::Error,
             title: "Something is badd".to_string(),
             id: "baddy".to_string(),
             groups: vec![AnnotationGroup {
                 annotations: vec![Annotation {
                     span,
                     label: Some("This is wrong".to_string()),
+                    kind: AnnotationKind::Primary,
                 }],
             }],
+            footers: vec![Footer {
+                level: Level::Help,
+                message: "Try being less badd".to_string(),
+            }],
+            suggestions: vec![Suggestion {
+                message: "Replace with `goody`".to_string(),
+                span,
+                replacement: "goody".to_string(),
+                applicability: sugg::Applicability::MaybeIncorrect,
+            }],
         };
 
         eprintln!(
@@ -114,4 +214,82 @@ mod tests {
             diag.render(&RenderConfig::default(), &input_table.inner())
         )
     }
+
+    #[test]
+    fn test_json() {
+        let input_table = InputTable::default();
+        let file = input_table
+            .inner_mut()
+            .get_or_push_file("Helpy\nhelpy helpy".to_string(), "prism.rs".into());
+        let span = Span::new(input_table.inner().start_of(file) + 12, 5);
+
+        let diag = Diag {
+            level: Level::Warning,
+            title: "Something is badd".to_string(),
+            id: "baddy".to_string(),
+            groups: vec![AnnotationGroup {
+                annotations: vec![Annotation {
+                    span,
+                    label: Some("This is wrong".to_string()),
+                    kind: AnnotationKind::Primary,
+                }],
+            }],
+            footers: vec![],
+            suggestions: vec![],
+        };
+
+        assert_eq!(
+            diag.render_json(&input_table.inner()),
+            r#"{"level":"warning","title":"Something is badd","id":"baddy","groups":[{"annotations":[{"file":"prism.rs","start":{"byte":12,"line":2,"column":7},"end":{"byte":17,"line":2,"column":12},"label":"This is wrong","kind":"primary"}]}],"footers":[],"suggestions":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_sarif() {
+        let input_table = InputTable::default();
+        let file = input_table
+            .inner_mut()
+            .get_or_push_file("Helpy\nhelpy helpy".to_string(), "<stdin>".into());
+        let span = Span::new(input_table.inner().start_of(file) + 12, 5);
+        let other_span = Span::new(input_table.inner().start_of(file), 5);
+
+        let diag = Diag {
+            level: Level::Error,
+            title: "Something is badd".to_string(),
+            id: "baddy".to_string(),
+            groups: vec![AnnotationGroup {
+                annotations: vec![
+                    Annotation {
+                        span: other_span,
+                        label: Some("Because of this".to_string()),
+                        kind: AnnotationKind::Secondary,
+                    },
+                    Annotation {
+                        span,
+                        label: Some("This is wrong".to_string()),
+                        kind: AnnotationKind::Primary,
+                    },
+                ],
+            }],
+            footers: vec![],
+            suggestions: vec![],
+        };
+
+        let log = sarif::SarifLog::new("prism", [&diag], &input_table.inner());
+        let result = &log.runs[0].results[0];
+        // The primary annotation is the location, and is not repeated in the related locations
+        let labels = |locations: &[sarif::SarifLocation]| -> Vec<_> {
+            locations
+                .iter()
+                .map(|l| l.message.as_ref().unwrap().text.clone())
+                .collect()
+        };
+        assert_eq!(labels(&result.locations), ["This is wrong"]);
+        assert_eq!(labels(&result.related_locations), ["Because of this"]);
+
+        // Programs that are not read from a file have no uri
+        let artifact = &result.locations[0].physical_location.artifact_location;
+        assert_eq!(artifact.uri, None);
+        assert!(!log.render().contains("\"uri\""));
+    }
 }
@@ -0,0 +1,241 @@
+use crate::{AnnotationKind, Diag, Level};
+use prism_input::input_table::InputTableInner;
+use prism_input::pos::Pos;
+use prism_input::span::Span;
+use serde::{Deserialize, Serialize};
+
+/// A SARIF 2.1.0 log, containing a single run of a tool
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: String,
+    pub version: String,
+    pub runs: Vec<SarifRun>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SarifRun {
+    pub tool: SarifTool,
+    pub results: Vec<SarifResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SarifTool {
+    pub driver: SarifDriver,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SarifDriver {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    pub rule_id: String,
+    pub level: String,
+    pub message: SarifMessage,
+    /// The first primary annotation of the diagnostic
+    pub locations: Vec<SarifLocation>,
+    /// The other annotations of the diagnostic
+    pub related_locations: Vec<SarifLocation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<SarifFix>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifFix {
+    pub description: SarifMessage,
+    pub artifact_changes: Vec<SarifArtifactChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifArtifactChange {
+    pub artifact_location: SarifArtifactLocation,
+    pub replacements: Vec<SarifReplacement>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifReplacement {
+    pub deleted_region: SarifRegion,
+    pub inserted_content: SarifMessage,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SarifMessage {
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifLocation {
+    pub physical_location: SarifPhysicalLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<SarifMessage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifPhysicalLocation {
+    pub artifact_location: SarifArtifactLocation,
+    pub region: SarifRegion,
+}
+
+/// The file of a location. Programs that are not read from a file, such as `<stdin>`, have no uri,
+/// their path is given as the description instead
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SarifArtifactLocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<SarifMessage>,
+}
+
+impl SarifArtifactLocation {
+    /// The location of the file that `span` is in
+    pub fn new(span: Span, input: &InputTableInner) -> Self {
+        let path = input.get_path(span.start_pos().file());
+        let path_str = path.to_string_lossy().into_owned();
+        // Programs that are not read from a file have a path of the form `<name>`
+        let is_file = !path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('<') && name.ends_with('>'));
+        if is_file {
+            Self {
+                uri: Some(path_str),
+                description: None,
+            }
+        } else {
+            Self {
+                uri: None,
+                description: Some(SarifMessage { text: path_str }),
+            }
+        }
+    }
+}
+
+/// A region of a file, lines and columns start at 1 and columns are counted in UTF-16 code units
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRegion {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub byte_offset: usize,
+    pub byte_length: usize,
+}
+
+impl SarifRegion {
+    pub fn new(span: Span, input: &InputTableInner) -> Self {
+        let (start_line, start_column) = Self::line_col(span.start_pos(), input);
+        let (end_line, end_column) = Self::line_col(span.end_pos(), input);
+        Self {
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+            byte_offset: span.start_pos().idx_in_file(),
+            byte_length: span.len(),
+        }
+    }
+
+    fn line_col(pos: Pos, input: &InputTableInner) -> (usize, usize) {
+        let (line, col) = input.line_col_of(pos);
+        let line_start = pos.idx_in_file() - col;
+        let col = input.get_str(pos.file())[line_start..pos.idx_in_file()]
+            .encode_utf16()
+            .count();
+        (line + 1, col + 1)
+    }
+}
+
+impl SarifLog {
+    /// Creates a log of the diagnostics reported by the tool `tool`
+    pub fn new<'a>(
+        tool: &str,
+        diags: impl IntoIterator<Item = &'a Diag>,
+        input: &InputTableInner,
+    ) -> Self {
+        let results = diags
+            .into_iter()
+            .map(|diag| {
+                let annotations: Vec<_> = diag
+                    .groups
+                    .iter()
+                    .flat_map(|group| &group.annotations)
+                    .collect();
+                let primary = annotations
+                    .iter()
+                    .position(|anno| anno.kind == AnnotationKind::Primary);
+                let mut locations: Vec<_> = annotations
+                    .iter()
+                    .map(|anno| SarifLocation {
+                        physical_location: SarifPhysicalLocation {
+                            artifact_location: SarifArtifactLocation::new(anno.span, input),
+                            region: SarifRegion::new(anno.span, input),
+                        },
+                        message: anno.label.clone().map(|text| SarifMessage { text }),
+                    })
+                    .collect();
+                let primary = primary.map(|i| locations.remove(i));
+                // SARIF has no footers, so they are added to the message
+                let mut text = diag.title.clone();
+                for footer in &diag.footers {
+                    text.push_str(&format!("\n{}: {}", footer.level, footer.message));
+                }
+                SarifResult {
+                    rule_id: diag.id.clone(),
+                    level: match diag.level {
+                        Level::Error => "error",
+                        Level::Warning => "warning",
+                        Level::Note | Level::Help => "note",
+                    }
+                    .to_string(),
+                    message: SarifMessage { text },
+                    locations: primary.into_iter().collect(),
+                    related_locations: locations,
+                    fixes: diag
+                        .suggestions
+                        .iter()
+                        .map(|sugg| SarifFix {
+                            description: SarifMessage {
+                                text: sugg.message.clone(),
+                            },
+                            artifact_changes: vec![SarifArtifactChange {
+                                artifact_location: SarifArtifactLocation::new(sugg.span, input),
+                                replacements: vec![SarifReplacement {
+                                    deleted_region: SarifRegion::new(sugg.span, input),
+                                    inserted_content: SarifMessage {
+                                        text: sugg.replacement.clone(),
+                                    },
+                                }],
+                            }],
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Self {
+            schema: "https://json.schemastore.org/sarif-2.1.0.json".to_string(),
+            version: "2.1.0".to_string(),
+            runs: vec![SarifRun {
+                tool: SarifTool {
+                    driver: SarifDriver {
+                        name: tool.to_string(),
+                    },
+                },
+                results,
+            }],
+        }
+    }
+
+    pub fn render(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Diagnostics can be serialized")
+    }
+}
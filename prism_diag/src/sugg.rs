@@ -1,4 +1,5 @@
 use prism_input::span::Span;
+use serde::{Deserialize, Serialize};
 
 pub trait SuggestionArgument<Env> {
     fn span(&self, env: &Env) -> Span;
@@ -9,3 +10,23 @@ impl<Env> SuggestionArgument<Env> for Span {
         *self
     }
 }
+
+/// A change to the source code that would fix a diagnostic, which is shown as a patch below it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Suggestion {
+    /// What the change does, such as ``replace with `x` ``
+    pub message: String,
+    /// The span of the source code that is replaced
+    pub span: Span,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Applicability {
+    /// The suggestion is certainly what the user intended, so it can be applied automatically with `--fix`
+    MachineApplicable,
+    /// The suggestion may not be what the user intended, so it should be reviewed before it is applied
+    MaybeIncorrect,
+}
@@ -1,81 +1,320 @@
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream};
-use syn::{Data, Expr, Fields, Ident, Meta, Token, Type};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, Expr, ExprLit, Fields, GenericArgument, Ident, Index, Lit, Member, Meta,
+    PathArguments, Token, Type,
+};
 use synstructure::{Structure, decl_derive};
 
-decl_derive!([Diagnostic, attributes(diag, sugg)] => derive_diagnostic);
+decl_derive!([Diagnostic, attributes(diag, sugg, note, help, fix)] => derive_diagnostic);
 
 fn derive_diagnostic(s: Structure<'_>) -> proc_macro::TokenStream {
-    let Data::Struct(data) = &s.ast().data else {
-        panic!("Expected struct")
-    };
-    let attr = s
-        .ast()
-        .attrs
-        .iter()
-        .find(|attr| attr.path().get_ident().unwrap().to_string().as_str() == "diag")
-        .expect("Expected diag attr");
-    let DiagArgs { title, id, env } = attr.parse_args().unwrap();
+    match expand(&s) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
 
-    let Fields::Named(named) = &data.fields else {
-        panic!("Expected named fields")
-    };
+fn expand(s: &Structure<'_>) -> syn::Result<TokenStream> {
+    let ast = s.ast();
+    let name = &ast.ident;
+
+    let (env, explanations, body) = match &ast.data {
+        Data::Struct(data) => {
+            let args = diag_args(&ast.attrs, name)?;
+            let env = args.env.clone();
+            let explanation = explanation(&ast.attrs, &args);
+            let diag = diag(
+                &ast.attrs,
+                args,
+                &data.fields,
+                &env,
+                |member| quote!(self.#member),
+            )?;
+            (env, vec![explanation], diag)
+        }
+        Data::Enum(data) => {
+            // The env is given on the enum, since all variants are converted in the same env
+            let env = match find_attr(&ast.attrs, "diag") {
+                Some(attr) => {
+                    let args: DiagArgs = attr.parse_args()?;
+                    if let Some(title) = &args.title {
+                        return Err(syn::Error::new_spanned(
+                            title,
+                            "The title of a diagnostic should be given on each variant",
+                        ));
+                    }
+                    args.env
+                }
+                None => None,
+            };
 
-    let struct_name = &s.ast().ident;
-    let diag_id = id.unwrap_or(struct_name_to_id(&struct_name.to_string()));
+            let mut explanations = vec![];
+            let mut arms = vec![];
+            for variant in &data.variants {
+                let args = diag_args(&variant.attrs, &variant.ident)?;
+                if let Some(variant_env) = &args.env {
+                    return Err(syn::Error::new_spanned(
+                        variant_env,
+                        "The env of a diagnostic should be given on the enum",
+                    ));
+                }
+                explanations.push(explanation(&variant.attrs, &args));
+
+                // The fields are bound by reference, tuple fields are bound as `field_0`, `field_1`, ...
+                let bindings: Vec<Ident> = variant
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| match &field.ident {
+                        Some(ident) => ident.clone(),
+                        None => format_ident!("field_{i}"),
+                    })
+                    .collect();
+                let variant_ident = &variant.ident;
+                let pattern = match &variant.fields {
+                    Fields::Named(_) => quote!(Self::#variant_ident { #(#bindings),* }),
+                    Fields::Unnamed(_) => quote!(Self::#variant_ident ( #(#bindings),* )),
+                    Fields::Unit => quote!(Self::#variant_ident),
+                };
+                let diag = diag(&variant.attrs, args, &variant.fields, &env, |member| {
+                    let binding = match member {
+                        Member::Named(ident) => ident.clone(),
+                        Member::Unnamed(index) => format_ident!("field_{}", index.index),
+                    };
+                    quote!((*#binding))
+                })?;
+                arms.push(quote! {
+                    #[allow(unused_variables)]
+                    #pattern => #diag
+                });
+            }
+            (env, explanations, quote!(match &self { #(#arms),* }))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "Diagnostics can't be derived for unions",
+            ));
+        }
+    };
 
     let (env_param, env_generic) = match env {
         Some(env) => (None, quote!(#env)),
         None => (Some(quote!(__Env)), quote!(__Env)),
     };
 
+    Ok(quote! {
+        impl ::prism_diag::explain::Explain for #name {
+            const EXPLANATIONS: &'static [::prism_diag::explain::Explanation] = &[
+                #(#explanations),*
+            ];
+        }
+
+        impl<#env_param> ::prism_diag::IntoDiag<#env_generic> for #name {
+            fn into_diag(self, env: &mut #env_generic) -> ::prism_diag::Diag {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the expression that converts a struct or a variant of an enum to a `Diag`,
+/// where `access` returns the expression of a field
+fn diag(
+    attrs: &[Attribute],
+    args: DiagArgs,
+    fields: &Fields,
+    env: &Option<Type>,
+    access: impl Fn(&Member) -> TokenStream,
+) -> syn::Result<TokenStream> {
+    let env_generic = match env {
+        Some(env) => quote!(#env),
+        None => quote!(__Env),
+    };
+    let title = args.title.expect("Checked by `diag_args`");
+    let id = args.id.expect("Set by `diag_args`").to_string();
+    let level = match &args.level {
+        None => quote!(::prism_diag::Level::Error),
+        Some(level) => match level.to_string().as_str() {
+            "error" => quote!(::prism_diag::Level::Error),
+            "warning" => quote!(::prism_diag::Level::Warning),
+            "note" => quote!(::prism_diag::Level::Note),
+            "help" => quote!(::prism_diag::Level::Help),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    level,
+                    "Unknown level, expected one of `error`, `warning`, `note` or `help`",
+                ));
+            }
+        },
+    };
+
+    let mut footers: Vec<TokenStream> = vec![];
+    for attr in attrs {
+        let footer_level = if attr.path().is_ident("note") {
+            quote!(::prism_diag::Level::Note)
+        } else if attr.path().is_ident("help") {
+            quote!(::prism_diag::Level::Help)
+        } else {
+            continue;
+        };
+        let message = attr.parse_args::<Expr>()?;
+        footers.push(quote! {
+            ::prism_diag::Footer {
+                level: #footer_level,
+                message: #message.to_string(),
+            }
+        });
+    }
+
     let mut groups: Vec<TokenStream> = vec![];
-    for field in &named.named {
-        if let Some(field_args) = field
-            .attrs
-            .iter()
-            .find(|attr| attr.path().get_ident().unwrap().to_string().as_str() == "sugg")
-        {
-            let field_ident = field.ident.as_ref().unwrap();
-            let sugg_args = if let Meta::List(..) = field_args.meta {
-                field_args.parse_args::<SuggArgs>().unwrap()
-            } else {
-                SuggArgs::default()
-            };
+    let mut suggestions: Vec<TokenStream> = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index {
+                index: i as u32,
+                span: field.span(),
+            }),
+        };
+        let value = &access(&member);
 
-            let label = match sugg_args.label {
-                Some(label) => quote!(Some(#label.to_string())),
-                None => quote!(None),
-            };
+        // Fields with `#[fix]` contain the suggestions, such as an `Option<Suggestion>`
+        if find_attr(&field.attrs, "fix").is_some() {
+            suggestions.push(quote! {
+                ::std::iter::IntoIterator::into_iter(::std::clone::Clone::clone(&#value))
+            });
+        }
 
-            groups.push(quote!{
-                ::prism_diag::AnnotationGroup {
+        let Some(sugg_attr) = find_attr(&field.attrs, "sugg") else {
+            continue;
+        };
+        let sugg_args = if let Meta::List(..) = sugg_attr.meta {
+            sugg_attr.parse_args::<SuggArgs>()?
+        } else {
+            SuggArgs::default()
+        };
+
+        let label = match sugg_args.label {
+            Some(label) => quote!(Some(#label.to_string())),
+            None => quote!(None),
+        };
+        let kind = if sugg_args.secondary {
+            quote!(::prism_diag::AnnotationKind::Secondary)
+        } else {
+            quote!(::prism_diag::AnnotationKind::Primary)
+        };
+        let group = |arg: TokenStream| {
+            quote! {
+                groups.push(::prism_diag::AnnotationGroup {
                     annotations: vec![
                         ::prism_diag::Annotation {
-                            span: <_ as prism_diag::sugg::SuggestionArgument<#env_generic>>::span(&self.#field_ident, env),
-                            label: #label
+                            span: <_ as ::prism_diag::sugg::SuggestionArgument<#env_generic>>::span(#arg, env),
+                            label: #label,
+                            kind: #kind,
                         }
                     ]
+                });
+            }
+        };
+
+        // An `Option` or `Vec` field is annotated once for each value it contains
+        groups.push(if is_repeated(&field.ty) {
+            let group = group(quote!(arg));
+            quote! {
+                for arg in ::std::iter::IntoIterator::into_iter(&#value) {
+                    #group
                 }
-            })
+            }
+        } else {
+            group(quote!(&#value))
+        });
+    }
+
+    Ok(quote! {{
+        let title = #title.to_string();
+        let mut groups = ::std::vec::Vec::new();
+        #(#groups)*
+        ::prism_diag::Diag {
+            level: #level,
+            title,
+            id: #id.to_string(),
+            groups,
+            footers: vec![
+                #(#footers),*
+            ],
+            suggestions: ::std::iter::empty()
+                #(.chain(#suggestions))*
+                .collect(),
         }
+    }})
+}
+
+/// Whether `typ` is an `Option<T>` or a `Vec<T>`
+fn is_repeated(typ: &Type) -> bool {
+    let Type::Path(path) = typ else {
+        return false;
+    };
+    let Some(last) = path.path.segments.last() else {
+        return false;
+    };
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return false;
+    };
+    (last.ident == "Option" || last.ident == "Vec")
+        && matches!(args.args.first(), Some(GenericArgument::Type(_)))
+}
+
+fn find_attr<'a>(attrs: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attrs.iter().find(|attr| attr.path().is_ident(name))
+}
+
+/// Parses the `#[diag(...)]` attribute of the struct or variant `name`, which should have a title
+fn diag_args(attrs: &[Attribute], name: &Ident) -> syn::Result<DiagArgs> {
+    let Some(attr) = find_attr(attrs, "diag") else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "Expected a `#[diag(title = ...)]` attribute",
+        ));
+    };
+    let mut args: DiagArgs = attr.parse_args()?;
+    if args.title.is_none() {
+        return Err(syn::Error::new_spanned(attr, "Expected a `title` argument"));
     }
+    args.id
+        .get_or_insert_with(|| Ident::new(&struct_name_to_id(&name.to_string()), name.span()));
+    Ok(args)
+}
+
+/// The doc comment of the struct or variant is the explanation of the diagnostic
+fn explanation(attrs: &[Attribute], args: &DiagArgs) -> TokenStream {
+    let explanation = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(line),
+                    ..
+                }) => Some(line.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').unwrap_or(&line).to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let explanation = explanation.trim();
+    let id = args.id.as_ref().expect("Set by `diag_args`").to_string();
 
     quote! {
-        impl<#env_param> ::prism_diag::IntoDiag<#env_generic> for #struct_name {
-            fn into_diag(self, env: &mut #env_generic) -> ::prism_diag::Diag {
-                ::prism_diag::Diag {
-                    title: #title.to_string(),
-                    id: #diag_id.to_string(),
-                    groups: vec![
-                        #(#groups),*
-                    ]
-                }
-            }
+        ::prism_diag::explain::Explanation {
+            id: #id,
+            explanation: #explanation,
         }
     }
-    .into()
 }
 
 fn struct_name_to_id(name: &str) -> String {
@@ -90,30 +329,44 @@ fn struct_name_to_id(name: &str) -> String {
 }
 
 struct DiagArgs {
-    title: Expr,
-    id: Option<String>,
+    title: Option<Expr>,
+    id: Option<Ident>,
     env: Option<Type>,
+    level: Option<Ident>,
 }
 
 impl Parse for DiagArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let mut title: Option<Expr> = None;
-        let mut id: Option<String> = None;
-        let mut env: Option<Type> = None;
-        loop {
+        let mut args = DiagArgs {
+            title: None,
+            id: None,
+            env: None,
+            level: None,
+        };
+        while !input.is_empty() {
             let name = input.parse::<Ident>()?;
             input.parse::<Token![=]>()?;
             match name.to_string().as_str() {
                 "title" => {
-                    title = Some(input.parse::<Expr>()?);
+                    args.title = Some(input.parse::<Expr>()?);
                 }
                 "id" => {
-                    id = Some(input.parse::<Ident>()?.to_string());
+                    args.id = Some(input.parse::<Ident>()?);
                 }
                 "env" => {
-                    env = Some(input.parse::<Type>()?);
+                    args.env = Some(input.parse::<Type>()?);
+                }
+                "level" => {
+                    args.level = Some(input.parse::<Ident>()?);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!(
+                            "Unknown argument `{name}`, expected one of `title`, `id`, `env` or `level`"
+                        ),
+                    ));
                 }
-                name => panic!("Unknown name: {name}"),
             }
             if input.is_empty() {
                 break;
@@ -121,38 +374,42 @@ impl Parse for DiagArgs {
             input.parse::<Token![,]>()?;
         }
 
-        Ok(DiagArgs {
-            title: title.expect("Expected `title` arg"),
-            id,
-            env,
-        })
+        Ok(args)
     }
 }
 
 #[derive(Default)]
 struct SuggArgs {
     label: Option<Expr>,
+    /// Whether the annotation gives context, rather than being what the diagnostic is about
+    secondary: bool,
 }
 
 impl Parse for SuggArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut args = Self::default();
 
-        if !input.is_empty() {
-            loop {
-                let name = input.parse::<Ident>()?;
-                input.parse::<Token![=]>()?;
-                match name.to_string().as_str() {
-                    "label" => {
-                        args.label = Some(input.parse::<Expr>()?);
-                    }
-                    name => panic!("Unknown name: {name}"),
+        while !input.is_empty() {
+            let name = input.parse::<Ident>()?;
+            match name.to_string().as_str() {
+                "label" => {
+                    input.parse::<Token![=]>()?;
+                    args.label = Some(input.parse::<Expr>()?);
+                }
+                "secondary" => {
+                    args.secondary = true;
                 }
-                if input.is_empty() {
-                    break;
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("Unknown argument `{name}`, expected `label` or `secondary`"),
+                    ));
                 }
-                input.parse::<Token![,]>()?;
             }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
         }
 
         Ok(args)
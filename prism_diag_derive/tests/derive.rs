@@ -0,0 +1,105 @@
+use prism_diag::explain::Explain;
+use prism_diag::{AnnotationKind, IntoDiag, Level};
+use prism_diag_derive::Diagnostic;
+use prism_input::input_table::InputTable;
+use prism_input::span::Span;
+
+/// Something happened
+#[derive(Diagnostic)]
+#[diag(title = "Something happened")]
+struct Happened {
+    #[sugg(label = "Here")]
+    span: Span,
+    #[sugg(label = "And maybe here", secondary)]
+    maybe: Option<Span>,
+}
+
+#[derive(Diagnostic)]
+#[diag(env = ())]
+enum Event {
+    /// Something was found
+    #[diag(title = format!("Found `{name}`"))]
+    Found {
+        name: String,
+        #[sugg]
+        span: Span,
+        #[sugg(label = format!("`{name}` is also here"))]
+        others: Vec<Span>,
+    },
+    /// Nothing was found
+    #[diag(title = "Found nothing", id = nothing, level = warning)]
+    #[help("Look somewhere else")]
+    Missing(#[sugg] Option<Span>),
+}
+
+fn spans() -> Vec<Span> {
+    let input_table = InputTable::default();
+    let file = input_table
+        .inner_mut()
+        .get_or_push_file("one two three".to_string(), "prism.rs".into());
+    let start = input_table.inner().start_of(file);
+    vec![
+        Span::new(start, 3),
+        Span::new(start + 4, 3),
+        Span::new(start + 8, 5),
+    ]
+}
+
+#[test]
+fn optional_suggs() {
+    let spans = spans();
+    let diag = Happened {
+        span: spans[0],
+        maybe: None,
+    }
+    .into_diag(&mut ());
+    assert_eq!(diag.id, "happened");
+    assert_eq!(diag.groups.len(), 1);
+
+    let diag = Happened {
+        span: spans[0],
+        maybe: Some(spans[1]),
+    }
+    .into_diag(&mut ());
+    assert_eq!(diag.groups.len(), 2);
+    assert_eq!(diag.groups[1].annotations[0].span, spans[1]);
+    assert_eq!(
+        diag.groups[1].annotations[0].kind,
+        AnnotationKind::Secondary
+    );
+}
+
+#[test]
+fn enum_variants() {
+    let spans = spans();
+    let diag = Event::Found {
+        name: "two".to_string(),
+        span: spans[1],
+        others: vec![spans[0], spans[2]],
+    }
+    .into_diag(&mut ());
+    assert_eq!(diag.title, "Found `two`");
+    assert_eq!(diag.id, "found");
+    assert_eq!(diag.level, Level::Error);
+    assert_eq!(diag.groups.len(), 3);
+    assert_eq!(
+        diag.groups[2].annotations[0].label.as_deref(),
+        Some("`two` is also here")
+    );
+
+    let diag = Event::Missing(None).into_diag(&mut ());
+    assert_eq!(diag.id, "nothing");
+    assert_eq!(diag.level, Level::Warning);
+    assert!(diag.groups.is_empty());
+    assert_eq!(diag.footers[0].message, "Look somewhere else");
+}
+
+#[test]
+fn explanations() {
+    assert_eq!(Happened::EXPLANATIONS.len(), 1);
+    assert_eq!(Happened::EXPLANATIONS[0].explanation, "Something happened");
+
+    let ids: Vec<_> = Event::EXPLANATIONS.iter().map(|e| e.id).collect();
+    assert_eq!(ids, ["found", "nothing"]);
+    assert_eq!(Event::EXPLANATIONS[1].explanation, "Nothing was found");
+}
@@ -0,0 +1,44 @@
+use crate::pos::Pos;
+use crate::span::Span;
+
+/// A change to a file, replacing the text in `span` by a text of `new_len` bytes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Edit {
+    pub span: Span,
+    pub new_len: usize,
+}
+
+impl Edit {
+    /// Returns the position of `pos` after the edit.
+    /// Positions in the replaced text are moved to the end of the new text.
+    pub fn shift(&self, pos: Pos) -> Pos {
+        let start = self.span.start_pos();
+        if pos.file() != start.file() || pos <= start {
+            pos
+        } else if pos >= self.span.end_pos() {
+            start + self.new_len + (pos - self.span.end_pos())
+        } else {
+            start + self.new_len
+        }
+    }
+
+    pub fn shift_span(&self, span: Span) -> Span {
+        Span::new_with_end(self.shift(span.start_pos()), self.shift(span.end_pos()))
+    }
+
+    /// Whether `pos` is strictly inside the replaced text, so it has no counterpart after the edit
+    pub fn replaces(&self, pos: Pos) -> bool {
+        pos.file() == self.span.start_pos().file()
+            && self.span.start_pos() < pos
+            && pos < self.span.end_pos()
+    }
+
+    /// Whether the edit changes any of the text from `start` up to (excluding) `end`.
+    /// Text inserted at `start` counts as a change, since it is read from `start`.
+    pub fn changes(&self, start: Pos, end: Pos) -> bool {
+        let edit_start = self.span.start_pos();
+        start.file() == edit_start.file()
+            && start < self.span.end_pos().max(edit_start + 1)
+            && edit_start < end
+    }
+}
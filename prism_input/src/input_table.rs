@@ -1,3 +1,4 @@
+use crate::edit::Edit;
 use crate::pos::Pos;
 use crate::span::Span;
 use std::fmt::Debug;
@@ -52,11 +53,31 @@ impl InputTableInner {
         &self.files[idx.0].path
     }
 
+    pub fn update_path(&mut self, idx: InputTableIndex, new_path: PathBuf) {
+        self.files[idx.0].path = new_path;
+    }
+
     pub fn update_file(&mut self, idx: InputTableIndex, new_content: String) {
         let file = &mut self.files[idx.0];
         file.source = new_content;
     }
 
+    /// Replaces the text in `span` by `text`, returning the edit to move positions in the file with.
+    /// If `span` starts or ends inside of a character, it is extended to contain the whole character.
+    pub fn edit_file(&mut self, span: Span, text: &str) -> Edit {
+        let file_idx = span.start_pos().file();
+        let file = &mut self.files[file_idx.0];
+        let start = file
+            .source
+            .floor_char_boundary(span.start_pos().idx_in_file());
+        let end = file.source.ceil_char_boundary(span.end_pos().idx_in_file());
+        file.source.replace_range(start..end, text);
+        Edit {
+            span: Span::new(Pos::start_of(file_idx) + start, end - start),
+            new_len: text.len(),
+        }
+    }
+
     pub fn remove(&mut self, idx: InputTableIndex) {
         let file = &mut self.files[idx.0];
         file.source = String::new();
@@ -98,6 +119,45 @@ impl InputTableInner {
         let col = input[last_line_start..pos.idx_in_file()].len();
         (line, col)
     }
+
+    /// Returns (line, col) of the pos, where the col is counted in UTF-16 code units as in the LSP.
+    /// Both are 0-indexed
+    pub fn line_utf16_col_of(&self, pos: Pos) -> (usize, usize) {
+        let (line, col) = self.line_col_of(pos);
+        let line_start = pos.idx_in_file() - col;
+        let col = self.get_str(pos.file())[line_start..pos.idx_in_file()]
+            .encode_utf16()
+            .count();
+        (line, col)
+    }
+
+    /// Returns the pos at the 0-indexed (line, col), where the col is counted in UTF-16 code units,
+    /// the inverse of `line_utf16_col_of`.
+    /// Positions past the end of a line or of the file are clamped to it,
+    /// positions inside of a character are moved to the start of the character.
+    pub fn pos_of_line_utf16_col(&self, idx: InputTableIndex, line: usize, col: usize) -> Pos {
+        let input = self.get_str(idx);
+
+        let line_start = if line == 0 {
+            0
+        } else {
+            input
+                .match_indices('\n')
+                .nth(line - 1)
+                .map(|(p, _)| p + '\n'.len_utf8())
+                .unwrap_or(input.len())
+        };
+        let mut utf16_col = 0;
+        let line_col = input[line_start..]
+            .char_indices()
+            .find(|&(_, c)| {
+                utf16_col += c.len_utf16();
+                c == '\n' || utf16_col > col
+            })
+            .map(|(p, _)| p)
+            .unwrap_or(input.len() - line_start);
+        Pos::start_of(idx) + line_start + line_col
+    }
 }
 
 impl InputTable {
@@ -115,3 +175,35 @@ impl InputTable {
         self.inner.write().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_columns() {
+        let input_table = InputTable::default();
+        let file = input_table
+            .inner_mut()
+            .get_or_push_file("// λx𝕏\nType".to_string(), "test.pr".into());
+        let start = input_table.inner().start_of(file);
+
+        // `λ` is two bytes and one UTF-16 code unit, `𝕏` is four bytes and two UTF-16 code units
+        let inner = input_table.inner();
+        assert_eq!(inner.pos_of_line_utf16_col(file, 0, 4), start + 5);
+        assert_eq!(inner.line_utf16_col_of(start + 5), (0, 4));
+        assert_eq!(inner.pos_of_line_utf16_col(file, 0, 5), start + 6);
+        assert_eq!(inner.line_utf16_col_of(start + 10), (0, 7));
+        assert_eq!(inner.pos_of_line_utf16_col(file, 1, 2), start + 13);
+        // Columns inside of `𝕏` and past the end of the line are clamped
+        assert_eq!(inner.pos_of_line_utf16_col(file, 0, 6), start + 6);
+        assert_eq!(inner.pos_of_line_utf16_col(file, 0, 100), start + 10);
+        drop(inner);
+
+        let edit = input_table
+            .inner_mut()
+            .edit_file(Span::new(start + 4, 0), "y");
+        assert_eq!(edit.span, Span::new(start + 3, 2));
+        assert_eq!(input_table.inner().get_str(file), "// yx𝕏\nType");
+    }
+}
@@ -1,3 +1,4 @@
+pub mod edit;
 pub mod input;
 pub mod input_table;
 pub mod pos;
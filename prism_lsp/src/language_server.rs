@@ -1,19 +1,23 @@
 use crate::{DocumentType, LspBackend, LspBackendInner, OpenDocument};
+use prism_diag::Level;
+use prism_diag::sugg::Applicability;
 use prism_input::input_table::{InputTableIndex, InputTableInner};
 use prism_input::span::Span;
 use prism_parser::core::tokens::TokenType;
+use std::collections::HashMap;
 use std::mem::take;
 use std::ops::DerefMut;
 use std::path::PathBuf;
 use tower_lsp_server::ls_types::{
-    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
-    DidCloseTextDocumentParams, DidOpenTextDocumentParams, Hover, HoverParams,
-    HoverProviderCapability, InitializeParams, InitializeResult, InitializedParams, Location,
-    MessageType, Position, Range, SemanticToken, SemanticTokenModifier, SemanticTokenType,
-    SemanticTokens, SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
-    SemanticTokensParams, SemanticTokensResult, SemanticTokensServerCapabilities,
-    ServerCapabilities, ServerInfo, TextDocumentSyncCapability, TextDocumentSyncKind,
-    TextDocumentSyncOptions, Uri,
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, Diagnostic, DiagnosticRelatedInformation,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, Hover, HoverParams, HoverProviderCapability, InitializeParams,
+    InitializeResult, InitializedParams, Location, MessageType, Position, Range, SemanticToken,
+    SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensFullOptions,
+    SemanticTokensLegend, SemanticTokensOptions, SemanticTokensParams, SemanticTokensResult,
+    SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions, TextEdit, Uri, WorkspaceEdit,
 };
 use tower_lsp_server::{Client, LanguageServer};
 
@@ -47,6 +51,7 @@ impl LanguageServer for LspBackend {
             }),
             capabilities: ServerCapabilities {
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
                         SemanticTokensOptions {
@@ -98,7 +103,7 @@ impl LanguageServer for LspBackend {
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
                     TextDocumentSyncOptions {
                         open_close: Some(true),
-                        change: Some(TextDocumentSyncKind::FULL), //TODO switch to incremental
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
                         ..Default::default()
                     },
                 )),
@@ -159,14 +164,20 @@ impl LanguageServer for LspBackend {
         let mut inner = self.inner.write().await;
         let index = inner.documents[&doc.uri].index;
 
-        let Ok([change]): std::result::Result<[_; 1], _> = params.content_changes.try_into() else {
-            panic!()
-        };
-        assert!(change.range.is_none());
-        assert!(change.range_length.is_none());
-
-        inner.db.update_file(index, change.text);
+        // The changes are applied in order, each range is in the text after the previous changes
+        for change in params.content_changes {
+            match change.range {
+                Some(range) => {
+                    let span =
+                        LspBackendInner::range_to_span(&inner.db.input.inner(), index, range);
+                    inner.db.edit_file(span, &change.text);
+                }
+                None => inner.db.update_file(index, change.text),
+            }
+        }
         inner.document_parses.remove(&index);
+        // Forget the values of the previous version of the file and of the files that depend on it
+        inner.db.collect_garbage();
 
         inner.process(index, doc.uri, &self.client).await;
     }
@@ -180,7 +191,37 @@ impl LanguageServer for LspBackend {
 
         let mut inner = self.inner.write().await;
         let doc = inner.documents.remove(&doc.uri).unwrap();
+        inner.document_code_actions.remove(&doc.index);
         inner.db.remove_file(doc.index);
+        inner.db.collect_garbage();
+    }
+
+    async fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> tower_lsp_server::jsonrpc::Result<Option<CodeActionResponse>> {
+        let inner = self.inner.read().await;
+        let Some(doc) = inner.documents.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        let Some(code_actions) = inner.document_code_actions.get(&doc.index) else {
+            return Ok(None);
+        };
+
+        // Offer the fixes of the diagnostics that overlap the requested range
+        let range = params.range;
+        let actions = code_actions
+            .iter()
+            .filter(|action| {
+                action
+                    .diagnostics
+                    .iter()
+                    .flatten()
+                    .any(|diag| diag.range.start <= range.end && range.start <= diag.range.end)
+            })
+            .map(|action| CodeActionOrCommand::CodeAction(action.clone()))
+            .collect();
+        Ok(Some(actions))
     }
 
     async fn hover(
@@ -229,7 +270,7 @@ impl LanguageServer for LspBackend {
                 }
 
                 // Convert span to LSP token info
-                let (cur_line, cur_start) = file_inner.line_col_of(token.span.start_pos());
+                let (cur_line, cur_start) = file_inner.line_utf16_col_of(token.span.start_pos());
                 let token_type = match token.token_type {
                     TokenType::Layout => 0,
                     TokenType::CharClass => 1,
@@ -248,7 +289,7 @@ impl LanguageServer for LspBackend {
                     } else {
                         cur_start
                     } as u32,
-                    length: token.span.len() as u32,
+                    length: file_inner.slice(token.span).encode_utf16().count() as u32,
                     token_type,
                     token_modifiers_bitset: 0,
                 });
@@ -292,6 +333,7 @@ impl LspBackendInner {
 
         // Update diagnostics
         let mut lsp_diags = Vec::new();
+        let mut code_actions = Vec::new();
         {
             let input = self.db.input.inner();
             for diag in diags {
@@ -320,13 +362,45 @@ impl LspBackendInner {
                     })
                     .collect();
 
-                lsp_diags.push(Diagnostic {
+                // The footers are shown below the title
+                let mut message = diag.title;
+                for footer in &diag.footers {
+                    message.push_str(&format!("\n{}: {}", footer.level, footer.message));
+                }
+
+                let lsp_diag = Diagnostic {
                     range: Self::span_to_range(&input, first_span),
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    message: diag.title,
+                    severity: Some(match diag.level {
+                        Level::Error => DiagnosticSeverity::ERROR,
+                        Level::Warning => DiagnosticSeverity::WARNING,
+                        Level::Note => DiagnosticSeverity::INFORMATION,
+                        Level::Help => DiagnosticSeverity::HINT,
+                    }),
+                    message,
                     related_information: Some(related_information),
                     ..Diagnostic::default()
-                });
+                };
+
+                for sugg in diag.suggestions {
+                    let edit = TextEdit {
+                        range: Self::span_to_range(&input, sugg.span),
+                        new_text: sugg.replacement,
+                    };
+                    let sugg_uri =
+                        Uri::from_file_path(input.get_path(sugg.span.start_pos().file())).unwrap();
+                    code_actions.push(CodeAction {
+                        title: sugg.message,
+                        kind: Some(CodeActionKind::QUICKFIX),
+                        diagnostics: Some(vec![lsp_diag.clone()]),
+                        edit: Some(WorkspaceEdit {
+                            changes: Some(HashMap::from([(sugg_uri, vec![edit])])),
+                            ..WorkspaceEdit::default()
+                        }),
+                        is_preferred: Some(sugg.applicability == Applicability::MachineApplicable),
+                        ..CodeAction::default()
+                    });
+                }
+                lsp_diags.push(lsp_diag);
             }
         }
 
@@ -346,11 +420,19 @@ impl LspBackendInner {
 
         // Store document parse
         self.document_parses.insert(index, tokens);
+        self.document_code_actions.insert(index, code_actions);
+    }
+
+    fn range_to_span(input: &InputTableInner, index: InputTableIndex, range: Range) -> Span {
+        let pos_of = |position: Position| {
+            input.pos_of_line_utf16_col(index, position.line as usize, position.character as usize)
+        };
+        Span::new_with_end(pos_of(range.start), pos_of(range.end))
     }
 
     fn span_to_range(input: &InputTableInner, span: Span) -> Range {
-        let (start_line, start_char) = input.line_col_of(span.start_pos());
-        let (end_line, end_char) = input.line_col_of(span.end_pos());
+        let (start_line, start_char) = input.line_utf16_col_of(span.start_pos());
+        let (end_line, end_char) = input.line_utf16_col_of(span.end_pos());
         Range {
             start: Position {
                 line: start_line as u32,
@@ -29,6 +29,8 @@ struct LspBackendInner {
     db: PrismDb,
     documents: HashMap<Uri, OpenDocument>,
     document_parses: HashMap<InputTableIndex, Arc<Tokens>>,
+    /// The fixes suggested by the diagnostics of each document, as of when it was last processed
+    document_code_actions: HashMap<InputTableIndex, Vec<CodeAction>>,
 }
 
 #[derive(Copy, Clone)]
@@ -5,9 +5,11 @@ use crate::grammar::rule::Rule;
 use crate::grammar::rule_block::RuleBlock;
 use crate::parsable::parsed::ArcExt;
 use crate::parser::VarMap;
+use prism_diag_derive::Diagnostic;
 use prism_input::input::Input;
 use prism_input::input_table::InputTable;
 use prism_input::pos::Pos;
+use prism_input::span::Span;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::sync::Arc;
@@ -32,10 +34,50 @@ impl Default for GrammarState {
     }
 }
 
-#[derive(Debug)]
+/// An error in adapting a grammar, after which parsing continues with the grammar as it was
+#[derive(Diagnostic, Debug, Clone, Eq, PartialEq)]
 pub enum AdaptError {
-    InvalidRuleMutation(Input),
-    SamePos(Pos),
+    /// A group that is adapted does not exist in the rule, or is adapted out of order.
+    ///
+    /// The groups of a rule are ordered, from the group that binds the loosest to the group that binds the tightest.
+    /// The groups that are adapted should be given in the same order as they appear in the rule,
+    /// new groups are inserted between them in the order they are given.
+    /// For example, `statement` comes before `fntype` in the `expr` rule, so they can't be adapted the other way around:
+    ///
+    /// ```prism
+    /// adapt grammar {
+    ///     adapt rule expr {
+    ///         adapt group fntype {
+    ///             x <- "%" x:#this;
+    ///         }
+    ///         adapt group statement {
+    ///             x <- "$" x:#this;
+    ///         }
+    ///     }
+    /// };
+    /// Type
+    /// ```
+    ///
+    /// The rest of the file is parsed as if the grammar was not adapted.
+    #[diag(title = format!("Group `{group}` of rule `{rule}` can't be adapted"))]
+    #[help("Groups should be adapted in the order in which they appear in the rule")]
+    InvalidRuleMutation {
+        rule: Input,
+        group: Input,
+        #[sugg(
+            label = "This group does not exist in the rule, or comes before a group adapted above it"
+        )]
+        span: Option<Span>,
+    },
+    /// A grammar is adapted at the same position as the grammar it adapts.
+    ///
+    /// This happens when a rule of an adapted grammar adapts the grammar again before consuming any input,
+    /// which would otherwise never end. The `adapt` fails to parse instead.
+    #[diag(title = "The grammar is adapted again at the same position")]
+    SamePos {
+        #[sugg]
+        span: Span,
+    },
 }
 
 impl GrammarState {
@@ -60,7 +102,9 @@ impl GrammarState {
             && let Some(last_mut_pos) = self.last_mut_pos
             && pos == last_mut_pos
         {
-            return Err(AdaptError::SamePos(pos));
+            return Err(AdaptError::SamePos {
+                span: pos.span_to(pos),
+            });
         }
 
         // Create a new ruleid or find an existing rule id for each rule that is adopted
@@ -95,7 +139,13 @@ impl GrammarState {
             new_rules[id.0] = Arc::new(
                 new_rules[id.0]
                     .update(rule, new_ctx.clone(), input_table)
-                    .map_err(|_| AdaptError::InvalidRuleMutation(rule.name.clone()))?,
+                    .map_err(|UpdateError::ToposortCycle(block)| {
+                        AdaptError::InvalidRuleMutation {
+                            rule: rule.name.clone(),
+                            group: block.name.clone(),
+                            span: block.span,
+                        }
+                    })?,
             );
         }
 
@@ -137,7 +187,8 @@ pub struct RuleState {
 }
 
 pub enum UpdateError {
-    ToposortCycle,
+    /// The block could not be matched to a block of the rule, since it does not exist or is out of order
+    ToposortCycle(Arc<RuleBlock>),
 }
 
 impl RuleState {
@@ -175,7 +226,7 @@ impl RuleState {
             loop {
                 // If the matching block can't be found, it must've already occurred, and therefore we have a cycle
                 let Some(old_block) = old_iter.next() else {
-                    return Err(UpdateError::ToposortCycle);
+                    return Err(UpdateError::ToposortCycle(new_block.clone()));
                 };
                 // If this is not the matching block, add it and continue searching
                 if old_block.name.as_str(input_table) != new_block.name.as_str(input_table) {
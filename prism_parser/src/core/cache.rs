@@ -4,11 +4,15 @@ use crate::core::context::{PV, ParserContext};
 use crate::core::presult::PResult;
 use crate::core::presult::PResult::{PErr, POk};
 use crate::core::state::ParserState;
+use crate::core::tokens::Tokens;
 use crate::error::error_label::ErrorLabel;
 use crate::error::{ParseError, err_combine_opt};
 use crate::parser::VarMap;
+use prism_input::edit::Edit;
 use prism_input::pos::Pos;
+use std::collections::HashMap;
 use std::hash::{DefaultHasher, Hasher};
+use std::mem;
 use std::sync::Arc;
 
 #[derive(Eq, PartialEq, Hash, Clone, Debug)]
@@ -25,17 +29,179 @@ pub type CacheVal<E> = PResult<PV, E>;
 pub struct ParserCacheEntry<PR> {
     pub read: bool,
     pub value: PR,
+    pub deps: ParseDeps,
+}
+
+/// What a parse depends on, which decides whether its cached result can be reused after an edit
+#[derive(Copy, Clone, Debug)]
+pub struct ParseDeps {
+    /// The end of the input that was examined, including the input that was only looked ahead at
+    pub examined: Pos,
+    /// Whether the parse changed the environment, which would not happen again if the result was reused
+    pub effects: bool,
+    /// Whether the result stores spans that can't be shifted, so it can't be reused after an edit before it
+    pub stores_spans: bool,
+}
+
+impl ParseDeps {
+    pub fn new(pos: Pos) -> Self {
+        Self {
+            examined: pos,
+            effects: false,
+            stores_spans: false,
+        }
+    }
+
+    /// Marks the input up to `end` as examined
+    pub fn examine_to(&mut self, end: Pos) {
+        if end.file() != self.examined.file() || end > self.examined {
+            self.examined = end;
+        }
+    }
+
+    pub fn merge(&mut self, other: ParseDeps) {
+        self.examine_to(other.examined);
+        self.effects |= other.effects;
+        self.stores_spans |= other.stores_spans;
+    }
+}
+
+/// The results of previous parses, these are kept between parses of the same file by an `IncrementalParser`
+pub struct ParserCache<E: ParseError> {
+    entries: HashMap<CacheKey, ParserCacheEntry<CacheVal<E>>>,
+    stack: Vec<CacheKey>,
+}
+
+impl<E: ParseError> Default for ParserCache<E> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+}
+
+impl<E: ParseError> ParserCache<E> {
+    pub(crate) fn is_read(&self, key: &CacheKey) -> Option<bool> {
+        self.entries.get(key).map(|v| v.read)
+    }
+
+    pub(crate) fn get(&mut self, key: &CacheKey) -> Option<&mut ParserCacheEntry<CacheVal<E>>> {
+        self.entries.get_mut(key)
+    }
+
+    pub(crate) fn insert(&mut self, key: CacheKey, value: CacheVal<E>, deps: ParseDeps) {
+        self.entries.insert(
+            key.clone(),
+            ParserCacheEntry {
+                read: false,
+                value,
+                deps,
+            },
+        );
+        self.stack.push(key);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub(crate) fn revert(&mut self, state: usize) {
+        self.stack.drain(state..).for_each(|key| {
+            self.entries.remove(&key);
+        })
+    }
+
+    /// Makes the entries inserted since `state` depend on `deps` as well
+    pub(crate) fn merge_deps_since(&mut self, state: usize, deps: ParseDeps) {
+        for key in &self.stack[state..] {
+            if let Some(entry) = self.entries.get_mut(key) {
+                entry.deps.merge(deps);
+            }
+        }
+    }
+
+    /// Keeps the entries that can be reused by a later parse.
+    /// These had no effects, and were parsed by the `root` grammar without rule arguments,
+    /// since the pointers to other grammars and to arguments are not valid after the parse that created them.
+    pub fn retain_reusable(&mut self, root: GrammarStateId) {
+        let no_args = DefaultHasher::new().finish() as usize;
+        self.stack.clear();
+        self.entries.retain(|key, entry| {
+            key.state == root && key.rule_args == no_args && !entry.deps.effects
+        });
+    }
+
+    /// Keeps the entries that are still valid after `edit`, and moves them to their new positions.
+    /// These didn't examine any input that the edit changes, and don't store spans that would be moved.
+    pub fn edit(&mut self, edit: &Edit) {
+        let mut shifted_tokens = HashMap::new();
+        self.entries = mem::take(&mut self.entries)
+            .into_iter()
+            .filter(|(key, entry)| {
+                let moves_spans = entry.deps.stores_spans && edit.shift(key.pos) != key.pos;
+                !edit.changes(key.pos, entry.deps.examined)
+                    && !moves_spans
+                    && !key
+                        .ctx
+                        .recovery_points
+                        .iter()
+                        .any(|(&from, &to)| edit.replaces(from) || edit.replaces(to))
+            })
+            .map(|(mut key, mut entry)| {
+                key.pos = edit.shift(key.pos);
+                key.ctx.recovery_points = key
+                    .ctx
+                    .recovery_points
+                    .iter()
+                    .map(|(&from, &to)| (edit.shift(from), edit.shift(to)))
+                    .collect();
+                entry.value = shift_value(entry.value, edit, &mut shifted_tokens);
+                entry.deps.examined = edit.shift(entry.deps.examined);
+                (key, entry)
+            })
+            .collect();
+    }
+}
+
+fn shift_value<E: ParseError>(
+    value: CacheVal<E>,
+    edit: &Edit,
+    shifted_tokens: &mut HashMap<*const Tokens, Arc<Tokens>>,
+) -> CacheVal<E> {
+    let shift_err = |(mut err, pos): (E, Pos)| {
+        err.shift(edit);
+        (err, edit.shift(pos))
+    };
+    match value {
+        POk {
+            obj,
+            start,
+            end,
+            best_err,
+        } => POk {
+            obj: PV::new_from(obj.parsed, obj.tokens.shift(edit, shifted_tokens)),
+            start: edit.shift(start),
+            end: edit.shift(end),
+            best_err: best_err.map(shift_err),
+        },
+        PErr { err, end } => {
+            let (err, end) = shift_err((err, end));
+            PErr { err, end }
+        }
+    }
 }
 
 impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
     pub fn parse_cache_recurse(
         &mut self,
-        mut sub: impl FnMut(&mut ParserState<Db, E>, Pos) -> PResult<PV, E>,
+        mut sub: impl FnMut(&mut ParserState<Db, E>, Pos, &mut Db) -> PResult<PV, E>,
         blocks: BorrowedArcSlice<Arc<BlockState>>,
         rule_args: &VarMap,
         grammar_state: GrammarStateId,
         pos_start: Pos,
         context: &ParserContext,
+        penv: &mut Db,
     ) -> PResult<PV, E> {
         //Check if this result is cached
         let mut args_hash = DefaultHasher::new();
@@ -56,6 +222,10 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
             return cached.clone();
         }
 
+        //Keep track of what this parse depends on, separately from the parse that called it
+        let outer_deps = mem::replace(&mut self.deps, ParseDeps::new(pos_start));
+        let effects = self.effect_count(penv);
+
         //Before executing, put a value for the current position in the cache.
         //This value is used if the rule is left-recursive
         let res_recursive = PResult::new_err(E::new(pos_start), pos_start);
@@ -70,8 +240,9 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
         //- Try to parse the current (rule, position). If this fails, there is definitely no left recursion. Otherwise, we now have a seed.
         //- Put the new seed in the cache, and rerun on the current (rule, position). Make sure to revert the cache to the previous state.
         //- At some point, the above will fail. Either because no new input is parsed, or because the entire parse now failed. At this point, we have reached the maximum size.
-        let res = sub(self, pos_start);
-        match res {
+        let res = sub(self, pos_start, penv);
+        self.deps.effects |= self.effect_count(penv) != effects;
+        let res = match res {
             POk {
                 obj: mut o,
                 start: mut spos,
@@ -105,7 +276,8 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                         );
 
                         //Grow the seed
-                        let new_res = sub(self, pos_start);
+                        let new_res = sub(self, pos_start, penv);
+                        self.deps.effects |= self.effect_count(penv) != effects;
                         match new_res {
                             POk {
                                 obj: new_o,
@@ -139,6 +311,9 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
 
                     //The seed is at its maximum size
                     //It should still be in the cache,
+                    //together with the entries that were parsed using it in the last attempt to grow it.
+                    //Whether these are still valid depends on all input that was read to grow the seed.
+                    self.cache.merge_deps_since(cache_state, self.deps);
                     POk {
                         obj: o,
                         start: spos,
@@ -151,6 +326,10 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                 self.cache_insert(key, res.clone());
                 res
             }
-        }
+        };
+
+        let deps = mem::replace(&mut self.deps, outer_deps);
+        self.deps.merge(deps);
+        res
     }
 }
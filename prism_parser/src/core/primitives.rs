@@ -11,6 +11,7 @@ use std::sync::Arc;
 
 impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
     pub fn parse_char(&mut self, f: impl Fn(&char) -> bool, pos: Pos) -> PResult<(Span, char), E> {
+        self.examine(pos);
         match pos.next(&self.input) {
             // We can parse the character
             (pos_new, Some((span, e))) if f(&e) => PResult::new_ok((span, e), pos, pos_new),
@@ -23,6 +24,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
     pub fn parse_lit(&mut self, lit: &str, start_pos: Pos) -> PResult<(), E> {
         let mut pos = start_pos;
         for char in lit.chars() {
+            self.examine(pos);
             match pos.next(&self.input) {
                 // Literal still matches
                 (pos_new, Some((_, c))) if c == char => {
@@ -37,6 +39,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
     }
 
     pub fn parse_end(&mut self, pos: Pos) -> PResult<PV, E> {
+        self.examine(pos);
         match pos.next(&self.input) {
             (_, Some(_)) => PResult::new_err(E::new(pos), pos),
             (s, None) => PResult::new_empty(PV::new_multi(Arc::new(Void).to_parsed(), vec![]), s),
@@ -1,39 +1,52 @@
-use crate::core::cache::{CacheKey, CacheVal, ParserCacheEntry};
+use crate::core::adaptive::AdaptError;
+use crate::core::cache::{CacheKey, CacheVal, ParseDeps, ParserCache};
 use crate::error::ParseError;
 use crate::parsable::parsable_dyn::ParsableDyn;
 use crate::parser::placeholder_store::PlaceholderStore;
 use prism_input::input_table::InputTable;
+use prism_input::pos::Pos;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 pub struct ParserState<Db, E: ParseError> {
     // Cache for parser_cache_recurse
-    cache: HashMap<CacheKey, ParserCacheEntry<CacheVal<E>>>,
-    cache_stack: Vec<CacheKey>,
+    pub(crate) cache: ParserCache<E>,
+    /// What the parse that is currently being cached depends on
+    pub(crate) deps: ParseDeps,
     pub input: Arc<InputTable>,
 
     pub parsables: HashMap<&'static str, ParsableDyn<Db>>,
     pub placeholders: PlaceholderStore<Db>,
+
+    /// Errors in adapting grammars, each reported once even if the `adapt` is parsed multiple times
+    pub(crate) adapt_errors: Vec<AdaptError>,
+    /// Counts the changes that parsing made to the environment,
+    /// parses that change it are not reused by an `IncrementalParser`
+    pub effects: fn(&Db) -> usize,
 }
 
 impl<Db, E: ParseError> ParserState<Db, E> {
     pub fn new(input: Arc<InputTable>, parsables: HashMap<&'static str, ParsableDyn<Db>>) -> Self {
         ParserState {
-            cache: HashMap::new(),
-            cache_stack: Vec::new(),
+            cache: ParserCache::default(),
+            deps: ParseDeps::new(Pos::dummy()),
             input,
             parsables,
             placeholders: Default::default(),
+            adapt_errors: Vec::new(),
+            effects: |_| 0,
         }
     }
 
     pub(crate) fn cache_is_read(&self, key: CacheKey) -> Option<bool> {
-        self.cache.get(&key).map(|v| v.read)
+        self.cache.is_read(&key)
     }
 
     pub(crate) fn cache_get(&mut self, key: &CacheKey) -> Option<&CacheVal<E>> {
-        if let Some(v) = self.cache.get_mut(key) {
+        if let Some(v) = self.cache.get(key) {
             v.read = true;
+            // Using a cached result depends on everything that result depends on
+            self.deps.merge(v.deps);
             Some(&v.value)
         } else {
             None
@@ -41,18 +54,34 @@ impl<Db, E: ParseError> ParserState<Db, E> {
     }
 
     pub(crate) fn cache_insert(&mut self, key: CacheKey, value: CacheVal<E>) {
-        self.cache
-            .insert(key.clone(), ParserCacheEntry { read: false, value });
-        self.cache_stack.push(key);
+        self.cache.insert(key, value, self.deps);
     }
 
     pub(crate) fn cache_state_get(&self) -> usize {
-        self.cache_stack.len()
+        self.cache.len()
+    }
+
+    pub(crate) fn push_adapt_error(&mut self, error: AdaptError) {
+        if !self.adapt_errors.contains(&error) {
+            self.adapt_errors.push(error);
+        }
     }
 
     pub(crate) fn cache_state_revert(&mut self, state: usize) {
-        self.cache_stack.drain(state..).for_each(|key| {
-            self.cache.remove(&key);
-        })
+        self.cache.revert(state)
+    }
+
+    /// Marks the input at `pos` as examined by the current parse
+    pub(crate) fn examine(&mut self, pos: Pos) {
+        self.deps.examine_to(pos + 1);
+    }
+
+    /// Marks the current parse as storing spans, see `Parsable::STORES_SPANS`
+    pub(crate) fn store_spans(&mut self) {
+        self.deps.stores_spans = true;
+    }
+
+    pub(crate) fn effect_count(&self, penv: &Db) -> usize {
+        (self.effects)(penv) + self.adapt_errors.len()
     }
 }
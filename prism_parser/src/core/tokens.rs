@@ -1,5 +1,7 @@
+use prism_input::edit::Edit;
 use prism_input::span::Span;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use std::sync::Arc;
@@ -82,4 +84,27 @@ impl Tokens {
         insert_tokens(self, &mut tokens);
         tokens
     }
+
+    /// Returns the tokens with their spans moved to where they are after `edit`.
+    /// Tokens are shared between the results of many parses, `shifted` keeps track of the tokens that were already shifted.
+    pub fn shift(
+        self: &Arc<Self>,
+        edit: &Edit,
+        shifted: &mut HashMap<*const Tokens, Arc<Tokens>>,
+    ) -> Arc<Tokens> {
+        if let Some(tokens) = shifted.get(&Arc::as_ptr(self)) {
+            return tokens.clone();
+        }
+        let tokens = Arc::new(match &**self {
+            Tokens::Single(token) => Tokens::Single(Token {
+                token_type: token.token_type,
+                span: edit.shift_span(token.span),
+            }),
+            Tokens::Multi(tokens) => {
+                Tokens::Multi(tokens.iter().map(|t| t.shift(edit, shifted)).collect())
+            }
+        });
+        shifted.insert(Arc::as_ptr(self), tokens.clone());
+        tokens
+    }
 }
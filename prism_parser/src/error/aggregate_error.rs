@@ -1,27 +1,41 @@
+use crate::core::adaptive::AdaptError;
 use crate::error::ParseError;
 use crate::error::error_label::ErrorLabel;
-use prism_diag::RenderConfig;
+use prism_diag::{Diag, IntoDiag, RenderConfig};
 use prism_input::input_table::InputTable;
 use std::io;
 
 #[must_use]
 pub struct AggregatedParseError<E: ParseError<L = ErrorLabel>> {
     pub errors: Vec<E>,
+    pub adapt_errors: Vec<AdaptError>,
 }
 
 impl<E: ParseError<L = ErrorLabel>> AggregatedParseError<E> {
+    /// Returns the diagnostics of all errors, the adapt errors first
+    pub fn diags(&self) -> impl Iterator<Item = Diag> + '_ {
+        self.adapt_errors
+            .iter()
+            .map(|e| e.clone().into_diag(&mut ()))
+            .chain(self.errors.iter().map(E::diag))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.adapt_errors.is_empty()
+    }
+
     pub fn eprint(&self, input: &InputTable) -> io::Result<()> {
-        for e in &self.errors {
+        for diag in self.diags() {
             eprintln!(
                 "{}\n",
-                e.diag().render(&RenderConfig::default(), &input.inner())
+                diag.render(&RenderConfig::default(), &input.inner())
             );
         }
         Ok(())
     }
 
     pub fn unwrap_or_eprint(&self, input: &InputTable) {
-        if self.errors.is_empty() {
+        if self.is_empty() {
             return;
         }
         self.eprint(input).unwrap();
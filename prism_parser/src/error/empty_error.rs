@@ -1,6 +1,7 @@
 use crate::error::ParseError;
 use crate::error::error_label::ErrorLabel;
 use prism_diag::Diag;
+use prism_input::edit::Edit;
 use prism_input::pos::Pos;
 use prism_input::span::Span;
 
@@ -29,6 +30,8 @@ impl ParseError for EmptyError {
 
     fn set_end(&mut self, _: Pos) {}
 
+    fn shift(&mut self, _: &Edit) {}
+
     fn diag(&self) -> Diag {
         unreachable!()
     }
@@ -1,3 +1,4 @@
+use prism_input::edit::Edit;
 use prism_input::span::Span;
 use std::fmt::{Display, Formatter};
 
@@ -14,6 +15,13 @@ impl ErrorLabel {
             ErrorLabel::Literal(s, _) => *s,
         }
     }
+
+    pub(crate) fn shift(&self, edit: &Edit) -> Self {
+        match self {
+            ErrorLabel::Explicit(s, l) => ErrorLabel::Explicit(edit.shift_span(*s), l.clone()),
+            ErrorLabel::Literal(s, l) => ErrorLabel::Literal(edit.shift_span(*s), l.clone()),
+        }
+    }
 }
 
 impl Display for ErrorLabel {
@@ -5,6 +5,7 @@ pub mod set_error;
 pub mod tree_error;
 
 use prism_diag::Diag;
+use prism_input::edit::Edit;
 use prism_input::pos::Pos;
 use prism_input::span::Span;
 use std::cmp::Ordering;
@@ -18,6 +19,8 @@ pub trait ParseError: Sized + Clone {
     fn merge(self, other: Self) -> Self;
     fn span(&self) -> Span;
     fn set_end(&mut self, end: Pos);
+    /// Moves the positions of the error to where they are after `edit`
+    fn shift(&mut self, edit: &Edit);
     fn diag(&self) -> Diag;
 }
 
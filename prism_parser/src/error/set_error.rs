@@ -1,6 +1,8 @@
 use crate::error::ParseError;
 use crate::error::error_label::ErrorLabel;
-use prism_diag::{Annotation, AnnotationGroup, Diag};
+use prism_diag::explain::{Explain, Explanation};
+use prism_diag::{Annotation, AnnotationGroup, AnnotationKind, Diag, Level};
+use prism_input::edit::Edit;
 use prism_input::pos::Pos;
 use prism_input::span::Span;
 use std::cmp::max;
@@ -14,6 +16,21 @@ pub struct SetError {
     pub explicit: bool,
 }
 
+impl Explain for SetError {
+    const EXPLANATIONS: &'static [Explanation] = &[Explanation {
+        id: "parser",
+        explanation: "\
+The input could not be parsed.
+
+The error points at the furthest position that the parser reached, and lists what it expected there.
+For example, a `let` should be followed by a `;` and the rest of the program:
+
+```prism
+let x = Type
+```",
+    }];
+}
+
 impl ParseError for SetError {
     type L = ErrorLabel;
 
@@ -63,35 +80,48 @@ impl ParseError for SetError {
         self.span = Span::new_with_end(self.span.start_pos(), end);
     }
 
+    fn shift(&mut self, edit: &Edit) {
+        self.span = edit.shift_span(self.span);
+        self.labels = self.labels.iter().map(|l| l.shift(edit)).collect();
+    }
+
     fn diag(&self) -> Diag {
-        let mut labels_map: HashMap<Pos, Vec<_>> = HashMap::new();
+        let mut labels_map: HashMap<Pos, Vec<String>> = HashMap::new();
         for l in self.labels.iter() {
-            labels_map.entry(l.span().start_pos()).or_default().push(l);
+            labels_map
+                .entry(l.span().start_pos())
+                .or_default()
+                .push(l.to_string());
         }
 
+        // Sort the labels, so the error is the same every time
+        let mut labels_map: Vec<_> = labels_map.into_iter().collect();
+        labels_map.sort_by_key(|(start, _)| start.idx_in_file());
+
         Diag {
+            level: Level::Error,
             title: "Parsing failed".into(),
-            id: "parser".into(),
+            id: Self::EXPLANATIONS[0].id.into(),
             groups: vec![AnnotationGroup {
                 annotations: labels_map
                     .into_iter()
-                    .map(|(start, labels)| Annotation {
-                        span: start.span_to(start),
-                        label: Some(match labels[..] {
-                            [] => unreachable!(),
-                            [label] => format!("Expected: {}", label),
-                            ref labels => format!(
-                                "Expected one of: {}",
-                                labels
-                                    .iter()
-                                    .map(|v| v.to_string())
-                                    .collect::<Vec<_>>()
-                                    .join(" ")
-                            ),
-                        }),
+                    .map(|(start, mut labels)| {
+                        labels.sort();
+                        labels.dedup();
+                        Annotation {
+                            span: start.span_to(start),
+                            label: Some(match &labels[..] {
+                                [] => unreachable!(),
+                                [label] => format!("Expected: {label}"),
+                                labels => format!("Expected one of: {}", labels.join(" ")),
+                            }),
+                            kind: AnnotationKind::Primary,
+                        }
                     })
                     .collect(),
             }],
+            footers: vec![],
+            suggestions: vec![],
         }
     }
 }
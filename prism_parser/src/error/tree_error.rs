@@ -1,6 +1,7 @@
 use crate::error::ParseError;
 use crate::error::error_label::ErrorLabel;
 use prism_diag::Diag;
+use prism_input::edit::Edit;
 use prism_input::pos::Pos;
 use prism_input::span::Span;
 use std::cmp::max;
@@ -47,6 +48,15 @@ impl<L: Eq + Hash + Clone> ErrorTree<L> {
     }
 }
 
+impl ErrorTree<ErrorLabel> {
+    fn shift(&mut self, edit: &Edit) {
+        if let Some(l) = &mut self.0 {
+            *l = l.shift(edit);
+        }
+        self.1.iter_mut().for_each(|t| t.shift(edit));
+    }
+}
+
 /// ErrorTree keeps track of all information that it is provided, it is really verbose
 #[derive(Clone)]
 pub struct TreeError {
@@ -101,6 +111,11 @@ impl ParseError for TreeError {
         self.span = Span::new_with_end(self.span.start_pos(), end);
     }
 
+    fn shift(&mut self, edit: &Edit) {
+        self.span = edit.shift_span(self.span);
+        self.labels.shift(edit);
+    }
+
     fn diag(&self) -> Diag {
         todo!()
         // let mut report: ReportBuilder<Span> = base_report(self.span);
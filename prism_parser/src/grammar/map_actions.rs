@@ -35,6 +35,7 @@ impl RuleBlock {
 
             name: self.name.clone(),
             adapt: self.adapt,
+            span: self.span,
         })
     }
 }
@@ -82,10 +83,16 @@ impl RuleExpr {
             RuleExpr::SliceInput(expr) => RuleExpr::SliceInput(expr.map_actions(map)),
             RuleExpr::PosLookahead(expr) => RuleExpr::PosLookahead(expr.map_actions(map)),
             RuleExpr::NegLookahead(expr) => RuleExpr::NegLookahead(expr.map_actions(map)),
-            RuleExpr::AtAdapt { ns, name, expr } => RuleExpr::AtAdapt {
+            RuleExpr::AtAdapt {
+                ns,
+                name,
+                expr,
+                span,
+            } => RuleExpr::AtAdapt {
                 ns: ns.clone(),
                 name: name.clone(),
                 expr: expr.map_actions(map),
+                span: *span,
             },
             RuleExpr::CharClass(_) | RuleExpr::Literal(_) => return self.clone(),
         })
@@ -96,14 +103,21 @@ impl RuleAction {
     pub fn map_actions(self: &Arc<Self>, map: &impl Fn(&Parsed) -> Parsed) -> Arc<Self> {
         Arc::new(match &**self {
             RuleAction::Name(..) | &RuleAction::InputLiteral(..) => return self.clone(),
-            RuleAction::Construct { ns, name, args } => RuleAction::Construct {
+            RuleAction::Construct {
+                ns,
+                name,
+                args,
+                span,
+            } => RuleAction::Construct {
                 ns: ns.clone(),
                 name: name.clone(),
                 args: alloc_extend(args.iter().map(|r| r.map_actions(map))),
+                span: *span,
             },
-            RuleAction::Value { ns, value } => RuleAction::Value {
+            RuleAction::Value { ns, value, span } => RuleAction::Value {
                 ns: ns.clone(),
                 value: map(value),
+                span: *span,
             },
         })
     }
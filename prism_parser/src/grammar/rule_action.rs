@@ -11,25 +11,40 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum RuleAction {
-    Name(Input),
-    InputLiteral(Input),
+    Name(Input, #[serde(skip, default = "Span::dummy")] Span),
+    InputLiteral(Input, #[serde(skip, default = "Span::dummy")] Span),
     Construct {
         ns: Input,
         name: Input,
         args: Arc<[Arc<Self>]>,
+        #[serde(skip, default = "Span::dummy")]
+        span: Span,
     },
     #[serde(skip)]
     Value {
         ns: Input,
         value: Parsed,
+        span: Span,
     },
 }
 
+impl RuleAction {
+    /// The span of this action in the grammar, this is not known for the meta grammar
+    pub fn span(&self) -> Span {
+        match self {
+            RuleAction::Name(_, span)
+            | RuleAction::InputLiteral(_, span)
+            | RuleAction::Construct { span, .. }
+            | RuleAction::Value { span, .. } => *span,
+        }
+    }
+}
+
 impl<Db> Parsable<Db> for RuleAction {
     type EvalCtx = ();
 
     fn from_construct(
-        _span: Span,
+        span: Span,
         constructor: &str,
         args: &[Parsed],
         _env: &mut Db,
@@ -48,20 +63,23 @@ impl<Db> Parsable<Db> for RuleAction {
                             .map(|((), v)| v)
                             .map(|sub| sub.value_cloned::<RuleAction>()),
                     ),
+                    span,
                 }
             }
-            "InputLiteral" => {
-                RuleAction::InputLiteral(args[0].value_ref::<Input>().parse_escaped_string(input))
-            }
+            "InputLiteral" => RuleAction::InputLiteral(
+                args[0].value_ref::<Input>().parse_escaped_string(input),
+                span,
+            ),
             "Name" => {
                 let parsed = &args[0];
-                RuleAction::Name(parsed.value_ref::<Input>().clone())
+                RuleAction::Name(parsed.value_ref::<Input>().clone(), span)
             }
             "Value" => {
                 let parsed = &args[0];
                 RuleAction::Value {
                     ns: parsed.value_ref::<Input>().clone(),
                     value: args[1].clone(),
+                    span,
                 }
             }
             _ => unreachable!(),
@@ -14,13 +14,17 @@ pub struct RuleBlock {
     pub name: Input,
     pub adapt: bool,
     pub constructors: Arc<[Arc<AnnotatedRuleExpr>]>,
+    /// Where the block is defined, if it was parsed from source rather than deserialized
+    #[serde(skip)]
+    pub span: Option<Span>,
 }
 
 impl<Db> Parsable<Db> for RuleBlock {
     type EvalCtx = ();
+    const STORES_SPANS: bool = true;
 
     fn from_construct(
-        _span: Span,
+        span: Span,
         constructor: &str,
         args: &[Parsed],
         _env: &mut Db,
@@ -38,6 +42,7 @@ impl<Db> Parsable<Db> for RuleBlock {
                     .map(|((), v)| v)
                     .map(|c| c.value_cloned::<AnnotatedRuleExpr>()),
             ),
+            span: Some(span),
         }
     }
 }
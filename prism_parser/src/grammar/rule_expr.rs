@@ -38,6 +38,9 @@ pub enum RuleExpr {
         name: Input,
 
         expr: Arc<Self>,
+        /// The span of the `#adapt` in the grammar, this is not known for the meta grammar
+        #[serde(skip, default = "Span::dummy")]
+        span: Span,
     },
 }
 
@@ -45,7 +48,7 @@ impl<Db> Parsable<Db> for RuleExpr {
     type EvalCtx = ();
 
     fn from_construct(
-        _span: Span,
+        span: Span,
         constructor: &str,
         args: &[Parsed],
         _env: &mut Db,
@@ -106,6 +109,7 @@ impl<Db> Parsable<Db> for RuleExpr {
                     ns: args[0].value_ref::<Input>().clone(),
                     name: parsed.value_ref::<Input>().clone(),
                     expr: args[2].value_cloned::<RuleExpr>(),
+                    span,
                 }
             }
             _ => unreachable!(),
@@ -14,6 +14,7 @@ pub struct ActionResult {
 
 impl<Db> Parsable<Db> for ActionResult {
     type EvalCtx = ();
+    const STORES_SPANS: bool = true;
 
     fn from_construct(
         span: Span,
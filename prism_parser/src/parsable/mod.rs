@@ -18,6 +18,10 @@ pub mod void;
 pub trait Parsable<Db>: Sized + Sync + Send + Any {
     type EvalCtx: Default + Clone + Send + Sync + Any;
 
+    /// Whether the values store spans of the input.
+    /// These spans can't be moved when the input is edited, so an `IncrementalParser` doesn't reuse them after an edit before them.
+    const STORES_SPANS: bool = false;
+
     fn from_construct(
         _span: Span,
         constructor: &str,
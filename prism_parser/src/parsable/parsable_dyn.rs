@@ -34,6 +34,8 @@ pub struct ParsableDyn<Db> {
         src: &InputTable,
         env: &mut Db,
     ) -> Arc<GrammarFile>,
+
+    pub stores_spans: bool,
 }
 
 impl<Db> Clone for ParsableDyn<Db> {
@@ -50,6 +52,7 @@ impl<Db> ParsableDyn<Db> {
             from_construct: from_construct_dyn::<Db, P>,
             create_eval_ctx: create_eval_ctx_dyn::<Db, P>,
             eval_to_grammar: eval_to_grammar_dyn::<Db, P>,
+            stores_spans: P::STORES_SPANS,
         }
     }
 }
@@ -23,7 +23,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
         eval_ctxs: &mut HashMap<String, (Parsed, ParsedPlaceholder)>,
     ) {
         match rule {
-            RuleAction::Name(n) => {
+            RuleAction::Name(n, _) => {
                 let n = n.as_str(&self.input);
                 if eval_ctxs.contains_key(n.as_ref()) {
                     // If ctx is void, ignore
@@ -42,6 +42,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                 ns: namespace,
                 name: constructor,
                 args,
+                ..
             } => {
                 let namespace = namespace.as_str(&self.input);
 
@@ -88,7 +89,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                     self.pre_apply_action(arg, penv, *placeholder, &env, eval_ctxs);
                 }
             }
-            RuleAction::InputLiteral(lit) => {
+            RuleAction::InputLiteral(lit, _) => {
                 let parsed = Arc::new(lit.clone()).to_parsed();
                 self.placeholders
                     .place_into_empty(placeholder, parsed, penv, &self.input);
@@ -100,30 +101,33 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
     }
 
     pub fn apply_action(
-        &self,
+        &mut self,
         rule: &RuleAction,
         span: Span,
         vars: &VarMap,
         penv: &mut Db,
     ) -> Parsed {
         match rule {
-            RuleAction::Name(name) => {
+            RuleAction::Name(name, _) => {
                 if let Some(ar) = vars.get(name.as_str(&self.input).as_ref()) {
                     ar.clone()
                 } else {
                     panic!("Name '{}' not in context", name.as_str(&self.input))
                 }
             }
-            RuleAction::InputLiteral(lit) => Arc::new(lit.clone()).to_parsed(),
-            RuleAction::Construct { ns, name, args } => {
+            RuleAction::InputLiteral(lit, _) => Arc::new(lit.clone()).to_parsed(),
+            RuleAction::Construct { ns, name, args, .. } => {
                 let ns = ns.as_str(&self.input);
 
-                let ns = self
+                let ns = *self
                     .parsables
                     .get(ns.as_ref())
                     .unwrap_or_else(|| panic!("Namespace '{ns}' exists"));
                 let args_vals =
                     alloc_extend(args.iter().map(|a| self.apply_action(a, span, vars, penv)));
+                if ns.stores_spans {
+                    self.store_spans();
+                }
                 (ns.from_construct)(
                     span,
                     name.as_str(&self.input).as_ref(),
@@ -132,13 +136,16 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                     &self.input,
                 )
             }
-            RuleAction::Value { ns, value } => {
+            RuleAction::Value { ns, value, .. } => {
                 let ns = ns.as_str(&self.input);
 
-                let ns = self
+                let ns = *self
                     .parsables
                     .get(ns.as_ref())
                     .unwrap_or_else(|| panic!("Namespace '{ns}' exists"));
+                if ns.stores_spans {
+                    self.store_spans();
+                }
                 (ns.from_construct)(
                     span,
                     "EnvCapture",
@@ -1,5 +1,6 @@
 use crate::META_GRAMMAR;
 use crate::core::adaptive::{AdaptError, GrammarState, RuleId};
+use crate::core::cache::ParserCache;
 
 use crate::core::context::{PV, ParserContext};
 use crate::core::state::ParserState;
@@ -22,6 +23,7 @@ use crate::parsable::parsed::ArcExt;
 use crate::parsable::void::Void;
 use crate::parser::VarMap;
 use crate::parser::parsed_list::ParsedList;
+use prism_input::edit::Edit;
 use prism_input::input_table::{InputTable, InputTableIndex};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -37,47 +39,12 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserInstance<Db, E> {
     pub fn new(
         input: Arc<InputTable>,
         from: &GrammarFile,
-        mut parsables: HashMap<&'static str, ParsableDyn<Db>>,
+        parsables: HashMap<&'static str, ParsableDyn<Db>>,
     ) -> Result<Self, AdaptError> {
-        parsables.insert("ActionResult", ParsableDyn::new::<ActionResult>());
-        parsables.insert("ParsedList", ParsableDyn::new::<ParsedList>());
-        parsables.insert("RuleAction", ParsableDyn::new::<RuleAction>());
-        parsables.insert("CharClass", ParsableDyn::new::<CharClass>());
-        parsables.insert("CharClassRange", ParsableDyn::new::<CharClassRange>());
-        parsables.insert("RuleAnnotation", ParsableDyn::new::<RuleAnnotation>());
-        parsables.insert("RuleExpr", ParsableDyn::new::<RuleExpr>());
-        parsables.insert("AnnotatedRuleExpr", ParsableDyn::new::<AnnotatedRuleExpr>());
-        parsables.insert("RuleBlock", ParsableDyn::new::<RuleBlock>());
-        parsables.insert("Rule", ParsableDyn::new::<Rule>());
-        parsables.insert("GrammarFile", ParsableDyn::new::<GrammarFile>());
-        parsables.insert("OptionU64", ParsableDyn::new::<Option<u64>>());
-
-        let state = ParserState::new(input, parsables);
-
-        let (grammar_state, meta_vars) = GrammarState::new_with(&META_GRAMMAR, &state.input);
-        let visible_rules = VarMap::from_iter([
-            (
-                "grammar".to_string(),
-                meta_vars
-                    .get("grammar")
-                    .expect("Meta grammar contains 'grammar' rule")
-                    .clone(),
-            ),
-            (
-                "prule_action".to_string(),
-                meta_vars
-                    .get("prule_action")
-                    .expect("Meta grammar contains 'prule_action' rule")
-                    .clone(),
-            ),
-        ]);
-
-        let (grammar_state, rules) =
-            grammar_state.adapt_with(from, &visible_rules, None, &state.input)?;
-
+        let (grammar_state, rules) = root_grammar(from, &input)?;
         Ok(Self {
-            state,
-            grammar_state: Arc::new(grammar_state),
+            state: ParserState::new(input, with_builtin_parsables(parsables)),
+            grammar_state,
             rules,
         })
     }
@@ -90,44 +57,165 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserInstance<Db, E> {
         file: InputTableIndex,
         penv: &mut Db,
     ) -> (PV, AggregatedParseError<E>) {
-        let rule = *self
-            .rules
-            .get(rule)
-            .as_ref()
-            .expect("Rule exists")
-            .value_ref::<RuleId>();
-
-        let (pv, errors) = self.state.parse_with_recovery(
-            |state, ctx, penv| {
-                let file_start = state.input.inner().start_of(file);
-                let result = state.parse_rule(
-                    &self.grammar_state,
-                    rule,
-                    &[],
-                    file_start,
-                    ctx,
-                    penv,
-                    &Arc::new(Void).to_parsed(),
-                );
-                let end_pos = result.end_pos();
-                result
-                    .merge_seq(state.parse_end_with_layout(
-                        &self.grammar_state,
-                        &self.rules,
-                        end_pos,
-                        &ParserContext::new(),
-                        penv,
-                    ))
-                    .map(|(o, lo)| PV::new_multi(o.parsed, vec![o.tokens, lo.tokens]))
-            },
+        run_rule(
+            &mut self.state,
+            &self.grammar_state,
+            &self.rules,
+            rule,
+            file,
+            penv,
+        )
+    }
+}
+
+/// Parses a file multiple times, reusing the results of the previous parse that are not changed by the edits since.
+/// Unlike a `ParserInstance` this does not depend on the parser environment, so it can be kept in the environment.
+pub struct IncrementalParser<E: ParseError<L = ErrorLabel>> {
+    cache: ParserCache<E>,
+
+    grammar_state: Arc<GrammarState>,
+    rules: VarMap,
+}
+
+impl<E: ParseError<L = ErrorLabel>> IncrementalParser<E> {
+    pub fn new(input: &InputTable, from: &GrammarFile) -> Result<Self, AdaptError> {
+        let (grammar_state, rules) = root_grammar(from, input)?;
+        Ok(Self {
+            cache: ParserCache::default(),
+            grammar_state,
+            rules,
+        })
+    }
+
+    /// Parses `file`, `effects` counts the changes that parsing makes to `penv`, see `ParserState::effects`
+    pub fn run<Db>(
+        &mut self,
+        rule: &'static str,
+        input: Arc<InputTable>,
+        file: InputTableIndex,
+        parsables: HashMap<&'static str, ParsableDyn<Db>>,
+        penv: &mut Db,
+        effects: fn(&Db) -> usize,
+    ) -> (PV, AggregatedParseError<E>) {
+        let mut state = ParserState::new(input, with_builtin_parsables(parsables));
+        state.cache = std::mem::take(&mut self.cache);
+        state.effects = effects;
+
+        let result = run_rule(
+            &mut state,
+            &self.grammar_state,
+            &self.rules,
+            rule,
             file,
             penv,
         );
+        self.cache = state.cache;
+        self.cache.retain_reusable(self.grammar_state.unique_id());
+        result
+    }
 
-        (pv, AggregatedParseError { errors })
+    /// Forgets the results of the previous parse that are changed by `edit`, and moves the others to their new positions.
+    /// `edit` should already be applied to the input.
+    pub fn edit(&mut self, edit: &Edit) {
+        self.cache.edit(edit);
     }
 }
 
+fn with_builtin_parsables<Db>(
+    mut parsables: HashMap<&'static str, ParsableDyn<Db>>,
+) -> HashMap<&'static str, ParsableDyn<Db>> {
+    parsables.insert("ActionResult", ParsableDyn::new::<ActionResult>());
+    parsables.insert("ParsedList", ParsableDyn::new::<ParsedList>());
+    parsables.insert("RuleAction", ParsableDyn::new::<RuleAction>());
+    parsables.insert("CharClass", ParsableDyn::new::<CharClass>());
+    parsables.insert("CharClassRange", ParsableDyn::new::<CharClassRange>());
+    parsables.insert("RuleAnnotation", ParsableDyn::new::<RuleAnnotation>());
+    parsables.insert("RuleExpr", ParsableDyn::new::<RuleExpr>());
+    parsables.insert("AnnotatedRuleExpr", ParsableDyn::new::<AnnotatedRuleExpr>());
+    parsables.insert("RuleBlock", ParsableDyn::new::<RuleBlock>());
+    parsables.insert("Rule", ParsableDyn::new::<Rule>());
+    parsables.insert("GrammarFile", ParsableDyn::new::<GrammarFile>());
+    parsables.insert("OptionU64", ParsableDyn::new::<Option<u64>>());
+    parsables
+}
+
+/// Creates the grammar state of `from`, in which the meta grammar rules `grammar` and `prule_action` are visible
+fn root_grammar(
+    from: &GrammarFile,
+    input: &InputTable,
+) -> Result<(Arc<GrammarState>, VarMap), AdaptError> {
+    let (grammar_state, meta_vars) = GrammarState::new_with(&META_GRAMMAR, input);
+    let visible_rules = VarMap::from_iter([
+        (
+            "grammar".to_string(),
+            meta_vars
+                .get("grammar")
+                .expect("Meta grammar contains 'grammar' rule")
+                .clone(),
+        ),
+        (
+            "prule_action".to_string(),
+            meta_vars
+                .get("prule_action")
+                .expect("Meta grammar contains 'prule_action' rule")
+                .clone(),
+        ),
+    ]);
+
+    let (grammar_state, rules) = grammar_state.adapt_with(from, &visible_rules, None, input)?;
+    Ok((Arc::new(grammar_state), rules))
+}
+
+fn run_rule<Db, E: ParseError<L = ErrorLabel>>(
+    state: &mut ParserState<Db, E>,
+    grammar_state: &GrammarState,
+    rules: &VarMap,
+    rule: &'static str,
+    file: InputTableIndex,
+    penv: &mut Db,
+) -> (PV, AggregatedParseError<E>) {
+    let rule = *rules
+        .get(rule)
+        .as_ref()
+        .expect("Rule exists")
+        .value_ref::<RuleId>();
+
+    let (pv, errors) = state.parse_with_recovery(
+        |state, ctx, penv| {
+            let file_start = state.input.inner().start_of(file);
+            let result = state.parse_rule(
+                grammar_state,
+                rule,
+                &[],
+                file_start,
+                ctx,
+                penv,
+                &Arc::new(Void).to_parsed(),
+            );
+            let end_pos = result.end_pos();
+            result
+                .merge_seq(state.parse_end_with_layout(
+                    grammar_state,
+                    rules,
+                    end_pos,
+                    &ParserContext::new(),
+                    penv,
+                ))
+                .map(|(o, lo)| PV::new_multi(o.parsed, vec![o.tokens, lo.tokens]))
+        },
+        file,
+        penv,
+    );
+
+    (
+        pv,
+        AggregatedParseError {
+            errors,
+            adapt_errors: std::mem::take(&mut state.adapt_errors),
+        },
+    )
+}
+
 pub fn run_parser_rule_raw<Db, E: ParseError<L = ErrorLabel>>(
     rules: &GrammarFile,
     rule: &'static str,
@@ -137,8 +225,17 @@ pub fn run_parser_rule_raw<Db, E: ParseError<L = ErrorLabel>>(
     parsables: HashMap<&'static str, ParsableDyn<Db>>,
     penv: &mut Db,
 ) -> (PV, AggregatedParseError<E>) {
-    let mut instance: ParserInstance<Db, E> = ParserInstance::new(input, rules, parsables).unwrap();
-    instance.run(rule, file, penv)
+    match ParserInstance::new(input, rules, parsables) {
+        Ok(mut instance) => instance.run(rule, file, penv),
+        // If the grammar can't be constructed, nothing can be parsed
+        Err(err) => (
+            PV::new_multi(Arc::new(Void).to_parsed(), vec![]),
+            AggregatedParseError {
+                errors: vec![],
+                adapt_errors: vec![err],
+            },
+        ),
+    }
 }
 
 pub fn run_parser_rule<Db, P: Parsable<Db>, E: ParseError<L = ErrorLabel>>(
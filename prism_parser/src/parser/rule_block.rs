@@ -25,7 +25,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
         eval_ctx: &Parsed,
     ) -> PResult<PV, E> {
         self.parse_cache_recurse(
-            |state, pos| {
+            |state, pos, penv| {
                 state.parse_sub_blocks(rules, blocks, rule_args, pos, context, penv, eval_ctx)
             },
             blocks,
@@ -33,6 +33,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
             rules.unique_id(),
             pos,
             context,
+            penv,
         )
     }
 
@@ -167,6 +168,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                                 .map_with_span(|pv, span| PV::new_single(pv.parsed, *token, span));
 
                             // Add error label
+                            state.examine(res.end_pos());
                             res.add_label_explicit(ErrorLabel::Explicit(
                                 pos.span_to(res.end_pos().next(&state.input).0),
                                 token.to_string(),
@@ -1,4 +1,4 @@
-use crate::core::adaptive::{BlockState, GrammarState, RuleId};
+use crate::core::adaptive::{AdaptError, BlockState, GrammarState, RuleId};
 use crate::core::arc_ref::BorrowedArcSlice;
 use crate::core::context::{PR, PV, ParserContext};
 use crate::core::presult::PResult;
@@ -433,6 +433,7 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                 ns,
                 name: grammar,
                 expr: body,
+                ..
             } => {
                 let ns = ns.as_str(&self.input);
 
@@ -447,21 +448,31 @@ impl<Db, E: ParseError<L = ErrorLabel>> ParserState<Db, E> {
                 // Create new grammarstate
                 //TODO performance: we shoud cache grammar states
                 //TODO this should not use `vars`, but instead the global scope in which this rule is defined
-                let (rules, _) = match rules.adapt_with(&grammar, vars, Some(pos), &self.input) {
-                    Ok(rules) => rules,
-                    Err(_) => {
+                let adapted;
+                let rules = match rules.adapt_with(&grammar, vars, Some(pos), &self.input) {
+                    Ok((new_rules, _)) => {
+                        adapted = new_rules;
+                        &adapted
+                    }
+                    // Adapting again at this position would never end, so this fails to parse
+                    Err(err @ AdaptError::SamePos { .. }) => {
+                        self.push_adapt_error(err);
                         let mut e = E::new(pos);
                         e.add_label_implicit(ErrorLabel::Explicit(
                             pos.span_to(pos),
-                            "language grammar to be correct, but adaptation created cycle in block order.".to_string(),
+                            "grammar to be adapted at most once at each position".to_string(),
                         ));
                         return PResult::new_err(e, pos);
                     }
+                    // Continue parsing without adapting the grammar
+                    Err(err @ AdaptError::InvalidRuleMutation { .. }) => {
+                        self.push_adapt_error(err);
+                        rules
+                    }
                 };
-                let rules: Arc<GrammarState> = Arc::new(rules);
 
                 self.parse_expr(
-                    body, &rules, blocks, rule_args, vars, pos, context, penv, eval_ctx, eval_ctxs,
+                    body, rules, blocks, rule_args, vars, pos, context, penv, eval_ctx, eval_ctxs,
                 )
             }
         }
@@ -0,0 +1,201 @@
+use prism_diag::RenderConfig;
+use prism_input::input_table::{InputTable, InputTableIndex};
+use prism_input::span::Span;
+use prism_parser::core::context::PV;
+use prism_parser::error::ParseError;
+use prism_parser::error::aggregate_error::AggregatedParseError;
+use prism_parser::error::set_error::SetError;
+use prism_parser::grammar::grammar_file::GrammarFile;
+use prism_parser::parsable::Parsable;
+use prism_parser::parsable::parsable_dyn::ParsableDyn;
+use prism_parser::parsable::parsed::Parsed;
+use prism_parser::parse_grammar;
+use prism_parser::parser::instance::{IncrementalParser, run_parser_rule_raw};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const GRAMMAR: &str = r#"
+    rule start = stmts;
+
+    rule stmts {
+        Cons(s, r) <- s:stmt ";" r:stmts;
+        Nil() <- "";
+    }
+
+    rule stmt {
+        group add {
+            Add(x, y) <- x:#this "+" y:#next;
+        }
+        group base {
+            Let(v) <- "let" #neg("t") v:#this;
+            Num(n) <- n:#str(['0'-'9']+);
+            Var(v) <- v:#str(['a'-'z']+);
+        }
+    }
+
+    rule layout = [' ' | '\n'];
+"#;
+
+/// A parsed value, which unlike an `ActionResult` doesn't store its span, so it can be moved by an edit
+struct Node {
+    constructor: String,
+    args: Vec<Parsed>,
+}
+
+impl Parsable<()> for Node {
+    type EvalCtx = ();
+
+    fn from_construct(
+        _span: Span,
+        constructor: &str,
+        args: &[Parsed],
+        _env: &mut (),
+        _input: &InputTable,
+    ) -> Self {
+        Self {
+            constructor: constructor.to_string(),
+            args: args.to_vec(),
+        }
+    }
+}
+
+fn show(parsed: &Parsed) -> String {
+    match parsed.try_value_ref::<Node>() {
+        Some(node) => format!(
+            "{}({})",
+            node.constructor,
+            node.args.iter().map(show).collect::<Vec<_>>().join(", ")
+        ),
+        None => format!("{parsed:?}"),
+    }
+}
+
+fn parsables() -> HashMap<&'static str, ParsableDyn<()>> {
+    HashMap::from([("", ParsableDyn::new::<Node>())])
+}
+
+/// Renders everything a parse results in, so an incremental parse can be compared to a fresh one
+fn render(input_table: &InputTable, (pv, errs): &(PV, AggregatedParseError<SetError>)) -> String {
+    let errs = errs
+        .errors
+        .iter()
+        .map(|e| {
+            e.diag()
+                .render(&RenderConfig::default(), &input_table.inner())
+        })
+        .collect::<Vec<_>>();
+    format!("{}\n{:?}\n{errs:?}", show(&pv.parsed), pv.tokens.to_vec())
+}
+
+fn parse_incremental(
+    parser: &mut IncrementalParser<SetError>,
+    input_table: &Arc<InputTable>,
+    file: InputTableIndex,
+) -> (PV, AggregatedParseError<SetError>) {
+    parser.run(
+        "start",
+        input_table.clone(),
+        file,
+        parsables(),
+        &mut (),
+        |_| 0,
+    )
+}
+
+fn parse_fresh(
+    grammar: &GrammarFile,
+    input_table: &Arc<InputTable>,
+    file: InputTableIndex,
+) -> (PV, AggregatedParseError<SetError>) {
+    run_parser_rule_raw(
+        grammar,
+        "start",
+        input_table.clone(),
+        file,
+        parsables(),
+        &mut (),
+    )
+}
+
+/// The statements of a parsed list of statements
+fn stmts(pv: &PV) -> Vec<&Parsed> {
+    let mut stmts = vec![];
+    let mut list = pv.parsed.value_ref::<Node>();
+    while let [stmt, rest] = &list.args[..] {
+        stmts.push(stmt);
+        list = rest.value_ref::<Node>();
+    }
+    stmts
+}
+
+/// Which statement of the previous parse is expected to be reused after an edit
+#[derive(Copy, Clone)]
+enum Reuses {
+    First,
+    Last,
+    Nothing,
+}
+
+#[test]
+fn incremental() {
+    let (input_table, grammar, _, errs) = parse_grammar::<SetError>(GRAMMAR);
+    errs.unwrap_or_eprint(&input_table);
+
+    let file = input_table
+        .inner_mut()
+        .get_or_push_file("1 + 2;\nlet x + 4 + 5;\n6;\n".into(), "test_file".into());
+    let mut parser = IncrementalParser::new(&input_table, &grammar).unwrap();
+    let mut previous = parse_incremental(&mut parser, &input_table, file);
+
+    // Each edit is given as (start, len, text), in the text after the previous edits
+    let edits: &[(usize, usize, &str, Reuses)] = &[
+        // Grow the left-recursive expression in the middle
+        (15, 1, "40 + 41", Reuses::First),
+        // Insert at the very end
+        (31, 0, "y;", Reuses::First),
+        // Extend a statement
+        (29, 0, " + 7", Reuses::First),
+        // Turn a keyword into a variable, and back
+        (10, 0, "t", Reuses::First),
+        (10, 1, "", Reuses::First),
+        // Remove a separator, causing an error, and put it back
+        (5, 1, "", Reuses::Nothing),
+        (5, 0, ";", Reuses::Nothing),
+        // Edit the first statement
+        (0, 1, "100", Reuses::Last),
+        // Insert before everything
+        (0, 0, "z;\n", Reuses::Last),
+        // Remove everything
+        (0, 42, "", Reuses::Nothing),
+        // Complete a left-recursive expression that could not be grown further,
+        // the expression depends on the input that was read in the last attempt to grow it
+        (0, 0, "1 + 2 + ;", Reuses::Nothing),
+        (8, 0, "3", Reuses::Nothing),
+    ];
+    for &(start, len, text, reuses) in edits {
+        let span = Span::new(input_table.inner().start_of(file) + start, len);
+        let edit = input_table.inner_mut().edit_file(span, text);
+        parser.edit(&edit);
+
+        let got = parse_incremental(&mut parser, &input_table, file);
+        let expected = parse_fresh(&grammar, &input_table, file);
+        assert_eq!(
+            render(&input_table, &expected),
+            render(&input_table, &got),
+            "after replacing {span:?} by {text:?} in {:?}",
+            input_table.inner().get_str(file)
+        );
+
+        match reuses {
+            Reuses::First => {
+                assert_eq!(stmts(&previous.0)[0].as_ptr(), stmts(&got.0)[0].as_ptr())
+            }
+            Reuses::Last => assert_eq!(
+                stmts(&previous.0).last().unwrap().as_ptr(),
+                stmts(&got.0).last().unwrap().as_ptr()
+            ),
+            Reuses::Nothing => {}
+        }
+        previous = got;
+    }
+}
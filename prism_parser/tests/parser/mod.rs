@@ -1,5 +1,6 @@
 mod adaptive;
 mod arithmetic;
+mod incremental;
 mod infinite;
 mod lambda;
 mod layout;